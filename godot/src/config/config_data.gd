@@ -356,6 +356,7 @@ func save_to_settings_file():
 	new_settings_file.set_value("config", "audio_voice_chat_volume", self.audio_voice_chat_volume)
 	new_settings_file.set_value("config", "audio_mic_amplification", self.audio_mic_amplification)
 	new_settings_file.set_value("config", "texture_quality", self.get_texture_quality())
+	new_settings_file.set_value("config", "data_saver", self.get_data_saver())
 	new_settings_file.set_value("session", "account", self.session_account)
 	new_settings_file.set_value("session", "guest_profile", self.guest_profile)
 	new_settings_file.set_value("user", "last_parcel_position", self.last_parcel_position)
@@ -9,19 +9,7 @@ func apply_audio_props(action_on_playing: bool):
 	if not valid:
 		return
 
-	self.pitch_scale = dcl_pitch
-
-	if dcl_global:
-		attenuation_model = AudioStreamPlayer3D.ATTENUATION_DISABLED
-	else:
-		attenuation_model = AudioStreamPlayer3D.ATTENUATION_INVERSE_DISTANCE
-
-	if not dcl_enable:
-		self.volume_db = -80
-	else:
-		# TODO: Check if it should be 10 instead 20 to talk in terms of power
-		self.volume_db = 20 * log(dcl_volume)
-		# -80 = 20 log 0.00001, so muted is when (volume <= 0.00001)
+	apply_spatial_props()
 
 	if action_on_playing:
 		if self.playing and not dcl_playing:
@@ -44,7 +32,7 @@ func _async_refresh_data(time_specified: bool):
 
 		var audio_clip_file_hash = content_mapping.get_hash(last_loaded_audio_clip)
 		if audio_clip_file_hash.is_empty():
-			# TODO: log file not found
+			Global.content_provider.report_scene_missing_file(dcl_scene_id, last_loaded_audio_clip)
 			return
 
 		var promise: Promise = Global.content_provider.fetch_audio(
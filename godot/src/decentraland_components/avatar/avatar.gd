@@ -22,12 +22,6 @@ var wearables_by_category: Dictionary = {}
 
 var emote_controller: AvatarEmoteController
 
-var generate_attach_points: bool = false
-var right_hand_idx: int = -1
-var right_hand_position: Transform3D
-var left_hand_idx: int = -1
-var left_hand_position: Transform3D
-
 var voice_chat_audio_player: AudioStreamPlayer = null
 var voice_chat_audio_player_gen: AudioStreamGenerator = null
 
@@ -59,7 +53,6 @@ func _ready():
 	label_3d_name.billboard = billboard_mode
 
 	emote_controller = AvatarEmoteController.new(self, animation_player, animation_tree)
-	body_shape_skeleton_3d.bone_pose_changed.connect(self._attach_point_bone_pose_changed)
 
 	avatar_modifier_area_detector.set_avatar_modifier_area.connect(
 		self._on_set_avatar_modifier_area
@@ -250,8 +243,6 @@ func try_to_set_body_shape(body_shape_hash):
 		new_child.add_child(body_shape.get_node("ResourceLocker").duplicate())
 		body_shape_skeleton_3d.add_child(new_child)
 
-	_add_attach_points()
-
 
 func apply_unshaded_mode(node_to_apply: Node):
 	if node_to_apply is MeshInstance3D:
@@ -510,33 +501,6 @@ func push_voice_frame(frame):
 	timer_hide_mic.start()
 
 
-func activate_attach_points():
-	generate_attach_points = true
-	_add_attach_points()
-
-
-func _add_attach_points():
-	if not generate_attach_points:
-		return
-
-	if body_shape_skeleton_3d == null:
-		return
-
-	right_hand_idx = body_shape_skeleton_3d.find_bone("Avatar_RightHand")
-	left_hand_idx = body_shape_skeleton_3d.find_bone("Avatar_LeftHand")
-
-
-func _attach_point_bone_pose_changed(bone_idx: int):
-	match bone_idx:
-		left_hand_idx:
-			left_hand_position = body_shape_skeleton_3d.get_bone_global_pose(bone_idx)
-			left_hand_position.basis = left_hand_position.basis.scaled(100.0 * Vector3.ONE)
-
-		right_hand_idx:
-			right_hand_position = body_shape_skeleton_3d.get_bone_global_pose(bone_idx)
-			right_hand_position.basis = right_hand_position.basis.scaled(100.0 * Vector3.ONE)
-
-
 func _on_timer_hide_mic_timeout():
 	sprite_3d_mic_enabled.hide()
 
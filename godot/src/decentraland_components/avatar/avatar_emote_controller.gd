@@ -322,9 +322,7 @@ func play_emote_audio(file_hash: String):
 
 
 func broadcast_avatar_animation(emote_id: String) -> void:
-	# Send emote
-	var timestamp = Time.get_unix_time_from_system() * 1000
-	Global.comms.send_chat("␐%s %d" % [emote_id, timestamp])
+	Global.comms.broadcast_player_emote(emote_id)
 
 
 func freeze_on_idle():
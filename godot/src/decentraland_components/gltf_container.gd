@@ -20,6 +20,7 @@ func async_load_gltf():
 
 	self.dcl_gltf_src = dcl_gltf_src.to_lower()
 	if content_mapping.get_hash(dcl_gltf_src).is_empty():
+		Global.content_provider.report_scene_missing_file(dcl_scene_id, dcl_gltf_src)
 		dcl_gltf_loading_state = GltfContainerLoadingState.NOT_FOUND
 		timer.stop()
 		return
@@ -128,6 +128,7 @@ func _ready():
 
 	var custom_importer = load("res://src/logic/custom_gltf_importer.gd").new()
 	GLTFDocument.register_gltf_document_extension(custom_importer)
+	GLTFDocument.register_gltf_document_extension(DclGltfKtx2Extension.new())
 
 	if args.has("--raycast-debugger"):
 		set_raycast_debugger_enable(true)
@@ -138,6 +139,8 @@ func _ready():
 	else:
 		self.network_inspector.set_is_active(false)
 
+	self.comms_inspector.set_is_active(args.has("--comms-debugger"))
+
 	DclMeshRenderer.init_primitive_shapes()
 
 
@@ -67,7 +67,6 @@ func _ready():
 	camera.current = true
 
 	set_camera_mode(Global.CameraMode.THIRD_PERSON)
-	avatar.activate_attach_points()
 
 	floor_snap_length = 0.2
 
@@ -105,6 +104,9 @@ func _physics_process(dt: float) -> void:
 	direction = (transform.basis * Vector3(input_dir.x, 0, input_dir.y)).normalized()
 	current_direction = current_direction.move_toward(direction, 8 * dt)
 
+	# restrictions of the InputModifier of the current scene
+	var input_modifier: Dictionary = Global.scene_runner.get_player_input_modifier()
+
 	var on_floor = is_on_floor() or position.y <= 0.0
 	jump_time -= dt
 	if not on_floor:
@@ -112,7 +114,11 @@ func _physics_process(dt: float) -> void:
 		avatar.rise = velocity.y > .3
 		avatar.fall = velocity.y < -.3
 		velocity.y -= gravity * dt
-	elif Input.is_action_pressed("ia_jump") and jump_time < 0:
+	elif (
+		Input.is_action_pressed("ia_jump")
+		and jump_time < 0
+		and not input_modifier.disable_jump
+	):
 		velocity.y = jump_velocity_0
 		avatar.land = false
 		avatar.rise = true
@@ -126,14 +132,24 @@ func _physics_process(dt: float) -> void:
 		avatar.rise = false
 		avatar.fall = false
 
+	var walk: bool = Input.is_action_pressed("ia_walk")
+	var run: bool = Input.is_action_pressed("ia_sprint")
+	var jog: bool = not walk and not run
+	if (
+		(walk and input_modifier.disable_walk)
+		or (run and input_modifier.disable_run)
+		or (jog and input_modifier.disable_jog)
+	):
+		current_direction = Vector3.ZERO
+
 	if current_direction:
-		if Input.is_action_pressed("ia_walk"):
+		if walk:
 			avatar.walk = true
 			avatar.run = false
 			avatar.jog = false
 			velocity.x = current_direction.x * walk_speed
 			velocity.z = current_direction.z * walk_speed
-		elif Input.is_action_pressed("ia_sprint"):
+		elif run:
 			avatar.walk = false
 			avatar.run = true
 			avatar.jog = false
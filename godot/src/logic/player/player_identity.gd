@@ -95,3 +95,21 @@ func async_deploy_profile(new_profile: DclUserProfile, has_new_snapshots: bool)
 	if response is Dictionary:
 		if response.get("creationTimestamp") != null:
 			self.set_profile(new_profile)
+
+
+func async_set_user_blocked(address: String, blocked: bool) -> void:
+	var new_profile: DclUserProfile = self.get_profile_or_null()
+	if new_profile == null:
+		return
+	new_profile = new_profile.duplicated()
+	new_profile.set_blocked(address, blocked)
+	await async_deploy_profile(new_profile, false)
+
+
+func async_set_user_muted(address: String, muted: bool) -> void:
+	var new_profile: DclUserProfile = self.get_profile_or_null()
+	if new_profile == null:
+		return
+	new_profile = new_profile.duplicated()
+	new_profile.set_muted(address, muted)
+	await async_deploy_profile(new_profile, false)
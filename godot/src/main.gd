@@ -44,6 +44,11 @@ func _start():
 		get_tree().change_scene_to_file(
 			"res://src/tool/avatar_renderer/avatar_renderer_standalone.tscn"
 		)
+	elif args.has("--comms-test"):
+		print("Running in Comms Test mode")
+		var comms_test = load("res://src/test/comms_test.gd").new()
+		add_child(comms_test)
+		comms_test.start(args)
 	elif args.has("--scene-test") or args.has("--scene-renderer"):
 		print("Running in Scene Test mode")
 		Global.get_config().guest_profile = {}
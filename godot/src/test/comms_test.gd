@@ -0,0 +1,117 @@
+class_name CommsTest
+extends Node
+
+# Two explorers connected to the same room, started by `cargo run -- comms-test`
+# with the local comms server. Each peer broadcasts its position, chats, profile
+# and scene messages, and checks that it receives the ones of the other peer.
+
+const TIMEOUT_SECONDS = 60.0
+# after passing, the peer keeps sending so the other one can pass too
+const LINGER_SECONDS = 3.0
+const SEND_INTERVAL_SECONDS = 1.0
+const POSITION_INTERVAL_SECONDS = 0.1
+const SCENE_ID = "comms-test-scene"
+const POSITIONS = {"a": Vector3(4.0, 0.0, 4.0), "b": Vector3(12.0, 0.0, 12.0)}
+
+var role: String = ""
+var other_role: String = ""
+var other_address: String = ""
+
+var elapsed: float = 0.0
+var passed_at: float = -1.0
+var send_elapsed: float = 0.0
+var position_elapsed: float = 0.0
+var chat_index: int = 0
+
+var received := {"position": false, "chat": false, "profile": false, "scene_message": false}
+
+
+func start(args: PackedStringArray):
+	var role_index := args.find("--comms-test")
+	var adapter_index := args.find("--comms-adapter")
+	if role_index == -1 or role_index + 1 >= args.size() or adapter_index == -1:
+		printerr("usage: --comms-test <a|b> --comms-adapter ws-room:ws://127.0.0.1:7667")
+		exit(1)
+		return
+
+	role = args[role_index + 1]
+	if not POSITIONS.has(role):
+		printerr("comms-test role must be 'a' or 'b'")
+		exit(1)
+		return
+	other_role = "b" if role == "a" else "a"
+
+	Global.player_identity.set_default_profile()
+	Global.player_identity.create_guest_account()
+	var profile: DclUserProfile = Global.player_identity.get_profile_or_null().duplicated()
+	profile.set_name("comms-test-" + role)
+	Global.player_identity.set_profile(profile)
+
+	Global.comms.chat_message.connect(self._on_chat_message)
+	Global.comms.change_adapter(args[adapter_index + 1])
+	prints("comms-test", role, "started as", Global.player_identity.get_address_str())
+
+
+func _process(delta: float):
+	if role.is_empty():
+		return
+
+	elapsed += delta
+	_send(delta)
+	_check()
+
+	if passed_at < 0.0:
+		if not received.values().has(false):
+			passed_at = elapsed
+			prints("comms-test", role, "passed")
+		elif elapsed > TIMEOUT_SECONDS:
+			prints("comms-test", role, "failed, received:", received)
+			exit(1)
+	elif elapsed - passed_at > LINGER_SECONDS:
+		exit(0)
+
+
+func _send(delta: float):
+	position_elapsed += delta
+	if position_elapsed >= POSITION_INTERVAL_SECONDS:
+		position_elapsed = 0.0
+		Global.comms.broadcast_position_and_rotation(POSITIONS[role], Quaternion.IDENTITY)
+
+	send_elapsed += delta
+	if send_elapsed >= SEND_INTERVAL_SECONDS:
+		send_elapsed = 0.0
+		chat_index += 1
+		Global.comms.send_chat("hello from %s #%d" % [role, chat_index])
+		Global.comms.send_scene_message_bytes(SCENE_ID, role.to_utf8_buffer())
+
+
+func _check():
+	for message in Global.comms.consume_scene_messages_bytes(SCENE_ID):
+		var data: PackedByteArray = message[1]
+		if data.get_string_from_utf8() == other_role:
+			received["scene_message"] = true
+
+	if other_address.is_empty():
+		return
+
+	var avatar = Global.avatars.get_avatar_by_address(other_address)
+	if avatar == null:
+		return
+
+	if avatar.global_position.distance_to(POSITIONS[other_role]) < 0.5:
+		received["position"] = true
+	if avatar.get_avatar_name().begins_with("comms-test-" + other_role):
+		received["profile"] = true
+
+
+func _on_chat_message(chats: Array):
+	for chat in chats:
+		var message: String = chat[2]
+		if message.begins_with("hello from " + other_role):
+			other_address = chat[0]
+			received["chat"] = true
+
+
+func exit(code: int):
+	print("comms-test-exiting with code ", code)
+	Global.testing_tools.exit_gracefully(code)
@@ -47,6 +47,8 @@ func on_chats_arrived(chats: Array):
 		var address: String = chat[0]
 		# var _timestamp: float = chat[1]
 		var message: String = chat[2]
+		# "nearby", "#channel" or "@0x..." with the other party of a private message
+		var channel: String = chat[3] if chat.size() > 3 else "nearby"
 
 		var avatar = Global.avatars.get_avatar_by_address(address)
 		if avatar == null:
@@ -72,9 +74,20 @@ func on_chats_arrived(chats: Array):
 		elif message.begins_with(ACK):
 			pass  # TODO: Calculate ping
 		else:
-			var text = "[b][color=#1cc]%s[/color] > [color=#fff]%s[/color]" % [avatar_name, message]
+			var channel_tag = ""
+			if channel.begins_with("@"):
+				channel_tag = "[color=#f8f](private)[/color] "
+			elif channel.begins_with("#"):
+				channel_tag = "[color=#cc8]%s[/color] " % channel
+			var text = (
+				"[b]%s[color=#1cc]%s[/color] > [color=#fff]%s[/color]"
+				% [channel_tag, avatar_name, message]
+			)
 			add_chat_message(text)
-			UiSounds.play_sound("notification_chatmessage_public_appear")
+			if channel.begins_with("@"):
+				UiSounds.play_sound("notification_chatmessage_private_appear")
+			else:
+				UiSounds.play_sound("notification_chatmessage_public_appear")
 
 
 func _on_button_send_pressed():
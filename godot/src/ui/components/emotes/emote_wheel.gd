@@ -71,6 +71,9 @@ func _physics_process(_delta):
 func _on_play_emote(emote_urn: String):
 	self.hide()
 	Global.explorer_grab_focus()
+	if Global.scene_runner.is_player_emote_disabled():
+		return
+
 	if avatar_node != null:
 		var emote_controller = avatar_node.emote_controller
 		emote_controller.play_emote(emote_urn)
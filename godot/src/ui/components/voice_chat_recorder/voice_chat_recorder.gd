@@ -1,5 +1,8 @@
 extends VoiceChatRecorder
 
+# Same values as the VoiceChatMode of the comms
+const VOICE_CHAT_MODE_OPEN_MIC = 1
+
 var is_recording = false
 
 var is_enabled = false
@@ -19,11 +22,17 @@ func set_recording_enabled_with_sfx(enabled: bool):
 
 func _on_adapter_changed(voice_chat_enabled, _adapter_str):
 	is_enabled = voice_chat_enabled
+	is_recording = false
 	set_recording_enabled(false)
 
 
 func _physics_process(_delta):
 	if is_enabled:
-		if is_recording != Input.is_action_pressed("ia_record_mic"):
-			is_recording = Input.is_action_pressed("ia_record_mic")
+		# in open mic the voice activity detection skips the silence
+		var should_record = (
+			Global.comms.get_voice_chat_mode() == VOICE_CHAT_MODE_OPEN_MIC
+			or Input.is_action_pressed("ia_record_mic")
+		)
+		if is_recording != should_record:
+			is_recording = should_record
 			set_recording_enabled_with_sfx(is_recording)
@@ -340,6 +340,15 @@ func _on_panel_chat_submit_message(message: String):
 		elif command_str == "/reload":
 			Global.realm.async_set_realm(Global.realm.get_realm_string())
 			loading_ui.enable_loading_screen()
+		elif (command_str == "/w" or command_str == "/whisper") and params.size() > 2:
+			var target = "@" + params[1].trim_prefix("@")
+			var text = " ".join(params.slice(2))
+			if Global.comms.send_chat_to(target, text):
+				panel_chat.on_chats_arrived(
+					[[Global.player_identity.get_address_str(), 0, text, target]]
+				)
+			else:
+				panel_chat.add_chat_message("[color=#ccc]> The message couldn't be sent[/color]")
 		else:
 			pass
 			# TODO: unknown command
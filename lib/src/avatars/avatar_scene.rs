@@ -1,12 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use ethers_core::types::H160;
 use godot::prelude::*;
 
 use crate::{
     auth::wallet::AsH160,
-    avatars::dcl_user_profile::DclUserProfile,
-    comms::profile::UserProfile,
+    avatars::{dcl_user_profile::DclUserProfile, position_buffer::InterpolationSettings},
+    comms::{
+        profile::UserProfile,
+        voice_processing::{get_distance_attenuation, get_voice_chat_settings},
+    },
     dcl::{
         components::{
             internal_player_data::InternalPlayerData,
@@ -36,12 +39,25 @@ pub struct AvatarScene {
 
     // map alias to the entity_id
     avatar_entity: HashMap<AvatarAlias, SceneEntityId>,
+    // the aliases of all the rooms are allocated here, so they never collide
+    last_alias: AvatarAlias,
     avatar_godot_scene: HashMap<SceneEntityId, Gd<DclAvatar>>,
     avatar_address: HashMap<H160, AvatarAlias>,
 
     crdt_state: SceneCrdtState,
 
     last_updated_profile: HashMap<SceneEntityId, UserProfile>,
+
+    // the same emote can be received more than once
+    last_emote_incremental_id: HashMap<SceneEntityId, u32>,
+
+    // from the primary player profile
+    blocked_addresses: HashSet<H160>,
+    muted_addresses: HashSet<H160>,
+    // voice channels of blocked avatars, spawned if they are unblocked
+    pending_voice_channels: HashMap<SceneEntityId, (u32, u32, u32)>,
+
+    interpolation_settings: InterpolationSettings,
 }
 
 #[godot_api]
@@ -50,10 +66,16 @@ impl INode for AvatarScene {
         AvatarScene {
             base,
             avatar_entity: HashMap::new(),
+            last_alias: 0,
             crdt_state: SceneCrdtState::from_proto(),
             avatar_godot_scene: HashMap::new(),
             avatar_address: HashMap::new(),
             last_updated_profile: HashMap::new(),
+            last_emote_incremental_id: HashMap::new(),
+            blocked_addresses: HashSet::new(),
+            muted_addresses: HashSet::new(),
+            pending_voice_channels: HashMap::new(),
+            interpolation_settings: InterpolationSettings::default(),
         }
     }
 
@@ -89,6 +111,7 @@ impl AvatarScene {
     #[func]
     pub fn update_primary_player_profile(&mut self, profile: Gd<DclUserProfile>) {
         self.update_avatar(SceneEntityId::PLAYER, &profile.bind().inner);
+        self.update_blocked_and_muted(&profile.bind().inner);
     }
 
     #[func]
@@ -105,11 +128,60 @@ impl AvatarScene {
         };
 
         let dcl_transform = DclTransformAndParent::from_godot(&transform, Vector3::ZERO);
-        self._update_avatar_transform(&entity_id, dcl_transform);
+        self._update_avatar_transform(&entity_id, dcl_transform, None);
+    }
+
+    #[func]
+    pub fn set_position_interpolation(
+        &mut self,
+        interpolation_delay: f64,
+        max_extrapolation: f64,
+        snap_distance: f32,
+    ) {
+        self.interpolation_settings = InterpolationSettings {
+            interpolation_delay: interpolation_delay.max(0.0),
+            max_extrapolation: max_extrapolation.max(0.0),
+            snap_distance: snap_distance.max(0.0),
+            ..self.interpolation_settings.clone()
+        };
+
+        for (_, avatar) in self.avatar_godot_scene.iter_mut() {
+            avatar
+                .bind_mut()
+                .set_interpolation_settings(self.interpolation_settings.clone());
+        }
+    }
+
+    #[func]
+    pub fn get_interpolation_delay(&self) -> f64 {
+        self.interpolation_settings.interpolation_delay
+    }
+
+    #[func]
+    pub fn get_max_extrapolation(&self) -> f64 {
+        self.interpolation_settings.max_extrapolation
+    }
+
+    #[func]
+    pub fn get_snap_distance(&self) -> f32 {
+        self.interpolation_settings.snap_distance
     }
 
     #[func]
     pub fn add_avatar(&mut self, alias: u32, address: GString) {
+        // the same peer can be in the island room and in the scene room, both
+        //  aliases share the avatar
+        if let Some(entity_id) = address
+            .to_string()
+            .as_h160()
+            .and_then(|address| self.avatar_address.get(&address))
+            .and_then(|existing_alias| self.avatar_entity.get(existing_alias))
+            .cloned()
+        {
+            self.avatar_entity.insert(alias, entity_id);
+            return;
+        }
+
         // TODO: the entity Self::MAX_ENTITY_ID + 1 would be a buggy avatar
         let entity_id = self
             .get_next_entity_id()
@@ -131,7 +203,10 @@ impl AvatarScene {
 
         new_avatar
             .bind_mut()
-            .set_movement_type(AvatarMovementType::LerpTwoPoints as i32);
+            .set_movement_type(AvatarMovementType::Interpolated as i32);
+        new_avatar
+            .bind_mut()
+            .set_interpolation_settings(self.interpolation_settings.clone());
 
         let instance_id = self.base.instance_id();
         let avatar_entity_id = entity_id;
@@ -185,6 +260,9 @@ impl AvatarScene {
         new_avatar.connect("emote_triggered".into(), emote_triggered_callable);
 
         self.base.add_child(new_avatar.clone().upcast());
+        if self.is_blocked_alias(alias) {
+            new_avatar.call("set_hidden".into(), &[true.to_variant()]);
+        }
         self.avatar_godot_scene.insert(entity_id, new_avatar);
     }
 
@@ -304,6 +382,15 @@ impl AvatarScene {
     const FROM_ENTITY_ID: u16 = 32;
     const MAX_ENTITY_ID: u16 = 256;
 
+    pub fn next_alias(&mut self) -> AvatarAlias {
+        loop {
+            self.last_alias = self.last_alias.wrapping_add(1).max(1);
+            if !self.avatar_entity.contains_key(&self.last_alias) {
+                return self.last_alias;
+            }
+        }
+    }
+
     // This function is not optimized, it will iterate over all the entities but this happens only when add an player
     fn get_next_entity_id(&self) -> Result<SceneEntityId, &'static str> {
         for entity_number in Self::FROM_ENTITY_ID..Self::MAX_ENTITY_ID {
@@ -320,6 +407,8 @@ impl AvatarScene {
 
     pub fn clean(&mut self) {
         self.avatar_entity.clear();
+        self.last_emote_incremental_id.clear();
+        self.pending_voice_channels.clear();
 
         let avatars = std::mem::take(&mut self.avatar_godot_scene);
         for (_, mut avatar) in avatars {
@@ -330,12 +419,28 @@ impl AvatarScene {
 
     pub fn remove_avatar(&mut self, alias: u32) {
         if let Some(entity_id) = self.avatar_entity.remove(&alias) {
+            // still connected through another room
+            if let Some(other_alias) = self
+                .avatar_entity
+                .iter()
+                .find_map(|(other_alias, v)| (*v == entity_id).then_some(*other_alias))
+            {
+                for v in self.avatar_address.values_mut() {
+                    if *v == alias {
+                        *v = other_alias;
+                    }
+                }
+                return;
+            }
+
             self.crdt_state.kill_entity(&entity_id);
             let mut avatar = self.avatar_godot_scene.remove(&entity_id).unwrap();
 
             self.avatar_address.retain(|_, v| *v != alias);
 
             self.last_updated_profile.remove(&entity_id);
+            self.last_emote_incremental_id.remove(&entity_id);
+            self.pending_voice_channels.remove(&entity_id);
 
             avatar.queue_free();
             self.base.remove_child(avatar.upcast());
@@ -356,6 +461,7 @@ impl AvatarScene {
         &mut self,
         avatar_entity_id: &SceneEntityId,
         dcl_transform: DclTransformAndParent,
+        position_index: Option<u32>,
     ) {
         let avatar_scene = self
             .avatar_godot_scene
@@ -363,7 +469,7 @@ impl AvatarScene {
             .expect("avatar not found");
         avatar_scene
             .bind_mut()
-            .set_target_position(dcl_transform.to_godot_transform_3d());
+            .push_position_sample(position_index, dcl_transform.to_godot_transform_3d());
 
         let mut scene_runner = DclGlobal::singleton().bind().scene_runner.clone();
         let mut scene_runner = scene_runner.bind_mut();
@@ -429,7 +535,7 @@ impl AvatarScene {
             parent: SceneEntityId::ROOT,
         };
 
-        self._update_avatar_transform(&entity_id, dcl_transform);
+        self._update_avatar_transform(&entity_id, dcl_transform, Some(transform.index));
     }
 
     pub fn update_avatar_by_alias(&mut self, alias: u32, profile: &UserProfile) {
@@ -526,6 +632,80 @@ impl AvatarScene {
         }
     }
 
+    // Playing the emote emits `emote_triggered`, then the AvatarEmoteCommand is sent to the scenes
+    pub fn play_emote_by_alias(&mut self, alias: u32, incremental_id: u32, emote_urn: &str) {
+        let entity_id = if let Some(entity_id) = self.avatar_entity.get(&alias) {
+            *entity_id
+        } else {
+            // TODO: handle this condition
+            return;
+        };
+
+        if self
+            .last_emote_incremental_id
+            .insert(entity_id, incremental_id)
+            == Some(incremental_id)
+        {
+            return;
+        }
+
+        if let Some(avatar) = self.avatar_godot_scene.get_mut(&entity_id) {
+            avatar.call("async_play_emote".into(), &[emote_urn.to_variant()]);
+        }
+    }
+
+    fn get_alias_address(&self, alias: u32) -> Option<H160> {
+        let entity_id = self.avatar_entity.get(&alias)?;
+        self.avatar_address.iter().find_map(|(address, v)| {
+            (self.avatar_entity.get(v) == Some(entity_id)).then_some(*address)
+        })
+    }
+
+    fn is_blocked_alias(&self, alias: u32) -> bool {
+        self.get_alias_address(alias)
+            .map_or(false, |address| self.blocked_addresses.contains(&address))
+    }
+
+    fn is_muted_alias(&self, alias: u32) -> bool {
+        self.get_alias_address(alias)
+            .map_or(false, |address| self.muted_addresses.contains(&address))
+    }
+
+    // Blocked avatars are hidden and don't get a voice channel
+    fn update_blocked_and_muted(&mut self, profile: &UserProfile) {
+        let to_address_set = |list: &Option<Vec<String>>| -> HashSet<H160> {
+            list.iter()
+                .flatten()
+                .filter_map(|address| address.as_h160())
+                .collect()
+        };
+        self.blocked_addresses = to_address_set(&profile.content.blocked);
+        self.muted_addresses = to_address_set(&profile.content.muted);
+
+        let avatars: Vec<(H160, AvatarAlias)> = self
+            .avatar_address
+            .iter()
+            .map(|(address, alias)| (*address, *alias))
+            .collect();
+        for (address, alias) in avatars {
+            let Some(entity_id) = self.avatar_entity.get(&alias).cloned() else {
+                continue;
+            };
+            let blocked = self.blocked_addresses.contains(&address);
+            if let Some(avatar) = self.avatar_godot_scene.get_mut(&entity_id) {
+                avatar.call("set_hidden".into(), &[blocked.to_variant()]);
+            }
+
+            if !blocked {
+                if let Some((sample_rate, num_channels, samples_per_channel)) =
+                    self.pending_voice_channels.remove(&entity_id)
+                {
+                    self.spawn_voice_channel(alias, sample_rate, num_channels, samples_per_channel);
+                }
+            }
+        }
+    }
+
     pub fn spawn_voice_channel(
         &mut self,
         alias: u32,
@@ -540,6 +720,12 @@ impl AvatarScene {
             return;
         };
 
+        if self.is_blocked_alias(alias) {
+            self.pending_voice_channels
+                .insert(entity_id, (sample_rate, num_channels, samples_per_channel));
+            return;
+        }
+
         let (sample_rate, num_channels, samples_per_channel) = (
             sample_rate.to_variant(),
             num_channels.to_variant(),
@@ -560,10 +746,44 @@ impl AvatarScene {
             return;
         };
 
-        self.avatar_godot_scene
-            .get_mut(&entity_id)
-            .unwrap()
-            .call("push_voice_frame".into(), &[frame.to_variant()]);
+        // blocked and muted avatars are not heard
+        if self.is_blocked_alias(alias) || self.is_muted_alias(alias) {
+            return;
+        }
+
+        let avatar = self.avatar_godot_scene.get_mut(&entity_id).unwrap();
+
+        // remote voices fade with the distance to the primary player
+        let settings = get_voice_chat_settings();
+        let player_position = DclGlobal::singleton()
+            .bind()
+            .scene_runner
+            .bind()
+            .get_player_global_position();
+        let gain = match player_position {
+            Some(player_position) => get_distance_attenuation(
+                avatar.get_global_position().distance_to(player_position),
+                settings.min_distance,
+                settings.max_distance,
+            ),
+            None => 1.0,
+        };
+
+        if gain <= 0.0 {
+            return;
+        }
+
+        let frame = if gain < 1.0 {
+            frame
+                .as_slice()
+                .iter()
+                .map(|sample| *sample * gain)
+                .collect::<PackedVector2Array>()
+        } else {
+            frame
+        };
+
+        avatar.call("push_voice_frame".into(), &[frame.to_variant()]);
     }
 
     // This function should be only called in the first tick
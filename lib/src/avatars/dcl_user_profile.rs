@@ -4,7 +4,7 @@ use godot::{
     obj::Gd,
 };
 
-use crate::comms::profile::UserProfile;
+use crate::{auth::wallet::AsH160, comms::profile::UserProfile};
 
 use super::avatar_type::DclAvatarWireFormat;
 
@@ -93,6 +93,36 @@ impl DclUserProfile {
         self.inner.content.avatar = avatar.bind().inner.clone();
     }
 
+    #[func]
+    fn is_blocked(&self, address: GString) -> bool {
+        address
+            .to_string()
+            .as_h160()
+            .map_or(false, |address| self.inner.content.is_blocked(&address))
+    }
+
+    #[func]
+    fn is_muted(&self, address: GString) -> bool {
+        address
+            .to_string()
+            .as_h160()
+            .map_or(false, |address| self.inner.content.is_muted(&address))
+    }
+
+    #[func]
+    fn set_blocked(&mut self, address: GString, blocked: bool) {
+        if let Some(address) = address.to_string().as_h160() {
+            self.inner.content.set_blocked(&address, blocked);
+        }
+    }
+
+    #[func]
+    fn set_muted(&mut self, address: GString, muted: bool) {
+        if let Some(address) = address.to_string().as_h160() {
+            self.inner.content.set_muted(&address, muted);
+        }
+    }
+
     #[func]
     pub fn from_godot_dictionary(dictionary: Dictionary) -> Gd<DclUserProfile> {
         let value = godot::engine::Json::stringify(dictionary.to_variant());
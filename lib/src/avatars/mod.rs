@@ -2,3 +2,4 @@ pub mod avatar_scene;
 pub mod avatar_type;
 pub mod dcl_user_profile;
 pub mod item;
+pub mod position_buffer;
@@ -0,0 +1,322 @@
+use std::collections::VecDeque;
+
+use godot::prelude::{Quaternion, Vector3};
+
+// Positions are broadcast every 0.1s while the player moves
+const EXPECTED_SAMPLE_INTERVAL: f64 = 0.1;
+
+// A lower index farther than this is a peer that restarted its counter
+const INDEX_RESET_THRESHOLD: i32 = 100;
+
+#[derive(Clone, Debug)]
+pub struct InterpolationSettings {
+    // how far in the past the avatars are rendered, in seconds
+    pub interpolation_delay: f64,
+    // how long the last velocity is kept when the packets are late, in seconds
+    pub max_extrapolation: f64,
+    // jumps longer than this (in meters) are not interpolated
+    pub snap_distance: f32,
+    pub max_samples: usize,
+}
+
+impl Default for InterpolationSettings {
+    fn default() -> Self {
+        Self {
+            interpolation_delay: 0.15,
+            max_extrapolation: 0.25,
+            snap_distance: 10.0,
+            max_samples: 16,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionSample {
+    pub index: u32,
+    // arrival time, in seconds
+    pub time: f64,
+    pub position: Vector3,
+    pub rotation: Quaternion,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampledPosition {
+    pub position: Vector3,
+    pub rotation: Quaternion,
+    // of the segment being rendered, zero when idle
+    pub velocity: Vector3,
+}
+
+// Buffers the positions received from a remote avatar and renders them
+//  `interpolation_delay` seconds in the past
+#[derive(Default)]
+pub struct PositionBuffer {
+    settings: InterpolationSettings,
+    samples: VecDeque<PositionSample>,
+}
+
+impl PositionBuffer {
+    pub fn new(settings: InterpolationSettings) -> Self {
+        Self {
+            settings,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: InterpolationSettings) {
+        self.settings = settings;
+        self.trim();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn last_index(&self) -> Option<u32> {
+        self.samples.back().map(|sample| sample.index)
+    }
+
+    // Returns false when the sample is discarded (duplicated or out of order)
+    pub fn push(&mut self, sample: PositionSample) -> bool {
+        let Some(last) = self.samples.back().cloned() else {
+            self.samples.push_back(sample);
+            return true;
+        };
+
+        let index_diff = sample.index.wrapping_sub(last.index) as i32;
+        if index_diff <= 0 && index_diff > -INDEX_RESET_THRESHOLD {
+            return false;
+        }
+
+        if index_diff <= -INDEX_RESET_THRESHOLD
+            || last.position.distance_to(sample.position) > self.settings.snap_distance
+        {
+            self.samples.clear();
+        } else if sample.time - last.time > 2.0 * EXPECTED_SAMPLE_INTERVAL {
+            // the peer was idle, it starts moving from the last position
+            //  instead of drifting along the whole gap
+            self.samples.push_back(PositionSample {
+                time: sample.time - EXPECTED_SAMPLE_INTERVAL,
+                ..last
+            });
+        }
+
+        self.samples.push_back(sample);
+        self.trim();
+        true
+    }
+
+    pub fn sample(&mut self, now: f64) -> Option<SampledPosition> {
+        let render_time = now - self.settings.interpolation_delay;
+
+        // keep only one sample older than the render time
+        while self.samples.len() > 1 && self.samples[1].time <= render_time {
+            self.samples.pop_front();
+        }
+
+        let first = self.samples.front()?;
+        if render_time <= first.time || self.samples.len() == 1 {
+            return Some(SampledPosition {
+                position: first.position,
+                rotation: first.rotation,
+                velocity: Vector3::ZERO,
+            });
+        }
+
+        let from = &self.samples[0];
+        let to = &self.samples[1];
+        let segment_time = (to.time - from.time).max(f64::EPSILON);
+        let velocity = (to.position - from.position) / segment_time as f32;
+
+        if render_time <= to.time {
+            let factor = ((render_time - from.time) / segment_time) as f32;
+            return Some(SampledPosition {
+                position: from.position.lerp(to.position, factor),
+                rotation: nlerp(from.rotation, to.rotation, factor),
+                velocity,
+            });
+        }
+
+        // no newer packet yet
+        let extrapolation = render_time - to.time;
+        if extrapolation > self.settings.max_extrapolation {
+            return Some(SampledPosition {
+                position: to.position + velocity * self.settings.max_extrapolation as f32,
+                rotation: to.rotation,
+                velocity: Vector3::ZERO,
+            });
+        }
+
+        Some(SampledPosition {
+            position: to.position + velocity * extrapolation as f32,
+            rotation: to.rotation,
+            velocity,
+        })
+    }
+
+    fn trim(&mut self) {
+        while self.samples.len() > self.settings.max_samples.max(2) {
+            self.samples.pop_front();
+        }
+    }
+}
+
+fn nlerp(from: Quaternion, to: Quaternion, factor: f32) -> Quaternion {
+    let dot = from.x * to.x + from.y * to.y + from.z * to.z + from.w * to.w;
+    // take the shortest path
+    let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+    let lerp = |a: f32, b: f32| a + (sign * b - a) * factor;
+    let result = Quaternion {
+        x: lerp(from.x, to.x),
+        y: lerp(from.y, to.y),
+        z: lerp(from.z, to.z),
+        w: lerp(from.w, to.w),
+    };
+
+    let length =
+        (result.x * result.x + result.y * result.y + result.z * result.z + result.w * result.w)
+            .sqrt();
+    if length <= f32::EPSILON {
+        return to;
+    }
+    Quaternion {
+        x: result.x / length,
+        y: result.y / length,
+        z: result.z / length,
+        w: result.w / length,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(index: u32, time: f64, x: f32) -> PositionSample {
+        PositionSample {
+            index,
+            time,
+            position: Vector3::new(x, 0.0, 0.0),
+            rotation: Quaternion {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 1.0,
+            },
+        }
+    }
+
+    fn buffer() -> PositionBuffer {
+        PositionBuffer::new(InterpolationSettings {
+            interpolation_delay: 0.1,
+            max_extrapolation: 0.2,
+            snap_distance: 10.0,
+            max_samples: 4,
+        })
+    }
+
+    fn sampled_x(buffer: &mut PositionBuffer, now: f64) -> f32 {
+        buffer.sample(now).unwrap().position.x
+    }
+
+    #[test]
+    fn test_discard_duplicated_and_out_of_order() {
+        let mut buffer = buffer();
+        assert!(buffer.push(sample(5, 0.0, 0.0)));
+        assert!(!buffer.push(sample(5, 0.1, 1.0)));
+        assert!(!buffer.push(sample(4, 0.1, 1.0)));
+        assert!(buffer.push(sample(6, 0.1, 1.0)));
+        assert_eq!(buffer.len(), 2);
+    }
+
+    #[test]
+    fn test_index_wraparound_and_reset() {
+        let mut buffer = buffer();
+        assert!(buffer.push(sample(u32::MAX, 0.0, 0.0)));
+        assert!(buffer.push(sample(0, 0.1, 1.0)));
+        assert_eq!(buffer.len(), 2);
+
+        // the peer restarted
+        assert!(buffer.push(sample(1_000_000, 0.2, 2.0)));
+        assert!(buffer.push(sample(1, 0.3, 3.0)));
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_max_samples() {
+        let mut buffer = buffer();
+        for i in 0..10 {
+            buffer.push(sample(i, i as f64 * 0.1, i as f32));
+        }
+        assert_eq!(buffer.len(), 4);
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let mut buffer = buffer();
+        buffer.push(sample(1, 0.0, 0.0));
+        buffer.push(sample(2, 0.1, 1.0));
+        buffer.push(sample(3, 0.2, 2.0));
+
+        assert_eq!(sampled_x(&mut buffer, 0.05), 0.0);
+        assert!((sampled_x(&mut buffer, 0.15) - 0.5).abs() < 0.001);
+        assert!((sampled_x(&mut buffer, 0.25) - 1.5).abs() < 0.001);
+        assert!((buffer.sample(0.25).unwrap().velocity.x - 10.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_extrapolation_is_limited() {
+        let mut buffer = buffer();
+        buffer.push(sample(1, 0.0, 0.0));
+        buffer.push(sample(2, 0.1, 1.0));
+
+        // 0.1s late
+        assert!((sampled_x(&mut buffer, 0.3) - 2.0).abs() < 0.001);
+        // stops after max_extrapolation
+        assert!((sampled_x(&mut buffer, 1.0) - 3.0).abs() < 0.001);
+        assert_eq!(buffer.sample(1.0).unwrap().velocity, Vector3::ZERO);
+    }
+
+    #[test]
+    fn test_snap_large_jumps() {
+        let mut buffer = buffer();
+        buffer.push(sample(1, 0.0, 0.0));
+        buffer.push(sample(2, 0.1, 1.0));
+        buffer.push(sample(3, 0.2, 50.0));
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(sampled_x(&mut buffer, 0.2), 50.0);
+    }
+
+    #[test]
+    fn test_idle_gap() {
+        let mut buffer = buffer();
+        buffer.push(sample(1, 0.0, 0.0));
+        buffer.push(sample(2, 2.0, 1.0));
+        assert_eq!(buffer.len(), 3);
+
+        // still at the last position until the new sample is due
+        assert_eq!(sampled_x(&mut buffer, 1.5), 0.0);
+        assert!((sampled_x(&mut buffer, 2.05) - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_rotation_shortest_path() {
+        let from = Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        };
+        let to = Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: -1.0,
+        };
+        let result = nlerp(from, to, 0.5);
+        assert!((result.w.abs() - 1.0).abs() < 0.001);
+    }
+}
@@ -1,16 +1,61 @@
 use ethers_core::types::H160;
+use godot::prelude::PackedVector2Array;
 
-use crate::{comms::profile::UserProfile, dcl::components::proto_components::kernel::comms::rfc4};
+use crate::{
+    avatars::avatar_scene::AvatarScene, comms::profile::UserProfile,
+    dcl::components::proto_components::kernel::comms::rfc4,
+};
+
+use super::reconnection::ConnectionState;
+
+// Received from a peer (by its alias) for the AvatarScene
+pub enum AvatarUpdate {
+    Position(rfc4::Position),
+    Profile(UserProfile),
+    Emote { incremental_id: u32, urn: String },
+    VoiceFrame(PackedVector2Array),
+}
+
+// The updates of the aliases removed meanwhile are ignored by the AvatarScene
+pub fn apply_avatar_updates(avatar_scene: &mut AvatarScene, updates: Vec<(u32, AvatarUpdate)>) {
+    for (alias, update) in updates {
+        match update {
+            AvatarUpdate::Position(position) => {
+                avatar_scene.update_avatar_transform_with_rfc4_position(alias, &position);
+            }
+            AvatarUpdate::Profile(profile) => {
+                avatar_scene.update_avatar_by_alias(alias, &profile);
+            }
+            AvatarUpdate::Emote {
+                incremental_id,
+                urn,
+            } => {
+                avatar_scene.play_emote_by_alias(alias, incremental_id, &urn);
+            }
+            AvatarUpdate::VoiceFrame(frame) => {
+                avatar_scene.push_voice_frame(alias, frame);
+            }
+        }
+    }
+}
 
 pub trait Adapter {
+    // Returns false when the adapter can't reconnect anymore
     fn poll(&mut self) -> bool;
+    fn connection_state(&self) -> ConnectionState;
     fn clean(&mut self);
 
     fn consume_chats(&mut self) -> Vec<(H160, rfc4::Chat)>;
+    // Received after the last call, applied by the owner of the adapter with
+    //  `apply_avatar_updates`
+    fn consume_avatar_updates(&mut self) -> Vec<(u32, AvatarUpdate)>;
     fn consume_scene_messages(&mut self, scene_id: &str) -> Vec<(H160, Vec<u8>)>;
     fn change_profile(&mut self, new_profile: UserProfile);
 
     fn send_rfc4(&mut self, packet: rfc4::Packet, unreliable: bool) -> bool;
+    // Reliable packet delivered only to `address`. Returns false when the
+    //  transport can't target a peer or the peer isn't in the room
+    fn send_rfc4_to(&mut self, packet: rfc4::Packet, address: H160) -> bool;
 
     fn broadcast_voice(&mut self, frame: Vec<i16>);
     fn support_voice_chat(&self) -> bool;
@@ -16,7 +16,12 @@ use ethers_core::types::H160;
 use godot::{engine::WebSocketPeer, prelude::*};
 use prost::Message;
 
-use super::{adapter_trait::Adapter, livekit::LivekitRoom};
+use super::{
+    adapter_trait::Adapter,
+    livekit::LivekitRoom,
+    network_simulator::wrap_adapter,
+    reconnection::{ConnectionState, Reconnection, ReconnectionPolicy},
+};
 
 #[derive(Clone)]
 enum ArchipelagoState {
@@ -32,7 +37,7 @@ pub struct ArchipelagoManager {
 
     // Connection
     ws_url: GString,
-    last_try_to_connect: Instant,
+    reconnection: Reconnection,
     ws_peer: Gd<WebSocketPeer>,
 
     player_address: H160,
@@ -72,7 +77,7 @@ impl ArchipelagoManager {
             player_address: ephemeral_auth_chain.signer(),
             ephemeral_auth_chain,
             player_profile,
-            last_try_to_connect: Instant::now(),
+            reconnection: Reconnection::new(ReconnectionPolicy::default()),
             adapter: None,
             avatar_scene,
             player_position: Vector3::new(0.0, 0.0, 0.0),
@@ -116,7 +121,8 @@ impl ArchipelagoManager {
         matches!(self.ws_peer.send(buf), godot::engine::global::Error::OK)
     }
 
-    pub fn poll(&mut self) {
+    // Returns false when it can't reconnect anymore
+    pub fn poll(&mut self) -> bool {
         let mut peer = self.ws_peer.clone();
         peer.poll();
 
@@ -125,7 +131,12 @@ impl ArchipelagoManager {
         match self.state.clone() {
             ArchipelagoState::Connecting => match ws_state {
                 godot::engine::web_socket_peer::State::STATE_CLOSED => {
-                    if (Instant::now() - self.last_try_to_connect).as_secs() > 1 {
+                    if self.reconnection.is_exhausted() {
+                        tracing::warn!("comms > archipelago max reconnection attempts reached");
+                        return false;
+                    }
+
+                    if self.reconnection.poll_attempt() {
                         let ws_protocols = {
                             let mut v = PackedStringArray::new();
                             v.push(GString::from("archipelago"));
@@ -134,8 +145,6 @@ impl ArchipelagoManager {
 
                         peer.set("supported_protocols".into(), ws_protocols.to_variant());
                         peer.call("connect_to_url".into(), &[self.ws_url.clone().to_variant()]);
-
-                        self.last_try_to_connect = Instant::now();
                     }
                 }
                 godot::engine::web_socket_peer::State::STATE_OPEN => {
@@ -170,7 +179,7 @@ impl ArchipelagoManager {
 
                                 if !challenge_to_sign.starts_with("dcl-") {
                                     tracing::error!("invalid challenge to sign");
-                                    return;
+                                    return true;
                                 }
 
                                 // TODO: should this block_on be async? the ephemeral wallet is sync
@@ -213,6 +222,7 @@ impl ArchipelagoManager {
                         match message {
                             server_packet::Message::Welcome(_welcome) => {
                                 self.state = ArchipelagoState::WelcomeMessageReceived;
+                                self.reconnection.on_connected();
                             }
                             _ => {
                                 tracing::info!(
@@ -257,11 +267,35 @@ impl ArchipelagoManager {
             true
         };
         if !adapter_ok {
-            self.adapter = None;
+            // the island access token could be expired, a new one comes with the
+            //  island assigned after authenticating again
+            if let Some(mut adapter) = self.adapter.take() {
+                adapter.clean();
+            }
+            self.ws_peer.close();
+            self.state = ArchipelagoState::Connecting;
+        }
+
+        true
+    }
+
+    pub fn connection_state(&self) -> ConnectionState {
+        if !matches!(self.state, ArchipelagoState::WelcomeMessageReceived) {
+            return self.reconnection.get_state(false);
+        }
+
+        match self.adapter.as_ref() {
+            Some(adapter) => adapter.connection_state(),
+            // waiting for the island
+            None => ConnectionState::Connecting,
         }
     }
 
-    pub fn clean(&self) {
+    pub fn clean(&mut self) {
+        if let Some(mut adapter) = self.adapter.take() {
+            adapter.clean();
+        }
+
         let mut peer = self.ws_peer.clone();
         peer.close();
         match peer.get_ready_state() {
@@ -291,12 +325,15 @@ impl ArchipelagoManager {
                     };
                     match protocol {
                         "livekit" => {
-                            self.adapter = Some(Box::new(LivekitRoom::new(
+                            if let Some(mut adapter) = self.adapter.take() {
+                                adapter.clean();
+                            }
+                            self.adapter = Some(wrap_adapter(Box::new(LivekitRoom::new(
                                 comms_address.to_string(),
                                 self.ephemeral_auth_chain.signer(),
                                 self.player_profile.clone(),
                                 self.avatar_scene.clone(),
-                            )));
+                            ))));
                         }
                         _ => {
                             tracing::info!(
@@ -1,11 +1,19 @@
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use ethers_core::types::H160;
 use futures_util::StreamExt;
 use godot::prelude::{GString, Gd, PackedVector2Array};
 use http::Uri;
 use livekit::{
-    options::TrackPublishOptions,
+    options::{AudioEncoding, TrackPublishOptions},
+    prelude::LocalTrackPublication,
     track::{LocalAudioTrack, LocalTrack, TrackSource},
     webrtc::{
         audio_source::native::NativeAudioSource,
@@ -18,15 +26,26 @@ use prost::Message;
 use crate::{
     auth::wallet::AsH160,
     avatars::avatar_scene::AvatarScene,
-    comms::profile::{SerializedProfile, UserProfile},
+    comms::{
+        profile::{SerializedProfile, UserProfile},
+        voice_processing::get_voice_chat_settings,
+    },
     dcl::components::proto_components::kernel::comms::rfc4,
+    tools::comms_inspector::{
+        inspect_peer_connection, inspect_profile_version, inspect_received, inspect_sent,
+    },
 };
 
-use super::adapter_trait::Adapter;
+use super::{
+    adapter_trait::{Adapter, AvatarUpdate},
+    reconnection::{ConnectionState, Reconnection, ReconnectionPolicy},
+};
 
 pub struct NetworkMessage {
     pub data: Vec<u8>,
     pub unreliable: bool,
+    // None is broadcast to the whole room
+    pub destination: Option<H160>,
 }
 
 enum ToSceneMessage<'a> {
@@ -48,6 +67,11 @@ struct Peer {
 }
 
 pub struct LivekitRoom {
+    remote_address: String,
+    reconnection: Reconnection,
+    // set by the livekit thread while the room is connected
+    room_connected: Arc<AtomicBool>,
+
     sender_to_thread: tokio::sync::mpsc::Sender<NetworkMessage>,
     mic_sender_to_thread: tokio::sync::mpsc::Sender<Vec<i16>>,
     receiver_from_thread: tokio::sync::mpsc::Receiver<IncomingMessage<'static>>,
@@ -55,14 +79,17 @@ pub struct LivekitRoom {
     player_profile: Option<UserProfile>,
     avatars: Gd<AvatarScene>,
     peer_identities: HashMap<H160, Peer>,
-    peer_alias_counter: u32,
     last_profile_response_sent: Instant,
     last_profile_request_sent: Instant,
     last_profile_version_announced: u32,
     chats: Vec<(H160, rfc4::Chat)>,
+    avatar_updates: Vec<(u32, AvatarUpdate)>,
 
     // Scene messges
     incoming_scene_messages: HashMap<String, Vec<(H160, Vec<u8>)>>,
+
+    // Name of the adapter in the comms inspector, without the access token
+    inspector_label: String,
 }
 
 impl LivekitRoom {
@@ -72,19 +99,23 @@ impl LivekitRoom {
         player_profile: Option<UserProfile>,
         avatars: Gd<AvatarScene>,
     ) -> Self {
-        tracing::debug!(">> lk connect async : {remote_address}");
-        let (sender, receiver_from_thread) = tokio::sync::mpsc::channel(1000);
-        let (sender_to_thread, receiver) = tokio::sync::mpsc::channel(1000);
-        let (mic_sender_to_thread, mic_receiver) = tokio::sync::mpsc::channel(1000);
-
-        let _ = std::thread::Builder::new()
-            .name("livekit dcl thread".into())
-            .spawn(move || {
-                spawn_livekit_task(remote_address, receiver, sender, mic_receiver);
-            })
-            .unwrap();
+        let mut reconnection = Reconnection::new(ReconnectionPolicy::default());
+        reconnection.poll_attempt();
+
+        let room_connected = Arc::new(AtomicBool::new(false));
+        let (sender_to_thread, mic_sender_to_thread, receiver_from_thread) =
+            spawn_livekit_thread(remote_address.clone(), room_connected.clone());
+
+        let inspector_label = format!(
+            "livekit:{}",
+            remote_address.split('?').next().unwrap_or_default()
+        );
 
         Self {
+            remote_address,
+            inspector_label,
+            reconnection,
+            room_connected,
             sender_to_thread,
             mic_sender_to_thread,
             receiver_from_thread,
@@ -94,16 +125,44 @@ impl LivekitRoom {
             peer_identities: HashMap::new(),
             last_profile_response_sent: Instant::now(),
             last_profile_request_sent: Instant::now(),
-            peer_alias_counter: 0,
             last_profile_version_announced: 0,
             chats: Vec::new(),
+            avatar_updates: Vec::new(),
             incoming_scene_messages: HashMap::new(),
         }
     }
 
-    fn _clean(&mut self) {}
+    fn _clean(&mut self) {
+        let mut avatar_scene = self.avatars.bind_mut();
+        for (address, peer) in self.peer_identities.drain() {
+            inspect_peer_connection(&self.inspector_label, address, false);
+            avatar_scene.remove_avatar(peer.alias);
+        }
+    }
+
+    // The livekit thread finished, the room was closed or it couldn't connect. The
+    //  token is reused, the parent adapter gets a new one if the attempts are exhausted
+    fn _reconnect(&mut self) -> bool {
+        if self.reconnection.is_exhausted() {
+            tracing::warn!("comms > livekit max reconnection attempts reached");
+            return false;
+        }
+
+        if self.reconnection.poll_attempt() {
+            let (sender_to_thread, mic_sender_to_thread, receiver_from_thread) =
+                spawn_livekit_thread(self.remote_address.clone(), self.room_connected.clone());
+            self.sender_to_thread = sender_to_thread;
+            self.mic_sender_to_thread = mic_sender_to_thread;
+            self.receiver_from_thread = receiver_from_thread;
+        }
+        true
+    }
 
     fn _poll(&mut self) -> bool {
+        if self.room_connected.load(Ordering::Relaxed) {
+            self.reconnection.on_connected();
+        }
+
         let mut avatar_scene_ref = self.avatars.clone();
         let mut avatar_scene = avatar_scene_ref.bind_mut();
 
@@ -113,26 +172,34 @@ impl LivekitRoom {
                     let peer = if let Some(value) = self.peer_identities.get_mut(&message.address) {
                         value
                     } else {
-                        self.peer_alias_counter += 1;
+                        let alias = avatar_scene.next_alias();
                         self.peer_identities.insert(
                             message.address,
                             Peer {
-                                alias: self.peer_alias_counter,
+                                alias,
                                 profile: None,
                                 announced_version: None,
                             },
                         );
-                        avatar_scene.add_avatar(
-                            self.peer_alias_counter,
-                            GString::from(format!("{:#x}", message.address)),
-                        );
+                        avatar_scene
+                            .add_avatar(alias, GString::from(format!("{:#x}", message.address)));
+                        inspect_peer_connection(&self.inspector_label, message.address, true);
                         self.peer_identities.get_mut(&message.address).unwrap()
                     };
 
+                    if let ToSceneMessage::Rfc4(rfc4_message) = &message.message {
+                        inspect_received(
+                            &self.inspector_label,
+                            message.address,
+                            Some(rfc4_message),
+                            rfc4_message.encoded_len(),
+                        );
+                    }
+
                     match message.message {
                         ToSceneMessage::Rfc4(rfc4::packet::Message::Position(position)) => {
-                            avatar_scene
-                                .update_avatar_transform_with_rfc4_position(peer.alias, &position);
+                            self.avatar_updates
+                                .push((peer.alias, AvatarUpdate::Position(position)));
                         }
                         ToSceneMessage::Rfc4(rfc4::packet::Message::Chat(chat)) => {
                             self.chats.push((message.address, chat));
@@ -214,7 +281,13 @@ impl LivekitRoom {
                                 base_url: profile_response.base_url.clone(),
                             };
 
-                            avatar_scene.update_avatar_by_alias(peer.alias, &profile);
+                            inspect_profile_version(
+                                &self.inspector_label,
+                                message.address,
+                                incoming_version,
+                            );
+                            self.avatar_updates
+                                .push((peer.alias, AvatarUpdate::Profile(profile.clone())));
                             peer.profile = Some(profile);
                         }
                         ToSceneMessage::Rfc4(rfc4::packet::Message::Scene(scene)) => {
@@ -226,6 +299,15 @@ impl LivekitRoom {
                             // TODO: should we limit the size of the queue or accumulated bytes?
                             entry.push((message.address, scene.data));
                         }
+                        ToSceneMessage::Rfc4(rfc4::packet::Message::PlayerEmote(player_emote)) => {
+                            self.avatar_updates.push((
+                                peer.alias,
+                                AvatarUpdate::Emote {
+                                    incremental_id: player_emote.incremental_id,
+                                    urn: player_emote.urn,
+                                },
+                            ));
+                        }
                         ToSceneMessage::Rfc4(rfc4::packet::Message::Voice(_voice)) => {}
                         ToSceneMessage::InitVoice(frame) => {
                             avatar_scene.spawn_voice_channel(
@@ -246,7 +328,8 @@ impl LivekitRoom {
                                 godot::prelude::Vector2 { x: val, y: val }
                             }));
 
-                            avatar_scene.push_voice_frame(peer.alias, frame);
+                            self.avatar_updates
+                                .push((peer.alias, AvatarUpdate::VoiceFrame(frame)));
                         }
                         _ => {
                             tracing::debug!("comms > unhandled message");
@@ -255,9 +338,8 @@ impl LivekitRoom {
                 }
 
                 Err(tokio::sync::mpsc::error::TryRecvError::Empty) => break,
-                Err(err) => {
-                    tracing::error!("error polling livekit thread: {err}");
-                    return false;
+                Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => {
+                    return self._reconnect();
                 }
             }
         }
@@ -318,9 +400,32 @@ impl LivekitRoom {
     fn _send_rfc4(&mut self, packet: rfc4::Packet, unreliable: bool) -> bool {
         let mut data: Vec<u8> = Vec::new();
         packet.encode(&mut data).unwrap();
+        inspect_sent(&self.inspector_label, packet.message.as_ref(), data.len());
+
+        self.sender_to_thread
+            .blocking_send(NetworkMessage {
+                data,
+                unreliable,
+                destination: None,
+            })
+            .is_ok()
+    }
+
+    fn _send_rfc4_to(&mut self, packet: rfc4::Packet, address: H160) -> bool {
+        if !self.peer_identities.contains_key(&address) {
+            return false;
+        }
+
+        let mut data: Vec<u8> = Vec::new();
+        packet.encode(&mut data).unwrap();
+        inspect_sent(&self.inspector_label, packet.message.as_ref(), data.len());
 
         self.sender_to_thread
-            .blocking_send(NetworkMessage { data, unreliable })
+            .blocking_send(NetworkMessage {
+                data,
+                unreliable: false,
+                destination: Some(address),
+            })
             .is_ok()
     }
 
@@ -342,6 +447,11 @@ impl Adapter for LivekitRoom {
         self._poll()
     }
 
+    fn connection_state(&self) -> ConnectionState {
+        self.reconnection
+            .get_state(self.room_connected.load(Ordering::Relaxed))
+    }
+
     fn clean(&mut self) {
         self._clean();
     }
@@ -354,10 +464,18 @@ impl Adapter for LivekitRoom {
         self._consume_chats()
     }
 
+    fn consume_avatar_updates(&mut self) -> Vec<(u32, AvatarUpdate)> {
+        std::mem::take(&mut self.avatar_updates)
+    }
+
     fn send_rfc4(&mut self, packet: rfc4::Packet, unreliable: bool) -> bool {
         self._send_rfc4(packet, unreliable)
     }
 
+    fn send_rfc4_to(&mut self, packet: rfc4::Packet, address: H160) -> bool {
+        self._send_rfc4_to(packet, address)
+    }
+
     fn broadcast_voice(&mut self, frame: Vec<i16>) {
         self._broadcast_voice(frame);
     }
@@ -375,11 +493,74 @@ impl Adapter for LivekitRoom {
     }
 }
 
+#[allow(clippy::type_complexity)]
+fn spawn_livekit_thread(
+    remote_address: String,
+    room_connected: Arc<AtomicBool>,
+) -> (
+    tokio::sync::mpsc::Sender<NetworkMessage>,
+    tokio::sync::mpsc::Sender<Vec<i16>>,
+    tokio::sync::mpsc::Receiver<IncomingMessage<'static>>,
+) {
+    tracing::debug!(">> lk connect async : {remote_address}");
+    let (sender, receiver_from_thread) = tokio::sync::mpsc::channel(1000);
+    let (sender_to_thread, receiver) = tokio::sync::mpsc::channel(1000);
+    let (mic_sender_to_thread, mic_receiver) = tokio::sync::mpsc::channel(1000);
+
+    let _ = std::thread::Builder::new()
+        .name("livekit dcl thread".into())
+        .spawn(move || {
+            spawn_livekit_task(
+                remote_address,
+                receiver,
+                sender,
+                mic_receiver,
+                room_connected,
+            );
+        })
+        .unwrap();
+
+    (sender_to_thread, mic_sender_to_thread, receiver_from_thread)
+}
+
+async fn publish_mic_track(
+    room: &livekit::prelude::Room,
+    native_source: &NativeAudioSource,
+    opus_bitrate_kbps: u32,
+) -> Option<LocalTrackPublication> {
+    let mic_track = LocalTrack::Audio(LocalAudioTrack::create_audio_track(
+        "mic",
+        RtcAudioSource::Native(native_source.clone()),
+    ));
+    let publish_options = TrackPublishOptions {
+        source: TrackSource::Microphone,
+        audio_encoding: Some(AudioEncoding {
+            max_bitrate: opus_bitrate_kbps as u64 * 1000,
+        }),
+        // Opus discontinuous transmission, the encoder only sends sparse comfort noise
+        //  packets while silent. It's unrelated to our voice activity detection
+        dtx: true,
+        ..Default::default()
+    };
+    match room
+        .local_participant()
+        .publish_track(mic_track, publish_options)
+        .await
+    {
+        Ok(publication) => Some(publication),
+        Err(e) => {
+            tracing::warn!("livekit mic track couldn't be published: {e}");
+            None
+        }
+    }
+}
+
 fn spawn_livekit_task(
     remote_address: String,
     mut receiver: tokio::sync::mpsc::Receiver<NetworkMessage>,
     sender: tokio::sync::mpsc::Sender<IncomingMessage<'static>>,
     mut mic_receiver: tokio::sync::mpsc::Receiver<Vec<i16>>,
+    room_connected: Arc<AtomicBool>,
 ) {
     let url = Uri::try_from(remote_address).unwrap();
     let address = format!(
@@ -406,19 +587,27 @@ fn spawn_livekit_task(
     let rt2 = rt.clone();
 
     let task = rt.spawn(async move {
-        let (room, mut network_rx) = livekit::prelude::Room::connect(&address, &token, RoomOptions{ auto_subscribe: true, adaptive_stream: false, dynacast: false, ..Default::default() }).await.unwrap();
+        let (room, mut network_rx) = match livekit::prelude::Room::connect(&address, &token, RoomOptions{ auto_subscribe: true, adaptive_stream: false, dynacast: false, ..Default::default() }).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                tracing::warn!("livekit room connection failed: {e}");
+                return;
+            }
+        };
         let native_source = NativeAudioSource::new(AudioSourceOptions{
             echo_cancellation: true,
             noise_suppression: true,
             auto_gain_control: true,
         }, 48000, 1);
-        let mic_track = LocalTrack::Audio(LocalAudioTrack::create_audio_track("mic", RtcAudioSource::Native(native_source.clone())));
-        room.local_participant().publish_track(mic_track, TrackPublishOptions{ source: TrackSource::Microphone, ..Default::default() }).await.unwrap();
+        let mut published_bitrate_kbps = get_voice_chat_settings().opus_bitrate_kbps;
+        let mut mic_publication = publish_mic_track(&room, &native_source, published_bitrate_kbps).await;
+        room_connected.store(true, Ordering::Relaxed);
 
+        let mic_source = native_source.clone();
         rt2.spawn(async move {
             while let Some(data) = mic_receiver.recv().await {
                 let samples_per_channel = data.len() as u32;
-                let res = native_source.capture_frame(&livekit::webrtc::prelude::AudioFrame {
+                let res = mic_source.capture_frame(&livekit::webrtc::prelude::AudioFrame {
                     data: data.into(),
                     sample_rate: 48000,
                     num_channels: 1,
@@ -431,6 +620,8 @@ fn spawn_livekit_task(
             }
         });
 
+        let mut bitrate_check = tokio::time::interval(Duration::from_secs(1));
+
         'stream: loop {
             tokio::select!(
                 incoming = network_rx.recv() => {
@@ -524,15 +715,44 @@ fn spawn_livekit_task(
                     } else {
                         DataPacketKind::Reliable
                     };
-                    if let Err(e) = room.local_participant().publish_data(DataPacket { payload: outgoing.data, kind, ..Default::default() }).await {
+                    let destination_sids = match outgoing.destination {
+                        Some(address) => {
+                            let sids = room.participants().into_iter()
+                                .filter(|(_, participant)| participant.identity().0.as_str().as_h160() == Some(address))
+                                .map(|(sid, _)| sid)
+                                .collect::<Vec<_>>();
+                            if sids.is_empty() {
+                                // never broadcast a packet addressed to a peer that left
+                                tracing::debug!("outgoing packet to {:#x} dropped, not in the room", address);
+                                continue 'stream;
+                            }
+                            sids
+                        }
+                        None => Vec::new(),
+                    };
+                    if let Err(e) = room.local_participant().publish_data(DataPacket { payload: outgoing.data, kind, destination_sids, ..Default::default() }).await {
                         tracing::debug!("outgoing failed: {e}; not exiting loop though since it often fails at least once or twice at the start...");
                         // break 'stream;
                     };
                 }
+                _ = bitrate_check.tick() => {
+                    // the bitrate of a published track can't be changed, it's published again
+                    let opus_bitrate_kbps = get_voice_chat_settings().opus_bitrate_kbps;
+                    if opus_bitrate_kbps != published_bitrate_kbps {
+                        if let Some(publication) = mic_publication.take() {
+                            if let Err(e) = room.local_participant().unpublish_track(&publication.sid()).await {
+                                tracing::warn!("livekit mic track couldn't be unpublished: {e}");
+                            }
+                        }
+                        mic_publication = publish_mic_track(&room, &native_source, opus_bitrate_kbps).await;
+                        published_bitrate_kbps = opus_bitrate_kbps;
+                    }
+                }
             );
         }
 
-        room.close().await.unwrap();
+        room_connected.store(false, Ordering::Relaxed);
+        let _ = room.close().await;
     });
 
     let _ = rt.block_on(task);
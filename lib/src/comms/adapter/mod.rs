@@ -3,4 +3,6 @@ pub mod adapter_trait;
 pub mod archipelago;
 #[cfg(feature = "use_livekit")]
 pub mod livekit;
+pub mod network_simulator;
+pub mod reconnection;
 pub mod ws_room;
@@ -0,0 +1,412 @@
+use std::{collections::VecDeque, sync::RwLock};
+
+#[cfg(debug_assertions)]
+use std::{collections::HashMap, time::Instant};
+
+#[cfg(debug_assertions)]
+use ethers_core::types::H160;
+
+#[cfg(debug_assertions)]
+use crate::{comms::profile::UserProfile, dcl::components::proto_components::kernel::comms::rfc4};
+
+use super::adapter_trait::Adapter;
+#[cfg(debug_assertions)]
+use super::{adapter_trait::AvatarUpdate, reconnection::ConnectionState};
+
+// Shared by every simulated adapter (island, archipelago and scene rooms)
+pub static GLOBAL_NETWORK_CONDITIONS: once_cell::sync::Lazy<RwLock<NetworkConditions>> =
+    once_cell::sync::Lazy::new(Default::default);
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetworkConditions {
+    pub latency_ms: f32,
+    // random variation added or removed to the latency
+    pub jitter_ms: f32,
+    // in the range [0, 1]
+    pub packet_loss: f32,
+    // 0 means unlimited
+    pub bandwidth_kbps: f32,
+}
+
+impl NetworkConditions {
+    pub fn is_enabled(&self) -> bool {
+        self.latency_ms > 0.0
+            || self.jitter_ms > 0.0
+            || self.packet_loss > 0.0
+            || self.bandwidth_kbps > 0.0
+    }
+}
+
+struct QueuedPacket<T> {
+    release_time: f64,
+    item: T,
+}
+
+// One direction of the simulated link, times are in seconds
+pub struct DelayQueue<T> {
+    queue: VecDeque<QueuedPacket<T>>,
+    // the link is sending until this time when the bandwidth is capped
+    busy_until: f64,
+    last_reliable_release: f64,
+}
+
+impl<T> Default for DelayQueue<T> {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            busy_until: 0.0,
+            last_reliable_release: 0.0,
+        }
+    }
+}
+
+impl<T> DelayQueue<T> {
+    // `random_loss` and `random_jitter` are in the range [0, 1). The unreliable
+    //  packets can be dropped or reordered, a lost reliable packet is
+    //  retransmitted after a round trip. Returns false when it's dropped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn push(
+        &mut self,
+        conditions: &NetworkConditions,
+        now: f64,
+        size_bytes: usize,
+        reliable: bool,
+        random_loss: f32,
+        random_jitter: f32,
+        item: T,
+    ) -> bool {
+        let lost = random_loss < conditions.packet_loss;
+        if lost && !reliable {
+            return false;
+        }
+
+        let send_time = if conditions.bandwidth_kbps > 0.0 {
+            let transmission =
+                (size_bytes * 8) as f64 / (conditions.bandwidth_kbps as f64 * 1000.0);
+            self.busy_until = self.busy_until.max(now) + transmission;
+            self.busy_until
+        } else {
+            now
+        };
+
+        let latency = conditions.latency_ms + conditions.jitter_ms * (2.0 * random_jitter - 1.0);
+        let mut delay = latency.max(0.0) as f64 / 1000.0;
+        if lost {
+            delay += 2.0 * conditions.latency_ms as f64 / 1000.0;
+        }
+
+        let mut release_time = send_time + delay;
+        if reliable {
+            release_time = release_time.max(self.last_reliable_release);
+            self.last_reliable_release = release_time;
+        }
+
+        self.queue.push_back(QueuedPacket { release_time, item });
+        true
+    }
+
+    pub fn pop_ready(&mut self, now: f64) -> Vec<T> {
+        let mut ready = Vec::new();
+        let mut pending = VecDeque::with_capacity(self.queue.len());
+        for packet in self.queue.drain(..) {
+            if packet.release_time <= now {
+                ready.push(packet);
+            } else {
+                pending.push_back(packet);
+            }
+        }
+        self.queue = pending;
+
+        // the jitter can reorder the unreliable packets
+        ready.sort_by(|a, b| a.release_time.total_cmp(&b.release_time));
+        ready.into_iter().map(|packet| packet.item).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+#[cfg(debug_assertions)]
+enum Outgoing {
+    Rfc4(rfc4::Packet, bool),
+    Voice(Vec<i16>),
+}
+
+// Delays and drops the packets of the inner adapter according to
+//  `GLOBAL_NETWORK_CONDITIONS`, the incoming ones are taken from the poll
+//  output of the inner adapter (chats, avatar updates and scene messages)
+#[cfg(debug_assertions)]
+pub struct NetworkSimulator {
+    inner: Box<dyn Adapter>,
+    start_time: Instant,
+    outgoing: DelayQueue<Outgoing>,
+    incoming_chats: DelayQueue<(H160, rfc4::Chat)>,
+    incoming_avatar_updates: DelayQueue<(u32, AvatarUpdate)>,
+    incoming_scene_messages: HashMap<String, DelayQueue<(H160, Vec<u8>)>>,
+}
+
+#[cfg(debug_assertions)]
+impl NetworkSimulator {
+    pub fn new(inner: Box<dyn Adapter>) -> Self {
+        Self {
+            inner,
+            start_time: Instant::now(),
+            outgoing: DelayQueue::default(),
+            incoming_chats: DelayQueue::default(),
+            incoming_avatar_updates: DelayQueue::default(),
+            incoming_scene_messages: HashMap::new(),
+        }
+    }
+
+    fn now(&self) -> f64 {
+        self.start_time.elapsed().as_secs_f64()
+    }
+}
+
+// Only debug builds simulate the network conditions
+#[cfg(debug_assertions)]
+pub fn wrap_adapter(adapter: Box<dyn Adapter>) -> Box<dyn Adapter> {
+    Box::new(NetworkSimulator::new(adapter))
+}
+
+#[cfg(not(debug_assertions))]
+pub fn wrap_adapter(adapter: Box<dyn Adapter>) -> Box<dyn Adapter> {
+    adapter
+}
+
+#[cfg(debug_assertions)]
+fn get_conditions() -> NetworkConditions {
+    GLOBAL_NETWORK_CONDITIONS.read().unwrap().clone()
+}
+
+// The positions and voice frames are sent unreliable
+#[cfg(debug_assertions)]
+fn is_avatar_update_reliable(update: &AvatarUpdate) -> bool {
+    matches!(
+        update,
+        AvatarUpdate::Profile(_) | AvatarUpdate::Emote { .. }
+    )
+}
+
+#[cfg(debug_assertions)]
+fn avatar_update_size(update: &AvatarUpdate) -> usize {
+    match update {
+        AvatarUpdate::Position(position) => prost::Message::encoded_len(position),
+        AvatarUpdate::Profile(profile) => serde_json::to_vec(&profile.content)
+            .map(|data| data.len())
+            .unwrap_or_default(),
+        AvatarUpdate::Emote { urn, .. } => urn.len() + 4,
+        AvatarUpdate::VoiceFrame(frame) => frame.len() * 8,
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Adapter for NetworkSimulator {
+    fn poll(&mut self) -> bool {
+        let now = self.now();
+        for outgoing in self.outgoing.pop_ready(now) {
+            match outgoing {
+                Outgoing::Rfc4(packet, unreliable) => {
+                    self.inner.send_rfc4(packet, unreliable);
+                }
+                Outgoing::Voice(frame) => self.inner.broadcast_voice(frame),
+            }
+        }
+
+        let result = self.inner.poll();
+
+        let conditions = get_conditions();
+        for (address, chat) in self.inner.consume_chats() {
+            let size = chat.message.len();
+            self.incoming_chats.push(
+                &conditions,
+                now,
+                size,
+                true,
+                rand::random(),
+                rand::random(),
+                (address, chat),
+            );
+        }
+        for (alias, update) in self.inner.consume_avatar_updates() {
+            let size = avatar_update_size(&update);
+            let reliable = is_avatar_update_reliable(&update);
+            self.incoming_avatar_updates.push(
+                &conditions,
+                now,
+                size,
+                reliable,
+                rand::random(),
+                rand::random(),
+                (alias, update),
+            );
+        }
+
+        result
+    }
+
+    fn connection_state(&self) -> ConnectionState {
+        self.inner.connection_state()
+    }
+
+    fn clean(&mut self) {
+        self.outgoing = DelayQueue::default();
+        self.incoming_chats = DelayQueue::default();
+        self.incoming_avatar_updates = DelayQueue::default();
+        self.incoming_scene_messages.clear();
+        self.inner.clean();
+    }
+
+    fn consume_chats(&mut self) -> Vec<(H160, rfc4::Chat)> {
+        self.incoming_chats.pop_ready(self.now())
+    }
+
+    fn consume_avatar_updates(&mut self) -> Vec<(u32, AvatarUpdate)> {
+        self.incoming_avatar_updates.pop_ready(self.now())
+    }
+
+    fn consume_scene_messages(&mut self, scene_id: &str) -> Vec<(H160, Vec<u8>)> {
+        let now = self.now();
+        let conditions = get_conditions();
+        let queue = self
+            .incoming_scene_messages
+            .entry(scene_id.to_string())
+            .or_default();
+        for (address, data) in self.inner.consume_scene_messages(scene_id) {
+            queue.push(
+                &conditions,
+                now,
+                data.len(),
+                true,
+                rand::random(),
+                rand::random(),
+                (address, data),
+            );
+        }
+        queue.pop_ready(now)
+    }
+
+    fn change_profile(&mut self, new_profile: UserProfile) {
+        self.inner.change_profile(new_profile);
+    }
+
+    fn send_rfc4(&mut self, packet: rfc4::Packet, unreliable: bool) -> bool {
+        let conditions = get_conditions();
+        if !conditions.is_enabled() && self.outgoing.is_empty() {
+            return self.inner.send_rfc4(packet, unreliable);
+        }
+
+        let size = prost::Message::encoded_len(&packet);
+        self.outgoing.push(
+            &conditions,
+            self.now(),
+            size,
+            !unreliable,
+            rand::random(),
+            rand::random(),
+            Outgoing::Rfc4(packet, unreliable),
+        );
+        true
+    }
+
+    // Sent right away, the caller needs the result of the inner adapter to
+    //  fall back to another room when the peer can't be reached
+    fn send_rfc4_to(&mut self, packet: rfc4::Packet, address: H160) -> bool {
+        self.inner.send_rfc4_to(packet, address)
+    }
+
+    fn broadcast_voice(&mut self, frame: Vec<i16>) {
+        let conditions = get_conditions();
+        if !conditions.is_enabled() && self.outgoing.is_empty() {
+            self.inner.broadcast_voice(frame);
+            return;
+        }
+
+        let size = frame.len() * 2;
+        self.outgoing.push(
+            &conditions,
+            self.now(),
+            size,
+            false,
+            rand::random(),
+            rand::random(),
+            Outgoing::Voice(frame),
+        );
+    }
+
+    fn support_voice_chat(&self) -> bool {
+        self.inner.support_voice_chat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditions(latency_ms: f32, jitter_ms: f32, packet_loss: f32) -> NetworkConditions {
+        NetworkConditions {
+            latency_ms,
+            jitter_ms,
+            packet_loss,
+            bandwidth_kbps: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_latency() {
+        let conditions = conditions(100.0, 0.0, 0.0);
+        let mut queue = DelayQueue::default();
+        assert!(queue.push(&conditions, 0.0, 10, false, 0.5, 0.5, 1));
+        assert!(queue.pop_ready(0.05).is_empty());
+        assert_eq!(queue.pop_ready(0.1), vec![1]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_packet_loss() {
+        let conditions = conditions(100.0, 0.0, 0.5);
+        let mut queue = DelayQueue::default();
+        assert!(!queue.push(&conditions, 0.0, 10, false, 0.1, 0.5, 1));
+        assert!(queue.push(&conditions, 0.0, 10, false, 0.9, 0.5, 2));
+
+        // the reliable ones are retransmitted
+        assert!(queue.push(&conditions, 0.0, 10, true, 0.1, 0.5, 3));
+        assert_eq!(queue.pop_ready(0.1), vec![2]);
+        assert_eq!(queue.pop_ready(0.31), vec![3]);
+    }
+
+    #[test]
+    fn test_jitter_order() {
+        let conditions = conditions(100.0, 50.0, 0.0);
+        let mut queue = DelayQueue::default();
+        queue.push(&conditions, 0.0, 10, false, 0.5, 0.9, 1);
+        queue.push(&conditions, 0.0, 10, false, 0.5, 0.1, 2);
+        assert_eq!(queue.pop_ready(1.0), vec![2, 1]);
+
+        // the reliable packets keep their order
+        queue.push(&conditions, 0.0, 10, true, 0.5, 0.9, 3);
+        queue.push(&conditions, 0.0, 10, true, 0.5, 0.1, 4);
+        assert_eq!(queue.pop_ready(1.0), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_bandwidth() {
+        let conditions = NetworkConditions {
+            bandwidth_kbps: 8.0,
+            ..Default::default()
+        };
+        let mut queue = DelayQueue::default();
+        // 1 kB per second
+        queue.push(&conditions, 0.0, 500, false, 0.5, 0.5, 1);
+        queue.push(&conditions, 0.0, 500, false, 0.5, 0.5, 2);
+        assert!(queue.pop_ready(0.4).is_empty());
+        assert_eq!(queue.pop_ready(0.5), vec![1]);
+        assert_eq!(queue.pop_ready(1.0), vec![2]);
+        assert_eq!(queue.len(), 0);
+    }
+}
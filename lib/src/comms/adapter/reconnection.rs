@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+
+// Sent to godot as i32 by `CommunicationManager::connection_state_changed`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ConnectionState {
+    Disconnected = 0,
+    Connecting = 1,
+    Connected = 2,
+    Reconnecting = 3,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReconnectionPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub max_attempts: u32,
+    // fraction of the delay randomly added or removed
+    pub jitter: f32,
+    // the attempts are restarted only after a connection that lasted this long
+    pub stable_time: Duration,
+}
+
+impl Default for ReconnectionPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            max_attempts: 10,
+            jitter: 0.25,
+            stable_time: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ReconnectionPolicy {
+    // `random` is in the range [0, 1)
+    pub fn get_delay(&self, attempt: u32, random: f32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16) as i32;
+        let delay = (self.initial_delay.as_secs_f32() * 2.0_f32.powi(exponent))
+            .min(self.max_delay.as_secs_f32());
+        let jitter = delay * self.jitter * (2.0 * random - 1.0);
+        Duration::from_secs_f32((delay + jitter).max(0.0))
+    }
+}
+
+// Tracks the connection attempts of an adapter, the attempts are restarted when
+//  the connection is lost after being stable, so a flapping room runs out of them
+pub struct Reconnection {
+    policy: ReconnectionPolicy,
+    attempts: u32,
+    next_attempt: Instant,
+    has_connected: bool,
+    connected_since: Option<Instant>,
+}
+
+impl Reconnection {
+    pub fn new(policy: ReconnectionPolicy) -> Self {
+        Self {
+            policy,
+            attempts: 0,
+            next_attempt: Instant::now(),
+            has_connected: false,
+            connected_since: None,
+        }
+    }
+
+    // Returns true when a new attempt has to start now
+    pub fn poll_attempt(&mut self) -> bool {
+        // it's only polled while disconnected
+        if self.was_stable() {
+            self.attempts = 0;
+            self.next_attempt = Instant::now();
+        }
+        self.connected_since = None;
+
+        if self.attempts >= self.policy.max_attempts || Instant::now() < self.next_attempt {
+            return false;
+        }
+
+        self.attempts += 1;
+        self.next_attempt =
+            Instant::now() + self.policy.get_delay(self.attempts, rand::random::<f32>());
+        true
+    }
+
+    // The last attempt also had its time to succeed
+    pub fn is_exhausted(&self) -> bool {
+        self.attempts >= self.policy.max_attempts
+            && Instant::now() >= self.next_attempt
+            && !self.was_stable()
+    }
+
+    // It can be called on every poll while the connection is established
+    pub fn on_connected(&mut self) {
+        self.connected_since.get_or_insert_with(Instant::now);
+        self.has_connected = true;
+    }
+
+    fn was_stable(&self) -> bool {
+        self.connected_since
+            .is_some_and(|since| since.elapsed() >= self.policy.stable_time)
+    }
+
+    pub fn has_connected(&self) -> bool {
+        self.has_connected
+    }
+
+    pub fn get_state(&self, connected: bool) -> ConnectionState {
+        if connected {
+            ConnectionState::Connected
+        } else if self.has_connected {
+            ConnectionState::Reconnecting
+        } else {
+            ConnectionState::Connecting
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_delay() {
+        let policy = ReconnectionPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.get_delay(1, 0.5), Duration::from_secs(1));
+        assert_eq!(policy.get_delay(2, 0.5), Duration::from_secs(2));
+        assert_eq!(policy.get_delay(4, 0.5), Duration::from_secs(8));
+        assert_eq!(policy.get_delay(10, 0.5), Duration::from_secs(30));
+        assert_eq!(policy.get_delay(u32::MAX, 0.5), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_delay_jitter() {
+        let policy = ReconnectionPolicy::default();
+        let min = policy.get_delay(3, 0.0).as_secs_f32();
+        let max = policy.get_delay(3, 0.999).as_secs_f32();
+        assert!((min - 3.0).abs() < 0.001);
+        assert!(max > 4.99 && max < 5.0);
+    }
+
+    #[test]
+    fn test_max_attempts() {
+        let mut reconnection = Reconnection::new(ReconnectionPolicy {
+            initial_delay: Duration::ZERO,
+            max_attempts: 2,
+            stable_time: Duration::ZERO,
+            ..Default::default()
+        });
+        assert_eq!(reconnection.get_state(false), ConnectionState::Connecting);
+
+        assert!(reconnection.poll_attempt());
+        assert!(reconnection.poll_attempt());
+        assert!(!reconnection.poll_attempt());
+        assert!(reconnection.is_exhausted());
+
+        reconnection.on_connected();
+        assert!(!reconnection.is_exhausted());
+        assert_eq!(reconnection.get_state(false), ConnectionState::Reconnecting);
+        assert!(reconnection.poll_attempt());
+    }
+
+    #[test]
+    fn test_flapping_connection() {
+        let mut reconnection = Reconnection::new(ReconnectionPolicy {
+            initial_delay: Duration::ZERO,
+            max_attempts: 2,
+            stable_time: Duration::from_secs(60),
+            ..Default::default()
+        });
+
+        assert!(reconnection.poll_attempt());
+        assert!(reconnection.poll_attempt());
+
+        // connected for a moment, the attempts are not restarted
+        reconnection.on_connected();
+        reconnection.on_connected();
+        assert_eq!(reconnection.get_state(true), ConnectionState::Connected);
+        assert!(reconnection.is_exhausted());
+        assert!(!reconnection.poll_attempt());
+        assert!(reconnection.is_exhausted());
+        assert_eq!(reconnection.get_state(false), ConnectionState::Reconnecting);
+    }
+}
@@ -11,13 +11,19 @@ use crate::{
         rfc4::{self},
         rfc5::{ws_packet, WsIdentification, WsPacket, WsPeerUpdate, WsSignedChallenge},
     },
+    tools::comms_inspector::{
+        inspect_peer_connection, inspect_profile_version, inspect_received, inspect_sent,
+    },
 };
 use ethers_core::types::{Signature, H160};
 use godot::{engine::WebSocketPeer, prelude::*};
 use prost::Message;
 use tracing::error;
 
-use super::adapter_trait::Adapter;
+use super::{
+    adapter_trait::{Adapter, AvatarUpdate},
+    reconnection::{ConnectionState, Reconnection, ReconnectionPolicy},
+};
 
 #[derive(Clone)]
 enum WsRoomState {
@@ -30,14 +36,17 @@ enum WsRoomState {
 
 pub struct Peer {
     address: H160,
+    // the alias in the AvatarScene, the server alias is the key of `peer_identities`
+    alias: u32,
     profile: Option<UserProfile>,
     announced_version: Option<u32>,
 }
 
 impl Peer {
-    pub fn new(address: H160) -> Self {
+    pub fn new(address: H160, alias: u32) -> Self {
         Self {
             address,
+            alias,
             profile: None,
             announced_version: None,
         }
@@ -49,7 +58,7 @@ pub struct WebSocketRoom {
 
     // Connection
     ws_url: GString,
-    last_try_to_connect: Instant,
+    reconnection: Reconnection,
     ws_peer: Gd<WebSocketPeer>,
     signature: Option<Signature>,
 
@@ -63,12 +72,16 @@ pub struct WebSocketRoom {
     // Trade-off with other peers
     avatars: Gd<AvatarScene>,
     chats: Vec<(H160, rfc4::Chat)>,
+    avatar_updates: Vec<(u32, AvatarUpdate)>,
     last_profile_response_sent: Instant,
     last_profile_request_sent: Instant,
     last_profile_version_announced: u32,
 
     // Scene messges
     incoming_scene_messages: HashMap<String, Vec<(H160, Vec<u8>)>>,
+
+    // Name of the adapter in the comms inspector
+    inspector_label: String,
 }
 
 impl WebSocketRoom {
@@ -92,6 +105,7 @@ impl WebSocketRoom {
         let old_time = Instant::now() - Duration::from_secs(1000);
 
         Self {
+            inspector_label: format!("ws-room:{ws_url}"),
             ws_peer: WebSocketPeer::new(),
             ws_url: GString::from(ws_url),
             state: WsRoomState::Connecting,
@@ -102,10 +116,11 @@ impl WebSocketRoom {
             peer_identities: HashMap::new(),
             avatars,
             chats: Vec::new(),
+            avatar_updates: Vec::new(),
             signature: None,
             last_profile_response_sent: old_time,
             last_profile_request_sent: old_time,
-            last_try_to_connect: old_time,
+            reconnection: Reconnection::new(ReconnectionPolicy::default()),
             last_profile_version_announced: 0,
             incoming_scene_messages: HashMap::new(),
         }
@@ -118,6 +133,7 @@ impl WebSocketRoom {
     fn _send_rfc4(&mut self, packet: rfc4::Packet, unreliable: bool) -> bool {
         let mut buf = Vec::new();
         packet.encode(&mut buf).unwrap();
+        inspect_sent(&self.inspector_label, packet.message.as_ref(), buf.len());
 
         let packet = WsPacket {
             message: Some(ws_packet::Message::PeerUpdateMessage(WsPeerUpdate {
@@ -156,7 +172,7 @@ impl WebSocketRoom {
         matches!(self.ws_peer.send(buf), godot::engine::global::Error::OK)
     }
 
-    fn _poll(&mut self) {
+    fn _poll(&mut self) -> bool {
         let mut peer = self.ws_peer.clone();
         peer.poll();
 
@@ -165,7 +181,13 @@ impl WebSocketRoom {
         match self.state.clone() {
             WsRoomState::Connecting => match ws_state {
                 godot::engine::web_socket_peer::State::STATE_CLOSED => {
-                    if (Instant::now() - self.last_try_to_connect).as_secs() > 1 {
+                    if self.reconnection.is_exhausted() {
+                        tracing::warn!("comms > ws-room max reconnection attempts reached");
+                        return false;
+                    }
+
+                    // the identification and the challenge are done again with the new connection
+                    if self.reconnection.poll_attempt() {
                         let ws_protocols = {
                             let mut v = PackedStringArray::new();
                             v.push(GString::from("rfc5"));
@@ -175,8 +197,7 @@ impl WebSocketRoom {
                         peer.set("supported_protocols".into(), ws_protocols.to_variant());
                         peer.call("connect_to_url".into(), &[self.ws_url.clone().to_variant()]);
 
-                        self.last_try_to_connect = Instant::now();
-                        self.peer_identities.clear();
+                        self.remove_peers();
                         self.from_alias = 0;
                         self.signature = None;
                     }
@@ -216,7 +237,7 @@ impl WebSocketRoom {
 
                                 if !challenge_to_sign.starts_with("dcl-") {
                                     tracing::error!("invalid challenge to sign");
-                                    return;
+                                    return true;
                                 }
 
                                 // TODO: should this block_on be async? the ephemeral wallet is sync
@@ -265,13 +286,18 @@ impl WebSocketRoom {
                     while let Some((packet_length, message)) = get_next_packet(peer.clone()) {
                         match message {
                             ws_packet::Message::WelcomeMessage(welcome_msg) => {
-                                // welcome_msg.
                                 self.state = WsRoomState::WelcomeMessageReceived;
+                                self.reconnection.on_connected();
                                 self.from_alias = welcome_msg.alias;
+                                let mut avatar_scene = self.avatars.clone();
                                 self.peer_identities = HashMap::from_iter(
                                     welcome_msg.peer_identities.into_iter().flat_map(
                                         |(alias, address)| {
-                                            address.as_h160().map(|h160| (alias, Peer::new(h160)))
+                                            address.as_h160().map(|h160| {
+                                                let avatar_alias =
+                                                    avatar_scene.bind_mut().next_alias();
+                                                (alias, Peer::new(h160, avatar_alias))
+                                            })
                                         },
                                     ),
                                 );
@@ -309,10 +335,14 @@ impl WebSocketRoom {
                                     );
                                 }
 
-                                self.avatars.bind_mut().clean();
-                                for (alias, peer) in self.peer_identities.iter() {
+                                for peer in self.peer_identities.values() {
+                                    inspect_peer_connection(
+                                        &self.inspector_label,
+                                        peer.address,
+                                        true,
+                                    );
                                     self.avatars.bind_mut().add_avatar(
-                                        *alias,
+                                        peer.alias,
                                         GString::from(format!("{:#x}", peer.address)),
                                     );
                                 }
@@ -339,9 +369,21 @@ impl WebSocketRoom {
                 }
             },
         }
+
+        true
+    }
+
+    // Only the avatars of this room, the same AvatarScene is used by the scene room
+    fn remove_peers(&mut self) {
+        let mut avatar_scene = self.avatars.bind_mut();
+        for (_, peer) in self.peer_identities.drain() {
+            avatar_scene.remove_avatar(peer.alias);
+        }
     }
 
-    fn _clean(&self) {
+    fn _clean(&mut self) {
+        self.remove_peers();
+
         let mut peer = self.ws_peer.clone();
         peer.close();
         match peer.get_ready_state() {
@@ -364,18 +406,27 @@ impl WebSocketRoom {
                 }
                 ws_packet::Message::PeerJoinMessage(peer) => {
                     if let Some(h160) = peer.address.as_h160() {
-                        self.peer_identities.insert(peer.alias, Peer::new(h160));
+                        inspect_peer_connection(&self.inspector_label, h160, true);
+                        let avatar_alias = self.avatars.bind_mut().next_alias();
+                        if let Some(previous_peer) = self
+                            .peer_identities
+                            .insert(peer.alias, Peer::new(h160, avatar_alias))
+                        {
+                            self.avatars.bind_mut().remove_avatar(previous_peer.alias);
+                        }
                         self.avatars
                             .bind_mut()
-                            .add_avatar(peer.alias, GString::from(format!("{:#x}", h160)));
+                            .add_avatar(avatar_alias, GString::from(format!("{:#x}", h160)));
                         // TODO: message XXX joined
                     } else {
                         // TODO: Invalid address
                     }
                 }
                 ws_packet::Message::PeerLeaveMessage(peer) => {
-                    self.peer_identities.remove(&peer.alias);
-                    self.avatars.bind_mut().remove_avatar(peer.alias);
+                    if let Some(peer) = self.peer_identities.remove(&peer.alias) {
+                        inspect_peer_connection(&self.inspector_label, peer.address, false);
+                        self.avatars.bind_mut().remove_avatar(peer.alias);
+                    }
                     // TODO: message XXX left
                 }
                 ws_packet::Message::PeerUpdateMessage(update) => {
@@ -395,15 +446,18 @@ impl WebSocketRoom {
                         error!("comms > peer not found {:?}", update);
                         continue;
                     };
+                    let avatar_alias = peer.alias;
+                    inspect_received(
+                        &self.inspector_label,
+                        peer.address,
+                        Some(&message),
+                        update.body.len(),
+                    );
 
                     match message {
                         rfc4::packet::Message::Position(position) => {
-                            self.avatars
-                                .bind_mut()
-                                .update_avatar_transform_with_rfc4_position(
-                                    update.from_alias,
-                                    &position,
-                                );
+                            self.avatar_updates
+                                .push((avatar_alias, AvatarUpdate::Position(position)));
                         }
                         rfc4::packet::Message::Chat(chat) => {
                             self.chats.push((peer.address, chat));
@@ -485,10 +539,14 @@ impl WebSocketRoom {
                                 content: serialized_profile.clone(),
                                 base_url: profile_response.base_url.clone(),
                             };
+                            inspect_profile_version(
+                                &self.inspector_label,
+                                peer.address,
+                                incoming_version,
+                            );
 
-                            self.avatars
-                                .bind_mut()
-                                .update_avatar_by_alias(update.from_alias, &profile);
+                            self.avatar_updates
+                                .push((avatar_alias, AvatarUpdate::Profile(profile.clone())));
 
                             self.peer_identities
                                 .get_mut(&update.from_alias)
@@ -504,6 +562,15 @@ impl WebSocketRoom {
                             // TODO: should we limit the size of the queue or accumulated bytes?
                             entry.push((peer.address, scene.data));
                         }
+                        rfc4::packet::Message::PlayerEmote(player_emote) => {
+                            self.avatar_updates.push((
+                                avatar_alias,
+                                AvatarUpdate::Emote {
+                                    incremental_id: player_emote.incremental_id,
+                                    urn: player_emote.urn,
+                                },
+                            ));
+                        }
                         rfc4::packet::Message::Voice(_voice) => {}
                         _ => {
                             tracing::error!("comms > unknown message");
@@ -595,8 +662,12 @@ fn get_next_packet(mut peer: Gd<WebSocketPeer>) -> Option<(usize, ws_packet::Mes
 
 impl Adapter for WebSocketRoom {
     fn poll(&mut self) -> bool {
-        self._poll();
-        true
+        self._poll()
+    }
+
+    fn connection_state(&self) -> ConnectionState {
+        self.reconnection
+            .get_state(matches!(self.state, WsRoomState::WelcomeMessageReceived))
     }
 
     fn clean(&mut self) {
@@ -611,10 +682,19 @@ impl Adapter for WebSocketRoom {
         self._consume_chats()
     }
 
+    fn consume_avatar_updates(&mut self) -> Vec<(u32, AvatarUpdate)> {
+        std::mem::take(&mut self.avatar_updates)
+    }
+
     fn send_rfc4(&mut self, packet: rfc4::Packet, unreliable: bool) -> bool {
         self._send_rfc4(packet, unreliable)
     }
 
+    // The rfc5 peer updates are relayed to the whole room
+    fn send_rfc4_to(&mut self, _packet: rfc4::Packet, _address: H160) -> bool {
+        false
+    }
+
     fn broadcast_voice(&mut self, _frame: Vec<i16>) {}
 
     fn support_voice_chat(&self) -> bool {
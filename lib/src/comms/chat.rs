@@ -0,0 +1,532 @@
+use std::collections::{HashMap, VecDeque};
+
+use ethers_core::types::H160;
+
+use crate::dcl::components::proto_components::kernel::comms::rfc4;
+
+use super::adapter::adapter_trait::Adapter;
+
+// Same convention as the emote (␐), ping (␑) and ack (␆) messages: the
+//  channel and private messages are regular rfc4 chats with a prefix. The
+//  channel messages are broadcast, so the clients that don't support them
+//  still get the text. The private ones are only sent to the recipient.
+const ENVELOPE_PREFIX: char = '␂';
+const CONTROL_PREFIXES: [char; 3] = ['␐', '␑', '␆'];
+
+const MAX_HISTORY_ENTRIES: usize = 1000;
+const MAX_CHANNEL_NAME_LENGTH: usize = 32;
+
+pub const NEARBY_CHANNEL: &str = "nearby";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChatTarget {
+    Nearby,
+    Channel(String),
+    // sent with `Adapter::send_rfc4_to`, it fails when the transport can't
+    //  target the peer (ws-room) or the peer isn't connected
+    Private(H160),
+}
+
+impl ChatTarget {
+    // "nearby", "#channel" or "@0x..."
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.is_empty() || value == NEARBY_CHANNEL {
+            return Some(Self::Nearby);
+        }
+
+        if let Some(channel) = value.strip_prefix('#') {
+            return is_valid_channel_name(channel).then(|| Self::Channel(channel.to_lowercase()));
+        }
+
+        let address = value.strip_prefix('@').unwrap_or(value);
+        address.parse::<H160>().ok().map(Self::Private)
+    }
+
+    pub fn to_key(&self) -> String {
+        match self {
+            Self::Nearby => NEARBY_CHANNEL.to_string(),
+            Self::Channel(channel) => format!("#{channel}"),
+            Self::Private(address) => format!("@{:#x}", address),
+        }
+    }
+}
+
+fn is_valid_channel_name(channel: &str) -> bool {
+    !channel.is_empty()
+        && channel.len() <= MAX_CHANNEL_NAME_LENGTH
+        && channel
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn encode_chat_message(target: &ChatTarget, text: &str) -> String {
+    match target {
+        ChatTarget::Nearby => text.to_string(),
+        _ => format!("{ENVELOPE_PREFIX}{} {text}", target.to_key()),
+    }
+}
+
+// A malformed envelope is shown as a nearby message
+pub fn decode_chat_message(message: &str) -> (ChatTarget, String) {
+    let Some(envelope) = message.strip_prefix(ENVELOPE_PREFIX) else {
+        return (ChatTarget::Nearby, message.to_string());
+    };
+
+    let (target, text) = envelope.split_once(' ').unwrap_or((envelope, ""));
+    match ChatTarget::parse(target) {
+        Some(target) if target != ChatTarget::Nearby => (target, text.to_string()),
+        _ => (ChatTarget::Nearby, message.to_string()),
+    }
+}
+
+// Emotes, pings and acks travel as chats but they are not kept in the history
+pub fn is_control_message(message: &str) -> bool {
+    message.starts_with(CONTROL_PREFIXES)
+}
+
+// The private messages go to the recipient through the island adapter or, when it
+//  can't reach the peer, through the scene room. Returns false when it wasn't sent
+pub fn send_chat(
+    island_adapter: Option<&mut dyn Adapter>,
+    scene_room_adapter: Option<&mut dyn Adapter>,
+    target: &ChatTarget,
+    text: &str,
+) -> bool {
+    let get_packet = || rfc4::Packet {
+        message: Some(rfc4::packet::Message::Chat(rfc4::Chat {
+            message: encode_chat_message(target, text),
+            timestamp: 0.0,
+        })),
+        protocol_version: 0,
+    };
+
+    // private messages are only sent to the recipient, never broadcast
+    let send = |adapter: &mut dyn Adapter| match target {
+        ChatTarget::Private(address) => adapter.send_rfc4_to(get_packet(), *address),
+        _ => adapter.send_rfc4(get_packet(), false),
+    };
+
+    let sent = island_adapter.map_or(false, send);
+    if sent || !matches!(target, ChatTarget::Private(_)) {
+        return sent;
+    }
+
+    // the recipient can be only in the scene room
+    scene_room_adapter.map_or(false, send)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatEntry {
+    pub id: u64,
+    pub address: H160,
+    // local unix time, in seconds
+    pub timestamp: f64,
+    // the target key, for the private messages it's the other party
+    pub channel: String,
+    pub message: String,
+    pub outgoing: bool,
+}
+
+// Chats of the current session, the oldest ones are dropped
+#[derive(Default)]
+pub struct ChatHistory {
+    entries: VecDeque<ChatEntry>,
+    next_id: u64,
+}
+
+impl ChatHistory {
+    pub fn push(
+        &mut self,
+        address: H160,
+        timestamp: f64,
+        channel: String,
+        message: String,
+        outgoing: bool,
+    ) -> &ChatEntry {
+        self.next_id += 1;
+        self.entries.push_back(ChatEntry {
+            id: self.next_id,
+            address,
+            timestamp,
+            channel,
+            message,
+            outgoing,
+        });
+
+        while self.entries.len() > MAX_HISTORY_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.back().unwrap()
+    }
+
+    // An empty `channel` returns the entries of every channel
+    pub fn entries_after(&self, channel: &str, after_id: u64) -> Vec<&ChatEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.id > after_id)
+            .filter(|entry| channel.is_empty() || entry.channel == channel)
+            .collect()
+    }
+
+    pub fn channels(&self) -> Vec<String> {
+        let mut channels: Vec<String> = Vec::new();
+        for entry in self.entries.iter() {
+            if !channels.contains(&entry.channel) {
+                channels.push(entry.channel.clone());
+            }
+        }
+        channels
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChatRateLimitSettings {
+    pub max_messages: usize,
+    // in seconds
+    pub window: f64,
+    // the same text repeated by a sender in this time is dropped, in seconds
+    pub duplicated_window: f64,
+    pub max_message_length: usize,
+}
+
+impl Default for ChatRateLimitSettings {
+    fn default() -> Self {
+        Self {
+            max_messages: 5,
+            window: 5.0,
+            duplicated_window: 10.0,
+            max_message_length: 1000,
+        }
+    }
+}
+
+#[derive(Default)]
+struct SenderState {
+    // times of the accepted messages inside the window
+    times: VecDeque<f64>,
+    last_message: String,
+    last_message_time: f64,
+}
+
+// Spam filter for the incoming chats, per sender
+#[derive(Default)]
+pub struct ChatRateLimiter {
+    settings: ChatRateLimitSettings,
+    senders: HashMap<H160, SenderState>,
+}
+
+impl ChatRateLimiter {
+    pub fn set_settings(&mut self, settings: ChatRateLimitSettings) {
+        self.settings = settings;
+    }
+
+    pub fn settings(&self) -> &ChatRateLimitSettings {
+        &self.settings
+    }
+
+    // `text` is the decoded chat text, the duplicates are checked on it so the same
+    //  text sent to several channels is also dropped. The control messages (emotes,
+    //  pings and acks) are not chat text, they're never limited
+    pub fn allow_chat(&mut self, address: H160, now: f64, text: &str) -> bool {
+        is_control_message(text) || self.allow(address, now, text)
+    }
+
+    // `now` is in seconds. Returns false when the message has to be dropped
+    fn allow(&mut self, address: H160, now: f64, message: &str) -> bool {
+        if message.trim().is_empty() || message.chars().count() > self.settings.max_message_length {
+            return false;
+        }
+
+        let window = self.settings.window;
+        let sender = self.senders.entry(address).or_default();
+        while sender
+            .times
+            .front()
+            .map_or(false, |time| now - time >= window)
+        {
+            sender.times.pop_front();
+        }
+
+        if sender.last_message == message
+            && now - sender.last_message_time < self.settings.duplicated_window
+        {
+            return false;
+        }
+
+        if sender.times.len() >= self.settings.max_messages {
+            return false;
+        }
+
+        sender.times.push_back(now);
+        sender.last_message = message.to_string();
+        sender.last_message_time = now;
+
+        if self.senders.len() > 256 {
+            let forget_after = window.max(self.settings.duplicated_window);
+            self.senders
+                .retain(|_, sender| now - sender.last_message_time < forget_after);
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.senders.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comms::{
+        adapter::{adapter_trait::AvatarUpdate, reconnection::ConnectionState},
+        profile::UserProfile,
+    };
+
+    use super::*;
+
+    // Records the sent packets, the directed ones fail when it can't target peers
+    #[derive(Default)]
+    struct TestAdapter {
+        can_target_peers: bool,
+        sent: Vec<(rfc4::Packet, Option<H160>)>,
+    }
+
+    impl Adapter for TestAdapter {
+        fn poll(&mut self) -> bool {
+            true
+        }
+
+        fn connection_state(&self) -> ConnectionState {
+            ConnectionState::Connected
+        }
+
+        fn clean(&mut self) {}
+
+        fn consume_chats(&mut self) -> Vec<(H160, rfc4::Chat)> {
+            Vec::new()
+        }
+
+        fn consume_avatar_updates(&mut self) -> Vec<(u32, AvatarUpdate)> {
+            Vec::new()
+        }
+
+        fn consume_scene_messages(&mut self, _scene_id: &str) -> Vec<(H160, Vec<u8>)> {
+            Vec::new()
+        }
+
+        fn change_profile(&mut self, _new_profile: UserProfile) {}
+
+        fn send_rfc4(&mut self, packet: rfc4::Packet, _unreliable: bool) -> bool {
+            self.sent.push((packet, None));
+            true
+        }
+
+        fn send_rfc4_to(&mut self, packet: rfc4::Packet, address: H160) -> bool {
+            if !self.can_target_peers {
+                return false;
+            }
+            self.sent.push((packet, Some(address)));
+            true
+        }
+
+        fn broadcast_voice(&mut self, _frame: Vec<i16>) {}
+
+        fn support_voice_chat(&self) -> bool {
+            false
+        }
+    }
+
+    fn address(n: u64) -> H160 {
+        H160::from_low_u64_be(n)
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let nearby = encode_chat_message(&ChatTarget::Nearby, "hello");
+        assert_eq!(nearby, "hello");
+        assert_eq!(
+            decode_chat_message(&nearby),
+            (ChatTarget::Nearby, "hello".to_string())
+        );
+
+        let channel = ChatTarget::parse("#Trade").unwrap();
+        let encoded = encode_chat_message(&channel, "selling a hat");
+        assert_eq!(
+            decode_chat_message(&encoded),
+            (ChatTarget::Channel("trade".into()), "selling a hat".into())
+        );
+
+        let private = ChatTarget::Private(address(7));
+        let encoded = encode_chat_message(&private, "hi there");
+        assert_eq!(decode_chat_message(&encoded), (private, "hi there".into()));
+    }
+
+    #[test]
+    fn test_malformed_envelope() {
+        assert_eq!(
+            decode_chat_message("␂#not!valid text"),
+            (ChatTarget::Nearby, "␂#not!valid text".into())
+        );
+        assert_eq!(
+            decode_chat_message("␂@0x123 text"),
+            (ChatTarget::Nearby, "␂@0x123 text".into())
+        );
+        assert!(ChatTarget::parse("#").is_none());
+        assert_eq!(ChatTarget::parse(""), Some(ChatTarget::Nearby));
+    }
+
+    #[test]
+    fn test_control_messages() {
+        assert!(is_control_message("␐wave 1234"));
+        assert!(is_control_message("␑"));
+        assert!(!is_control_message("hello"));
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = ChatHistory::default();
+        history.push(address(1), 1.0, "nearby".into(), "a".into(), false);
+        history.push(address(2), 2.0, "#trade".into(), "b".into(), false);
+        let last_id = history
+            .push(address(1), 3.0, "nearby".into(), "c".into(), true)
+            .id;
+
+        assert_eq!(history.entries_after("", 0).len(), 3);
+        assert_eq!(history.entries_after("nearby", 0).len(), 2);
+        assert_eq!(history.entries_after("", 1).len(), 2);
+        assert!(history.entries_after("", last_id).is_empty());
+        assert_eq!(history.channels(), vec!["nearby", "#trade"]);
+
+        for i in 0..MAX_HISTORY_ENTRIES {
+            history.push(address(1), i as f64, "nearby".into(), "x".into(), false);
+        }
+        assert_eq!(history.len(), MAX_HISTORY_ENTRIES);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut limiter = ChatRateLimiter::default();
+        for i in 0..5 {
+            assert!(limiter.allow(address(1), i as f64 * 0.1, &format!("message {i}")));
+        }
+        assert!(!limiter.allow(address(1), 0.6, "one more"));
+        // other senders are not affected
+        assert!(limiter.allow(address(2), 0.6, "one more"));
+        // the window moved
+        assert!(limiter.allow(address(1), 5.05, "one more"));
+    }
+
+    #[test]
+    fn test_spam_filter() {
+        let mut limiter = ChatRateLimiter::default();
+        assert!(limiter.allow(address(1), 0.0, "spam"));
+        assert!(!limiter.allow(address(1), 1.0, "spam"));
+        assert!(limiter.allow(address(1), 11.0, "spam"));
+
+        assert!(!limiter.allow(address(1), 12.0, "   "));
+        assert!(!limiter.allow(address(1), 12.0, &"a".repeat(1001)));
+    }
+
+    #[test]
+    fn test_control_messages_are_not_limited() {
+        let mut limiter = ChatRateLimiter::default();
+        for i in 0..10 {
+            assert!(limiter.allow_chat(address(1), i as f64 * 0.1, "␐wave 1234"));
+        }
+        // the emotes didn't use the quota of the sender
+        for i in 0..5 {
+            assert!(limiter.allow_chat(address(1), 1.0, &format!("message {i}")));
+        }
+    }
+
+    #[test]
+    fn test_duplicates_are_checked_on_the_decoded_text() {
+        let mut limiter = ChatRateLimiter::default();
+        let (_, text) = decode_chat_message(&encode_chat_message(
+            &ChatTarget::parse("#a").unwrap(),
+            "spam",
+        ));
+        assert!(limiter.allow_chat(address(1), 0.0, &text));
+        let (_, text) = decode_chat_message(&encode_chat_message(
+            &ChatTarget::parse("#b").unwrap(),
+            "spam",
+        ));
+        assert!(!limiter.allow_chat(address(1), 1.0, &text));
+    }
+
+    #[test]
+    fn test_private_chat_falls_back_to_the_scene_room() {
+        let mut island = TestAdapter::default();
+        let mut scene_room = TestAdapter {
+            can_target_peers: true,
+            ..Default::default()
+        };
+
+        let target = ChatTarget::Private(address(7));
+        assert!(send_chat(
+            Some(&mut island),
+            Some(&mut scene_room),
+            &target,
+            "hi"
+        ));
+        assert!(island.sent.is_empty());
+        assert_eq!(scene_room.sent.len(), 1);
+        assert_eq!(scene_room.sent[0].1, Some(address(7)));
+
+        // without scene room the message is not sent
+        assert!(!send_chat(Some(&mut island), None, &target, "hi"));
+
+        // the broadcast messages never use the scene room
+        assert!(send_chat(
+            Some(&mut island),
+            Some(&mut scene_room),
+            &ChatTarget::Nearby,
+            "hi"
+        ));
+        assert_eq!(island.sent.len(), 1);
+        assert_eq!(scene_room.sent.len(), 1);
+    }
+
+    // The simulator must report the result of the inner adapter, a directed
+    //  packet that couldn't be delivered goes through the scene room
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_private_chat_falls_back_behind_the_network_simulator() {
+        use crate::comms::adapter::network_simulator::{
+            wrap_adapter, NetworkConditions, GLOBAL_NETWORK_CONDITIONS,
+        };
+
+        *GLOBAL_NETWORK_CONDITIONS.write().unwrap() = NetworkConditions {
+            latency_ms: 100.0,
+            ..Default::default()
+        };
+
+        let mut island = wrap_adapter(Box::<TestAdapter>::default());
+        let mut scene_room = TestAdapter {
+            can_target_peers: true,
+            ..Default::default()
+        };
+        let sent = send_chat(
+            Some(island.as_mut()),
+            Some(&mut scene_room),
+            &ChatTarget::Private(address(7)),
+            "hi",
+        );
+
+        *GLOBAL_NETWORK_CONDITIONS.write().unwrap() = NetworkConditions::default();
+
+        assert!(sent);
+        assert_eq!(scene_room.sent.len(), 1);
+    }
+}
@@ -4,13 +4,25 @@ use http::Uri;
 
 use crate::{
     comms::{adapter::ws_room::WebSocketRoom, signed_login::SignedLoginMeta},
-    dcl::components::proto_components::kernel::comms::rfc4,
+    dcl::{components::proto_components::kernel::comms::rfc4, SceneId},
     godot_classes::dcl_global::DclGlobal,
 };
 
 use super::{
-    adapter::adapter_trait::Adapter,
+    adapter::{
+        adapter_trait::{apply_avatar_updates, Adapter},
+        network_simulator::{wrap_adapter, NetworkConditions, GLOBAL_NETWORK_CONDITIONS},
+        reconnection::{ConnectionState, Reconnection, ReconnectionPolicy},
+    },
+    chat::{
+        decode_chat_message, is_control_message, send_chat, ChatHistory, ChatRateLimitSettings,
+        ChatRateLimiter, ChatTarget, NEARBY_CHANNEL,
+    },
+    scene_room::{SceneRoom, DEFAULT_SCENE_ROOM_GATEKEEPER_URL},
     signed_login::{SignedLogin, SignedLoginPollStatus},
+    voice_processing::{
+        get_voice_chat_settings, VoiceActivityDetector, VoiceChatMode, GLOBAL_VOICE_CHAT_SETTINGS,
+    },
 };
 
 #[cfg(feature = "use_livekit")]
@@ -20,6 +32,7 @@ use crate::comms::adapter::{archipelago::ArchipelagoManager, livekit::LivekitRoo
 enum CommsConnection {
     None,
     WaitingForIdentity(String),
+    WaitingForReconnection(String),
     SignedLogin(SignedLogin),
     #[cfg(feature = "use_livekit")]
     Archipelago(ArchipelagoManager),
@@ -32,7 +45,25 @@ pub struct CommunicationManager {
     current_connection: CommsConnection,
     current_connection_str: String,
     last_position_broadcast_index: u64,
+    last_emote_incremental_id: u32,
     voice_chat_enabled: bool,
+    voice_activity_detector: VoiceActivityDetector,
+    chat_history: ChatHistory,
+    chat_rate_limiter: ChatRateLimiter,
+
+    // The signed login is done again when its adapter can't reconnect, the
+    //  access token could be expired
+    signed_login_adapter_str: Option<String>,
+    reconnection: Reconnection,
+    connection_state: ConnectionState,
+
+    // Alongside the island connection, for the current parcel scene
+    scene_room: Option<SceneRoom>,
+    // The scene room is joined again for it when the island connection changes
+    current_scene: Option<(String, Vector2i)>,
+    // Empty to disable the scene rooms
+    #[var]
+    scene_room_gatekeeper_url: GString,
 
     #[base]
     base: Base<Node>,
@@ -45,7 +76,17 @@ impl INode for CommunicationManager {
             current_connection: CommsConnection::None,
             current_connection_str: String::default(),
             last_position_broadcast_index: 0,
+            last_emote_incremental_id: 0,
             voice_chat_enabled: false,
+            voice_activity_detector: VoiceActivityDetector::default(),
+            chat_history: ChatHistory::default(),
+            chat_rate_limiter: ChatRateLimiter::default(),
+            signed_login_adapter_str: None,
+            reconnection: Reconnection::new(ReconnectionPolicy::default()),
+            connection_state: ConnectionState::Disconnected,
+            scene_room: None,
+            current_scene: None,
+            scene_room_gatekeeper_url: DEFAULT_SCENE_ROOM_GATEKEEPER_URL.into(),
             base,
         }
     }
@@ -55,6 +96,8 @@ impl INode for CommunicationManager {
     }
 
     fn process(&mut self, _dt: f64) {
+        let mut incoming_chats = Vec::new();
+        let mut avatar_updates = Vec::new();
         match &mut self.current_connection {
             CommsConnection::None => {}
             CommsConnection::WaitingForIdentity(adapter_url) => {
@@ -65,58 +108,229 @@ impl INode for CommunicationManager {
                         .call_deferred("change_adapter".into(), &[adapter_url.to_variant()]);
                 }
             }
+            CommsConnection::WaitingForReconnection(adapter_str) => {
+                if self.reconnection.is_exhausted() {
+                    tracing::warn!("comms > max reconnection attempts reached");
+                    self.current_connection = CommsConnection::None;
+                } else if self.reconnection.poll_attempt() {
+                    self.base
+                        .call_deferred("change_adapter".into(), &[adapter_str.to_variant()]);
+                }
+            }
             CommsConnection::SignedLogin(signed_login) => match signed_login.poll() {
                 SignedLoginPollStatus::Pending => {}
                 SignedLoginPollStatus::Complete(response) => {
+                    let signed_login_adapter_str = self.signed_login_adapter_str.take();
                     self.change_adapter(response.fixed_adapter.unwrap_or("offline".into()).into());
+                    self.signed_login_adapter_str = signed_login_adapter_str;
                 }
                 SignedLoginPollStatus::Error(e) => {
                     tracing::info!("Error in signed login: {:?}", e);
-                    self.current_connection = CommsConnection::None;
+                    self.reauthenticate();
                 }
             },
             #[cfg(feature = "use_livekit")]
             CommsConnection::Archipelago(archipelago) => {
-                archipelago.poll();
+                // the island adapter is polled and reconnected by the archipelago
+                let archipelago_polling_ok = archipelago.poll();
                 if let Some(adapter) = archipelago.adapter_as_mut() {
-                    let adapter = adapter.as_mut();
-                    let adapter_polling_ok = adapter.poll();
-                    let chats = adapter.consume_chats();
-
-                    if !chats.is_empty() {
-                        let chats_variant_array = get_chat_array(chats);
-                        self.base.emit_signal(
-                            "chat_message".into(),
-                            &[chats_variant_array.to_variant()],
-                        );
-                    }
+                    incoming_chats.extend(adapter.consume_chats());
+                    avatar_updates.extend(adapter.consume_avatar_updates());
+                }
 
-                    if !adapter_polling_ok {
-                        self.current_connection = CommsConnection::None;
-                    }
+                if !archipelago_polling_ok {
+                    self.current_connection = CommsConnection::None;
                 }
             }
             CommsConnection::Connected(adapter) => {
                 let adapter = adapter.as_mut();
                 let adapter_polling_ok = adapter.poll();
-                let chats = adapter.consume_chats();
-
-                if !chats.is_empty() {
-                    let chats_variant_array = get_chat_array(chats);
-                    self.base
-                        .emit_signal("chat_message".into(), &[chats_variant_array.to_variant()]);
-                }
+                incoming_chats.extend(adapter.consume_chats());
+                avatar_updates.extend(adapter.consume_avatar_updates());
 
                 if !adapter_polling_ok {
-                    self.current_connection = CommsConnection::None;
+                    self.reauthenticate();
                 }
             }
         }
+
+        if let Some(scene_room) = self.scene_room.as_mut() {
+            let scene_room_polling_ok = scene_room.poll();
+            incoming_chats.extend(scene_room.consume_chats());
+            avatar_updates.extend(scene_room.consume_avatar_updates());
+
+            if !scene_room_polling_ok {
+                scene_room.clean();
+                self.scene_room = None;
+            }
+        }
+
+        if !avatar_updates.is_empty() {
+            let mut avatar_scene = DclGlobal::singleton().bind().get_avatars();
+            apply_avatar_updates(&mut avatar_scene.bind_mut(), avatar_updates);
+        }
+
+        self.on_chats_received(incoming_chats);
+
+        let connection_state = self.get_connection_state();
+        if connection_state != self.connection_state {
+            if connection_state == ConnectionState::Connected {
+                self.reconnection.on_connected();
+                self.retry_scene_room();
+            }
+            self.connection_state = connection_state;
+            self.base.emit_signal(
+                "connection_state_changed".into(),
+                &[(connection_state as i32).to_variant()],
+            );
+        }
     }
 }
 
 impl CommunicationManager {
+    // Filters, decodes and records the chats received from every room
+    fn on_chats_received(&mut self, chats: Vec<(H160, rfc4::Chat)>) {
+        let chats = filter_chats(chats);
+        if chats.is_empty() {
+            return;
+        }
+
+        let own_address = DclGlobal::singleton()
+            .bind()
+            .get_player_identity()
+            .bind()
+            .try_get_address();
+        let now = get_unix_time();
+
+        let mut chats_variant_array = VariantArray::new();
+        for (address, chat) in chats {
+            let (target, text) = decode_chat_message(&chat.message);
+            let channel = match target {
+                ChatTarget::Private(recipient) => {
+                    // checked before the rate limit, it doesn't count for the sender
+                    if own_address != Some(recipient) {
+                        continue;
+                    }
+                    ChatTarget::Private(address).to_key()
+                }
+                target => target.to_key(),
+            };
+
+            if !self.chat_rate_limiter.allow_chat(address, now, &text) {
+                tracing::debug!("chat from {:#x} dropped by the rate limit", address);
+                continue;
+            }
+
+            if !is_control_message(&text) {
+                self.chat_history
+                    .push(address, now, channel.clone(), text.clone(), false);
+            }
+            chats_variant_array.push(get_chat_variant(address, chat.timestamp, text, channel));
+        }
+
+        if !chats_variant_array.is_empty() {
+            self.base
+                .emit_signal("chat_message".into(), &[chats_variant_array.to_variant()]);
+        }
+    }
+
+    fn reauthenticate(&mut self) {
+        self.current_connection = match self.signed_login_adapter_str.clone() {
+            Some(adapter_str) if !self.reconnection.is_exhausted() => {
+                CommsConnection::WaitingForReconnection(adapter_str)
+            }
+            _ => CommsConnection::None,
+        };
+    }
+
+    fn get_connection_state(&self) -> ConnectionState {
+        let connection_state = match &self.current_connection {
+            CommsConnection::None => ConnectionState::Disconnected,
+            CommsConnection::WaitingForIdentity(_) | CommsConnection::SignedLogin(_) => {
+                ConnectionState::Connecting
+            }
+            CommsConnection::WaitingForReconnection(_) => ConnectionState::Reconnecting,
+            CommsConnection::Connected(adapter) => adapter.connection_state(),
+            #[cfg(feature = "use_livekit")]
+            CommsConnection::Archipelago(archipelago) => archipelago.connection_state(),
+        };
+
+        // a new signed login after losing the connection
+        if connection_state == ConnectionState::Connecting && self.reconnection.has_connected() {
+            ConnectionState::Reconnecting
+        } else {
+            connection_state
+        }
+    }
+
+    fn get_scene_room_adapter(&mut self, scene_id: &str) -> Option<&mut Box<dyn Adapter>> {
+        self.scene_room
+            .as_mut()
+            .filter(|scene_room| scene_room.scene_entity_id() == scene_id)?
+            .adapter_as_mut()
+    }
+
+    fn change_scene_room(&mut self, scene: Option<(String, Vector2i)>) {
+        self.current_scene.clone_from(&scene);
+
+        let current_scene_entity_id = self
+            .scene_room
+            .as_ref()
+            .map(|scene_room| scene_room.scene_entity_id());
+        let new_scene_entity_id = scene.as_ref().map(|(id, _)| id.as_str());
+        if current_scene_entity_id == new_scene_entity_id {
+            return;
+        }
+
+        if let Some(mut scene_room) = self.scene_room.take() {
+            scene_room.clean();
+        }
+
+        let Some((scene_entity_id, base_parcel)) = scene else {
+            return;
+        };
+
+        // only in realms with comms
+        if matches!(
+            self.current_connection,
+            CommsConnection::None | CommsConnection::WaitingForIdentity(_)
+        ) || self.scene_room_gatekeeper_url.is_empty()
+        {
+            return;
+        }
+
+        self.scene_room = SceneRoom::new(
+            &self.scene_room_gatekeeper_url.to_string(),
+            scene_entity_id,
+            base_parcel,
+        );
+    }
+
+    // The scene room can't be joined without an island connection, or it was
+    //  dropped with the previous one
+    fn retry_scene_room(&mut self) {
+        if self.scene_room.is_none() && self.current_scene.is_some() {
+            self.change_scene_room(self.current_scene.clone());
+        }
+    }
+
+    // The scene room is used once it's connected, the island room until then
     pub fn send_scene_message(&mut self, scene_id: String, data: Vec<u8>) {
+        if self
+            .scene_room
+            .as_ref()
+            .map_or(false, |scene_room| scene_room.is_connected())
+        {
+            if let Some(adapter) = self.get_scene_room_adapter(&scene_id) {
+                let scene_message = rfc4::Packet {
+                    message: Some(rfc4::packet::Message::Scene(rfc4::Scene { scene_id, data })),
+                    protocol_version: 0,
+                };
+                adapter.send_rfc4(scene_message, true);
+                return;
+            }
+        }
+
         let scene_message = rfc4::Packet {
             message: Some(rfc4::packet::Message::Scene(rfc4::Scene { scene_id, data })),
             protocol_version: 0,
@@ -136,7 +350,12 @@ impl CommunicationManager {
     }
 
     pub fn get_pending_messages(&mut self, scene_id: &str) -> Vec<(H160, Vec<u8>)> {
-        match &mut self.current_connection {
+        let mut messages = self
+            .get_scene_room_adapter(scene_id)
+            .map(|adapter| adapter.consume_scene_messages(scene_id))
+            .unwrap_or_default();
+
+        let island_messages = match &mut self.current_connection {
             CommsConnection::Connected(adapter) => adapter.consume_scene_messages(scene_id),
             #[cfg(feature = "use_livekit")]
             CommsConnection::Archipelago(archipelago) => {
@@ -147,7 +366,10 @@ impl CommunicationManager {
                 }
             }
             _ => vec![],
-        }
+        };
+
+        messages.extend(island_messages);
+        messages
     }
 }
 
@@ -159,6 +381,15 @@ impl CommunicationManager {
     #[signal]
     fn on_adapter_changed(voice_chat_enabled: bool, new_adapter: GString) {}
 
+    // 0 = disconnected, 1 = connecting, 2 = connected, 3 = reconnecting
+    #[signal]
+    fn connection_state_changed(connection_state: i32) {}
+
+    #[func]
+    fn get_connection_state_id(&self) -> i32 {
+        self.connection_state as i32
+    }
+
     #[func]
     fn broadcast_voice(&mut self, frame: PackedVector2Array) {
         let adapter = match &mut self.current_connection {
@@ -179,21 +410,26 @@ impl CommunicationManager {
             return;
         }
 
-        let mut max_value = 0;
-        let vec = frame
+        // the silence is not sent, the peers stop receiving frames instead
+        let samples = frame
             .as_slice()
             .iter()
-            .map(|v| {
-                let value = ((0.5 * (v.x + v.y)) * i16::MAX as f32) as i16;
+            .map(|v| 0.5 * (v.x + v.y))
+            .collect::<Vec<f32>>();
+        let settings = get_voice_chat_settings();
+        if !self.voice_activity_detector.process(
+            &samples,
+            settings.vad_threshold,
+            settings.vad_hangover_frames,
+        ) {
+            return;
+        }
 
-                max_value = std::cmp::max(max_value, value);
-                value
-            })
+        let vec = samples
+            .into_iter()
+            .map(|value| (value * i16::MAX as f32) as i16)
             .collect::<Vec<i16>>();
-
-        if max_value > 0 {
-            adapter.broadcast_voice(vec);
-        }
+        adapter.broadcast_voice(vec);
     }
 
     #[func]
@@ -201,6 +437,25 @@ impl CommunicationManager {
         self.voice_chat_enabled
     }
 
+    // The scenes send and receive their messages with the rpc, these are used
+    //  by the comms test (src/test/comms_test.gd)
+    #[func]
+    fn send_scene_message_bytes(&mut self, scene_id: GString, data: PackedByteArray) {
+        self.send_scene_message(scene_id.to_string(), data.to_vec());
+    }
+
+    #[func]
+    fn consume_scene_messages_bytes(&mut self, scene_id: GString) -> VariantArray {
+        let mut messages = VariantArray::new();
+        for (address, data) in self.get_pending_messages(&scene_id.to_string()) {
+            let mut message = VariantArray::new();
+            message.push(format!("{:#x}", address).to_variant());
+            message.push(PackedByteArray::from(data.as_slice()).to_variant());
+            messages.push(message.to_variant());
+        }
+        messages
+    }
+
     #[func]
     fn broadcast_position_and_rotation(&mut self, position: Vector3, rotation: Quaternion) -> bool {
         let index = self.last_position_broadcast_index;
@@ -225,7 +480,8 @@ impl CommunicationManager {
         let message_sent = match &mut self.current_connection {
             CommsConnection::None
             | CommsConnection::SignedLogin(_)
-            | CommsConnection::WaitingForIdentity(_) => false,
+            | CommsConnection::WaitingForIdentity(_)
+            | CommsConnection::WaitingForReconnection(_) => false,
             CommsConnection::Connected(adapter) => adapter.send_rfc4(get_packet(), true),
             #[cfg(feature = "use_livekit")]
             CommsConnection::Archipelago(archipelago) => {
@@ -238,6 +494,15 @@ impl CommunicationManager {
             }
         };
 
+        // the players of the scene room could be in other islands
+        if let Some(adapter) = self
+            .scene_room
+            .as_mut()
+            .and_then(|scene_room| scene_room.adapter_as_mut())
+        {
+            adapter.send_rfc4(get_packet(), true);
+        }
+
         if message_sent {
             self.last_position_broadcast_index += 1;
         }
@@ -246,18 +511,66 @@ impl CommunicationManager {
 
     #[func]
     fn send_chat(&mut self, text: GString) -> bool {
+        self.send_chat_to(NEARBY_CHANNEL.into(), text)
+    }
+
+    // `target` is "nearby", "#channel" or "@0x..." for a private message
+    #[func]
+    fn send_chat_to(&mut self, target: GString, text: GString) -> bool {
+        let Some(target) = ChatTarget::parse(&target.to_string()) else {
+            tracing::warn!("invalid chat target {target}");
+            return false;
+        };
+
+        let text = text.to_string();
+        let island_adapter: Option<&mut dyn Adapter> = match &mut self.current_connection {
+            CommsConnection::None
+            | CommsConnection::SignedLogin(_)
+            | CommsConnection::WaitingForIdentity(_)
+            | CommsConnection::WaitingForReconnection(_) => None,
+            CommsConnection::Connected(adapter) => Some(adapter.as_mut()),
+            #[cfg(feature = "use_livekit")]
+            CommsConnection::Archipelago(archipelago) => archipelago
+                .adapter_as_mut()
+                .map(|adapter| adapter.as_mut() as &mut dyn Adapter),
+        };
+        let scene_room_adapter: Option<&mut dyn Adapter> = self
+            .scene_room
+            .as_mut()
+            .and_then(|scene_room| scene_room.adapter_as_mut())
+            .map(|adapter| adapter.as_mut() as &mut dyn Adapter);
+
+        let sent = send_chat(island_adapter, scene_room_adapter, &target, &text);
+
+        if sent && !is_control_message(&text) {
+            let own_address = DclGlobal::singleton()
+                .bind()
+                .get_player_identity()
+                .bind()
+                .try_get_address()
+                .unwrap_or_default();
+            self.chat_history
+                .push(own_address, get_unix_time(), target.to_key(), text, true);
+        }
+        sent
+    }
+
+    #[func]
+    fn broadcast_player_emote(&mut self, emote_urn: GString) -> bool {
+        let incremental_id = self.last_emote_incremental_id + 1;
         let get_packet = || rfc4::Packet {
-            message: Some(rfc4::packet::Message::Chat(rfc4::Chat {
-                message: text.to_string(),
-                timestamp: 0.0,
+            message: Some(rfc4::packet::Message::PlayerEmote(rfc4::PlayerEmote {
+                incremental_id,
+                urn: emote_urn.to_string(),
             })),
             protocol_version: 0,
         };
 
-        match &mut self.current_connection {
+        let message_sent = match &mut self.current_connection {
             CommsConnection::None
             | CommsConnection::SignedLogin(_)
-            | CommsConnection::WaitingForIdentity(_) => false,
+            | CommsConnection::WaitingForIdentity(_)
+            | CommsConnection::WaitingForReconnection(_) => false,
             CommsConnection::Connected(adapter) => adapter.send_rfc4(get_packet(), false),
             #[cfg(feature = "use_livekit")]
             CommsConnection::Archipelago(archipelago) => {
@@ -267,7 +580,20 @@ impl CommunicationManager {
                     false
                 }
             }
+        };
+
+        if let Some(adapter) = self
+            .scene_room
+            .as_mut()
+            .and_then(|scene_room| scene_room.adapter_as_mut())
+        {
+            adapter.send_rfc4(get_packet(), false);
+        }
+
+        if message_sent {
+            self.last_emote_incremental_id = incremental_id;
         }
+        message_sent
     }
 
     #[func]
@@ -282,6 +608,34 @@ impl CommunicationManager {
             "profile_changed".into(),
             self.base.callable("_on_profile_changed"),
         );
+
+        DclGlobal::singleton().bind().scene_runner.clone().connect(
+            "on_change_scene_id".into(),
+            self.base.callable("_on_change_scene_id"),
+        );
+    }
+
+    #[func]
+    fn _on_change_scene_id(&mut self, scene_id: i32) {
+        self.base.call_deferred(
+            "_on_change_scene_id_deferred".into(),
+            &[scene_id.to_variant()],
+        );
+    }
+
+    #[func]
+    fn _on_change_scene_id_deferred(&mut self, scene_id: i32) {
+        let scene_runner = DclGlobal::singleton().bind().scene_runner.clone();
+        let scene_runner = scene_runner.bind();
+        let scene = scene_runner.get_scene(&SceneId(scene_id)).map(|scene| {
+            (
+                scene.scene_entity_definition.id.clone(),
+                scene.scene_entity_definition.scene_meta_scene.scene.base,
+            )
+        });
+        drop(scene_runner);
+
+        self.change_scene_room(scene);
     }
 
     #[func]
@@ -368,7 +722,17 @@ impl CommunicationManager {
             return;
         }
 
+        if protocol == "signed-login"
+            && !matches!(
+                self.current_connection,
+                CommsConnection::WaitingForReconnection(_)
+            )
+        {
+            self.reconnection = Reconnection::new(ReconnectionPolicy::default());
+        }
+
         self.current_connection = CommsConnection::None;
+        self.signed_login_adapter_str = None;
         self.current_connection_str
             .clone_from(&comms_fixed_adapter_str);
         let avatar_scene = DclGlobal::singleton().bind().get_avatars();
@@ -384,12 +748,13 @@ impl CommunicationManager {
 
         match protocol {
             "ws-room" => {
-                self.current_connection = CommsConnection::Connected(Box::new(WebSocketRoom::new(
-                    comms_address,
-                    current_ephemeral_auth_chain,
-                    player_profile,
-                    avatar_scene,
-                )));
+                self.current_connection =
+                    CommsConnection::Connected(wrap_adapter(Box::new(WebSocketRoom::new(
+                        comms_address,
+                        current_ephemeral_auth_chain,
+                        player_profile,
+                        avatar_scene,
+                    ))));
             }
             "signed-login" => {
                 let Ok(uri) = Uri::try_from(comms_address.to_string()) else {
@@ -414,16 +779,18 @@ impl CommunicationManager {
                     current_ephemeral_auth_chain,
                     SignedLoginMeta::new(true, origin),
                 ));
+                self.signed_login_adapter_str = Some(comms_fixed_adapter_str.clone());
             }
 
             #[cfg(feature = "use_livekit")]
             "livekit" => {
-                self.current_connection = CommsConnection::Connected(Box::new(LivekitRoom::new(
-                    comms_address.to_string(),
-                    current_ephemeral_auth_chain.signer(),
-                    player_profile,
-                    avatar_scene,
-                )));
+                self.current_connection =
+                    CommsConnection::Connected(wrap_adapter(Box::new(LivekitRoom::new(
+                        comms_address.to_string(),
+                        current_ephemeral_auth_chain.signer(),
+                        player_profile,
+                        avatar_scene,
+                    ))));
             }
 
             #[cfg(not(feature = "use_livekit"))]
@@ -461,6 +828,8 @@ impl CommunicationManager {
             _ => false,
         };
 
+        self.retry_scene_room();
+
         self.base.emit_signal(
             "on_adapter_changed".into(),
             &[
@@ -474,7 +843,8 @@ impl CommunicationManager {
         match &mut self.current_connection {
             CommsConnection::None
             | CommsConnection::SignedLogin(_)
-            | CommsConnection::WaitingForIdentity(_) => {}
+            | CommsConnection::WaitingForIdentity(_)
+            | CommsConnection::WaitingForReconnection(_) => {}
             CommsConnection::Connected(adapter) => {
                 adapter.clean();
             }
@@ -482,8 +852,13 @@ impl CommunicationManager {
             CommsConnection::Archipelago(archipelago) => archipelago.clean(),
         }
 
+        if let Some(mut scene_room) = self.scene_room.take() {
+            scene_room.clean();
+        }
+
         self.current_connection = CommsConnection::None;
         self.current_connection_str = String::default();
+        self.signed_login_adapter_str = None;
     }
 
     #[func]
@@ -494,11 +869,21 @@ impl CommunicationManager {
             return;
         };
         match &mut self.current_connection {
-            CommsConnection::Connected(adapter) => adapter.change_profile(player_profile),
+            CommsConnection::Connected(adapter) => adapter.change_profile(player_profile.clone()),
             #[cfg(feature = "use_livekit")]
-            CommsConnection::Archipelago(archipelago) => archipelago.change_profile(player_profile),
+            CommsConnection::Archipelago(archipelago) => {
+                archipelago.change_profile(player_profile.clone())
+            }
             _ => {}
         }
+
+        if let Some(adapter) = self
+            .scene_room
+            .as_mut()
+            .and_then(|scene_room| scene_room.adapter_as_mut())
+        {
+            adapter.change_profile(player_profile);
+        }
     }
 
     #[func]
@@ -514,18 +899,181 @@ impl CommunicationManager {
     pub fn get_current_adapter_conn_str(&self) -> GString {
         GString::from(self.current_connection_str.clone())
     }
-}
 
-fn get_chat_array(chats: Vec<(H160, rfc4::Chat)>) -> VariantArray {
-    let mut chats_variant_array = VariantArray::new();
-    for (address, chat) in chats {
-        let mut chat_arr = VariantArray::new();
-        let address = format!("{:#x}", address);
-        chat_arr.push(address.to_variant());
-        chat_arr.push(chat.timestamp.to_variant());
-        chat_arr.push(chat.message.to_variant());
+    // Only applied in debug builds, all zeros disables the simulation
+    #[func]
+    fn set_network_conditions(
+        &mut self,
+        latency_ms: f32,
+        jitter_ms: f32,
+        packet_loss: f32,
+        bandwidth_kbps: f32,
+    ) {
+        if !cfg!(debug_assertions) {
+            tracing::warn!("network conditions are only simulated in debug builds");
+            return;
+        }
+
+        *GLOBAL_NETWORK_CONDITIONS.write().unwrap() = NetworkConditions {
+            latency_ms: latency_ms.max(0.0),
+            jitter_ms: jitter_ms.max(0.0),
+            packet_loss: packet_loss.clamp(0.0, 1.0),
+            bandwidth_kbps: bandwidth_kbps.max(0.0),
+        };
+    }
+
+    #[func]
+    fn get_network_conditions(&self) -> Dictionary {
+        let conditions = GLOBAL_NETWORK_CONDITIONS.read().unwrap().clone();
+        let mut dict = Dictionary::new();
+        dict.set("latency_ms", conditions.latency_ms);
+        dict.set("jitter_ms", conditions.jitter_ms);
+        dict.set("packet_loss", conditions.packet_loss);
+        dict.set("bandwidth_kbps", conditions.bandwidth_kbps);
+        dict
+    }
+
+    // 0: push to talk, 1: open mic
+    #[func]
+    fn set_voice_chat_mode(&mut self, mode: i32) {
+        let Some(mode) = VoiceChatMode::from_i32(mode) else {
+            tracing::warn!("invalid voice chat mode {mode}");
+            return;
+        };
+        GLOBAL_VOICE_CHAT_SETTINGS.write().unwrap().mode = mode;
+        self.voice_activity_detector.reset();
+    }
+
+    #[func]
+    fn get_voice_chat_mode(&self) -> i32 {
+        get_voice_chat_settings().mode as i32
+    }
+
+    // `threshold` is the rms level in the range [0, 1], 0 sends every frame
+    #[func]
+    fn set_voice_activity_threshold(&mut self, threshold: f32, hangover_frames: u32) {
+        let mut settings = GLOBAL_VOICE_CHAT_SETTINGS.write().unwrap();
+        settings.vad_threshold = threshold.clamp(0.0, 1.0);
+        settings.vad_hangover_frames = hangover_frames;
+    }
+
+    #[func]
+    fn get_voice_activity_threshold(&self) -> f32 {
+        get_voice_chat_settings().vad_threshold
+    }
+
+    // The microphone track is published again with the new bitrate, it takes up to a
+    //  second to apply
+    #[func]
+    fn set_voice_opus_bitrate(&mut self, bitrate_kbps: u32) {
+        GLOBAL_VOICE_CHAT_SETTINGS
+            .write()
+            .unwrap()
+            .opus_bitrate_kbps = bitrate_kbps.clamp(6, 510);
+    }
+
+    #[func]
+    fn get_voice_opus_bitrate(&self) -> u32 {
+        get_voice_chat_settings().opus_bitrate_kbps
+    }
 
-        chats_variant_array.push(chat_arr.to_variant());
+    #[func]
+    fn set_voice_spatial_attenuation(&mut self, min_distance: f32, max_distance: f32) {
+        let min_distance = min_distance.max(0.0);
+        let mut settings = GLOBAL_VOICE_CHAT_SETTINGS.write().unwrap();
+        settings.min_distance = min_distance;
+        settings.max_distance = max_distance.max(min_distance);
+    }
+
+    #[func]
+    fn get_voice_spatial_attenuation(&self) -> Vector2 {
+        let settings = get_voice_chat_settings();
+        Vector2::new(settings.min_distance, settings.max_distance)
     }
-    chats_variant_array
+
+    // Chats of this session, an empty `channel` returns every channel
+    #[func]
+    fn get_chat_history(&self, channel: GString, after_id: i64) -> VariantArray {
+        let mut entries = VariantArray::new();
+        for entry in self
+            .chat_history
+            .entries_after(&channel.to_string(), after_id.max(0) as u64)
+        {
+            let mut dict = Dictionary::new();
+            dict.set("id", entry.id as i64);
+            dict.set("address", format!("{:#x}", entry.address));
+            dict.set("timestamp", entry.timestamp);
+            dict.set("channel", entry.channel.clone());
+            dict.set("message", entry.message.clone());
+            dict.set("outgoing", entry.outgoing);
+            entries.push(dict.to_variant());
+        }
+        entries
+    }
+
+    #[func]
+    fn get_chat_channels(&self) -> PackedStringArray {
+        self.chat_history
+            .channels()
+            .into_iter()
+            .map(GString::from)
+            .collect()
+    }
+
+    #[func]
+    fn clear_chat_history(&mut self) {
+        self.chat_history.clear();
+    }
+
+    // Per sender: `max_messages` every `window` seconds, and the same text
+    //  is dropped if it's repeated before `duplicated_window` seconds
+    #[func]
+    fn set_chat_rate_limit(&mut self, max_messages: u32, window: f64, duplicated_window: f64) {
+        self.chat_rate_limiter.set_settings(ChatRateLimitSettings {
+            max_messages: max_messages as usize,
+            window: window.max(0.0),
+            duplicated_window: duplicated_window.max(0.0),
+            ..self.chat_rate_limiter.settings().clone()
+        });
+    }
+}
+
+// Drops the chats of the users blocked or muted by the primary player
+fn filter_chats(chats: Vec<(H160, rfc4::Chat)>) -> Vec<(H160, rfc4::Chat)> {
+    if chats.is_empty() {
+        return chats;
+    }
+
+    let Some(profile) = DclGlobal::singleton()
+        .bind()
+        .get_player_identity()
+        .bind()
+        .clone_profile()
+    else {
+        return chats;
+    };
+
+    chats
+        .into_iter()
+        .filter(|(address, _)| {
+            !profile.content.is_blocked(address) && !profile.content.is_muted(address)
+        })
+        .collect()
+}
+
+fn get_chat_variant(address: H160, timestamp: f64, message: String, channel: String) -> Variant {
+    let mut chat_arr = VariantArray::new();
+    let address = format!("{:#x}", address);
+    chat_arr.push(address.to_variant());
+    chat_arr.push(timestamp.to_variant());
+    chat_arr.push(message.to_variant());
+    chat_arr.push(channel.to_variant());
+    chat_arr.to_variant()
+}
+
+fn get_unix_time() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
 }
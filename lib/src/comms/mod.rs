@@ -1,5 +1,8 @@
 pub mod adapter;
+pub mod chat;
 pub mod communication_manager;
 pub mod profile;
+pub mod scene_room;
 pub mod signed_login;
 pub mod voice_chat;
+pub mod voice_processing;
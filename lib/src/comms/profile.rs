@@ -1,6 +1,8 @@
+use ethers_core::types::H160;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    auth::wallet::AsH160,
     dcl::components::proto_components::{
         common::Color3,
         sdk::components::{PbAvatarBase, PbAvatarEquippedData, PbPlayerIdentityData},
@@ -206,6 +208,22 @@ impl SerializedProfile {
         }
     }
 
+    pub fn is_blocked(&self, address: &H160) -> bool {
+        contains_address(&self.blocked, address)
+    }
+
+    pub fn is_muted(&self, address: &H160) -> bool {
+        contains_address(&self.muted, address)
+    }
+
+    pub fn set_blocked(&mut self, address: &H160, blocked: bool) {
+        set_address(&mut self.blocked, address, blocked);
+    }
+
+    pub fn set_muted(&mut self, address: &H160, muted: bool) {
+        set_address(&mut self.muted, address, muted);
+    }
+
     pub fn convert_snapshots(&mut self) {
         // clean up the lambda result
         if let Some(snapshots) = self.avatar.snapshots.as_mut() {
@@ -227,6 +245,22 @@ impl SerializedProfile {
     }
 }
 
+// The addresses in the profile lists can be checksummed or lowercase
+fn contains_address(list: &Option<Vec<String>>, address: &H160) -> bool {
+    list.as_ref().map_or(false, |list| {
+        list.iter()
+            .any(|item| item.as_h160().as_ref() == Some(address))
+    })
+}
+
+fn set_address(list: &mut Option<Vec<String>>, address: &H160, value: bool) {
+    let list = list.get_or_insert_with(Vec::new);
+    list.retain(|item| item.as_h160().as_ref() != Some(address));
+    if value {
+        list.push(format!("{:#x}", address));
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct UserProfile {
     pub version: u32,
@@ -273,3 +307,29 @@ impl UserProfile {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocked_and_muted_addresses() {
+        let address = "0x0F5D2fB29fb7d3CFeE444a200298f468908cC942"
+            .as_h160()
+            .unwrap();
+        let mut profile = SerializedProfile {
+            blocked: Some(vec!["0x0f5d2fb29fb7d3cfee444a200298f468908cc942".into()]),
+            ..Default::default()
+        };
+        assert!(profile.is_blocked(&address));
+        assert!(!profile.is_muted(&address));
+
+        profile.set_blocked(&address, true);
+        assert_eq!(profile.blocked.as_ref().unwrap().len(), 1);
+
+        profile.set_blocked(&address, false);
+        profile.set_muted(&address, true);
+        assert!(!profile.is_blocked(&address));
+        assert!(profile.is_muted(&address));
+    }
+}
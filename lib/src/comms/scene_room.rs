@@ -0,0 +1,320 @@
+use ethers_core::types::H160;
+use godot::prelude::*;
+use http::Uri;
+
+use crate::{
+    comms::{
+        adapter::{
+            adapter_trait::{Adapter, AvatarUpdate},
+            reconnection::ConnectionState,
+        },
+        signed_login::{SignedLogin, SignedLoginMeta, SignedLoginPollStatus},
+    },
+    dcl::components::proto_components::kernel::comms::rfc4,
+    godot_classes::dcl_global::DclGlobal,
+};
+
+#[cfg(feature = "use_livekit")]
+use crate::comms::adapter::{livekit::LivekitRoom, network_simulator::wrap_adapter};
+
+pub const DEFAULT_SCENE_ROOM_GATEKEEPER_URL: &str =
+    "https://comms-gatekeeper.decentraland.org/get-scene-adapter";
+
+#[allow(clippy::large_enum_variant)]
+enum SceneRoomConnection {
+    SignedLogin(SignedLogin),
+    Connected(Box<dyn Adapter>),
+}
+
+// The room shared by all the players in a parcel scene, whatever their island is
+pub struct SceneRoom {
+    scene_entity_id: String,
+    connection: SceneRoomConnection,
+}
+
+impl SceneRoom {
+    pub fn new(
+        gatekeeper_url: &str,
+        scene_entity_id: String,
+        base_parcel: Vector2i,
+    ) -> Option<Self> {
+        let Ok(uri) = Uri::try_from(gatekeeper_url) else {
+            tracing::warn!("failed to parse the scene room gatekeeper url: {gatekeeper_url}");
+            return None;
+        };
+
+        let realm = DclGlobal::singleton().bind().get_realm();
+        let realm_url = realm.get("realm_url".into()).to_string();
+        let Ok(origin) = Uri::try_from(&realm_url) else {
+            tracing::warn!("failed to parse origin comms_address as a uri: {realm_url}");
+            return None;
+        };
+        let realm_name = realm.get("realm_name".into()).to_string();
+
+        let ephemeral_auth_chain = DclGlobal::singleton()
+            .bind()
+            .get_player_identity()
+            .bind()
+            .try_get_ephemeral_auth_chain()?;
+
+        let signed_login = SignedLogin::new(
+            uri,
+            ephemeral_auth_chain,
+            SignedLoginMeta::new_scene_room(
+                true,
+                origin,
+                realm_name,
+                scene_entity_id.clone(),
+                (base_parcel.x, base_parcel.y),
+            ),
+        );
+
+        Some(Self {
+            scene_entity_id,
+            connection: SceneRoomConnection::SignedLogin(signed_login),
+        })
+    }
+
+    pub fn scene_entity_id(&self) -> &str {
+        &self.scene_entity_id
+    }
+
+    pub fn adapter_as_mut(&mut self) -> Option<&mut Box<dyn Adapter>> {
+        match &mut self.connection {
+            SceneRoomConnection::SignedLogin(_) => None,
+            SceneRoomConnection::Connected(adapter) => Some(adapter),
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        match &self.connection {
+            SceneRoomConnection::SignedLogin(_) => false,
+            SceneRoomConnection::Connected(adapter) => {
+                adapter.connection_state() == ConnectionState::Connected
+            }
+        }
+    }
+
+    // Returns false when the room is lost, the CommunicationManager drops it and
+    //  `retry_scene_room` requests it again when the island connection is back or
+    //  the adapter changes, or a new one is requested when the player enters another scene
+    pub fn poll(&mut self) -> bool {
+        match &mut self.connection {
+            SceneRoomConnection::SignedLogin(signed_login) => match signed_login.poll() {
+                SignedLoginPollStatus::Pending => true,
+                SignedLoginPollStatus::Complete(response) => {
+                    let Some(adapter) = response.adapter.as_deref().and_then(create_adapter) else {
+                        tracing::warn!(
+                            "scene room for {} without a valid adapter: {:?}",
+                            self.scene_entity_id,
+                            response
+                        );
+                        return false;
+                    };
+                    self.connection = SceneRoomConnection::Connected(adapter);
+                    true
+                }
+                SignedLoginPollStatus::Error(e) => {
+                    tracing::info!("Error in scene room signed login: {:?}", e);
+                    false
+                }
+            },
+            SceneRoomConnection::Connected(adapter) => adapter.poll(),
+        }
+    }
+
+    pub fn consume_chats(&mut self) -> Vec<(H160, rfc4::Chat)> {
+        self.adapter_as_mut()
+            .map(|adapter| adapter.consume_chats())
+            .unwrap_or_default()
+    }
+
+    pub fn consume_avatar_updates(&mut self) -> Vec<(u32, AvatarUpdate)> {
+        self.adapter_as_mut()
+            .map(|adapter| adapter.consume_avatar_updates())
+            .unwrap_or_default()
+    }
+
+    pub fn clean(&mut self) {
+        if let Some(adapter) = self.adapter_as_mut() {
+            adapter.clean();
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "use_livekit"), allow(unused_variables))]
+fn create_adapter(adapter_str: &str) -> Option<Box<dyn Adapter>> {
+    let Some((protocol, comms_address)) = adapter_str.split_once(':') else {
+        tracing::warn!("unrecognised scene room adapter string: {adapter_str}");
+        return None;
+    };
+
+    match protocol {
+        #[cfg(feature = "use_livekit")]
+        "livekit" => {
+            let player_identity = DclGlobal::singleton().bind().get_player_identity();
+            let ephemeral_auth_chain = player_identity.bind().try_get_ephemeral_auth_chain()?;
+            let player_profile = player_identity.bind().clone_profile();
+            let avatar_scene = DclGlobal::singleton().bind().get_avatars();
+
+            Some(wrap_adapter(Box::new(LivekitRoom::new(
+                comms_address.to_string(),
+                ephemeral_auth_chain.signer(),
+                player_profile,
+                avatar_scene,
+            ))))
+        }
+        _ => {
+            tracing::warn!("protocol not supported for the scene room: {protocol}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comms::{
+        profile::UserProfile,
+        signed_login::{SignedLoginPollStatus, SignedLoginResponse},
+    };
+
+    use super::*;
+
+    struct TestAdapter {
+        poll_ok: bool,
+        state: ConnectionState,
+        chats: Vec<(H160, rfc4::Chat)>,
+    }
+
+    impl Adapter for TestAdapter {
+        fn poll(&mut self) -> bool {
+            self.poll_ok
+        }
+
+        fn connection_state(&self) -> ConnectionState {
+            self.state
+        }
+
+        fn clean(&mut self) {
+            self.state = ConnectionState::Disconnected;
+        }
+
+        fn consume_chats(&mut self) -> Vec<(H160, rfc4::Chat)> {
+            std::mem::take(&mut self.chats)
+        }
+
+        fn consume_avatar_updates(&mut self) -> Vec<(u32, AvatarUpdate)> {
+            Vec::new()
+        }
+
+        fn consume_scene_messages(&mut self, _scene_id: &str) -> Vec<(H160, Vec<u8>)> {
+            Vec::new()
+        }
+
+        fn change_profile(&mut self, _new_profile: UserProfile) {}
+
+        fn send_rfc4(&mut self, _packet: rfc4::Packet, _unreliable: bool) -> bool {
+            true
+        }
+
+        fn send_rfc4_to(&mut self, _packet: rfc4::Packet, _address: H160) -> bool {
+            true
+        }
+
+        fn broadcast_voice(&mut self, _frame: Vec<i16>) {}
+
+        fn support_voice_chat(&self) -> bool {
+            false
+        }
+    }
+
+    fn signed_login_room() -> (
+        tokio::sync::oneshot::Sender<SignedLoginPollStatus>,
+        SceneRoom,
+    ) {
+        let (sender, signed_login) = SignedLogin::from_channel();
+        let scene_room = SceneRoom {
+            scene_entity_id: "scene".into(),
+            connection: SceneRoomConnection::SignedLogin(signed_login),
+        };
+        (sender, scene_room)
+    }
+
+    fn connected_room(state: ConnectionState) -> SceneRoom {
+        SceneRoom {
+            scene_entity_id: "scene".into(),
+            connection: SceneRoomConnection::Connected(Box::new(TestAdapter {
+                poll_ok: true,
+                state,
+                chats: vec![(H160::zero(), rfc4::Chat::default())],
+            })),
+        }
+    }
+
+    fn login_response(adapter: Option<&str>) -> SignedLoginPollStatus {
+        SignedLoginPollStatus::Complete(SignedLoginResponse {
+            message: None,
+            fixed_adapter: None,
+            adapter: adapter.map(String::from),
+        })
+    }
+
+    #[test]
+    fn test_waiting_for_the_signed_login() {
+        let (_sender, mut scene_room) = signed_login_room();
+        assert!(scene_room.poll());
+        assert!(scene_room.poll());
+        assert!(!scene_room.is_connected());
+        assert!(scene_room.adapter_as_mut().is_none());
+        assert!(scene_room.consume_chats().is_empty());
+    }
+
+    #[test]
+    fn test_signed_login_errors_lose_the_room() {
+        let (sender, mut scene_room) = signed_login_room();
+        let _ = sender.send(SignedLoginPollStatus::Error(anyhow::anyhow!("forbidden")));
+        assert!(!scene_room.poll());
+
+        // the gatekeeper request finished without a result
+        let (sender, mut scene_room) = signed_login_room();
+        drop(sender);
+        assert!(!scene_room.poll());
+    }
+
+    #[test]
+    fn test_gatekeeper_response_without_a_valid_adapter() {
+        for adapter in [None, Some("livekit"), Some("ws-room:wss://host/room")] {
+            let (sender, mut scene_room) = signed_login_room();
+            let _ = sender.send(login_response(adapter));
+            assert!(!scene_room.poll(), "adapter {adapter:?}");
+            assert!(!scene_room.is_connected());
+        }
+    }
+
+    #[test]
+    fn test_connected_room_follows_the_adapter() {
+        let mut scene_room = connected_room(ConnectionState::Connecting);
+        assert!(scene_room.poll());
+        assert!(!scene_room.is_connected());
+        assert_eq!(scene_room.consume_chats().len(), 1);
+        assert!(scene_room.consume_chats().is_empty());
+
+        let mut scene_room = connected_room(ConnectionState::Connected);
+        assert!(scene_room.is_connected());
+        scene_room.clean();
+        assert!(!scene_room.is_connected());
+    }
+
+    #[test]
+    fn test_connected_room_is_lost_with_its_adapter() {
+        let mut scene_room = SceneRoom {
+            scene_entity_id: "scene".into(),
+            connection: SceneRoomConnection::Connected(Box::new(TestAdapter {
+                poll_ok: false,
+                state: ConnectionState::Disconnected,
+                chats: Vec::new(),
+            })),
+        };
+        assert!(!scene_room.poll());
+    }
+}
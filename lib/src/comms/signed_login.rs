@@ -19,6 +19,8 @@ pub struct SignedLoginResponse {
     pub message: Option<String>,
     #[serde(rename = "fixedAdapter")]
     pub fixed_adapter: Option<String>,
+    // the scene room adapter
+    pub adapter: Option<String>,
 }
 
 #[derive(serde::Serialize)]
@@ -28,6 +30,14 @@ pub struct SignedLoginMeta {
     #[serde(rename = "isGuest")]
     is_guest: bool,
     origin: String,
+
+    // Only for the scene rooms
+    #[serde(rename = "realmName", skip_serializing_if = "Option::is_none")]
+    realm_name: Option<String>,
+    #[serde(rename = "sceneId", skip_serializing_if = "Option::is_none")]
+    scene_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parcel: Option<String>,
 }
 
 impl SignedLoginMeta {
@@ -39,6 +49,24 @@ impl SignedLoginMeta {
             signer: "dcl:explorer".to_owned(),
             is_guest,
             origin: format!("{}://{}", origin.scheme.unwrap(), origin.authority.unwrap()),
+            realm_name: None,
+            scene_id: None,
+            parcel: None,
+        }
+    }
+
+    pub fn new_scene_room(
+        is_guest: bool,
+        origin: Uri,
+        realm_name: String,
+        scene_id: String,
+        parcel: (i32, i32),
+    ) -> Self {
+        Self {
+            realm_name: Some(realm_name),
+            scene_id: Some(scene_id),
+            parcel: Some(format!("{},{}", parcel.0, parcel.1)),
+            ..Self::new(is_guest, origin)
         }
     }
 }
@@ -125,6 +153,18 @@ impl SignedLogin {
         }
     }
 
+    // The login result is sent by the test instead of the gatekeeper
+    #[cfg(test)]
+    pub fn from_channel() -> (tokio::sync::oneshot::Sender<SignedLoginPollStatus>, Self) {
+        let (login_result_sender, login_result_receiver) = tokio::sync::oneshot::channel();
+        (
+            login_result_sender,
+            Self {
+                login_result_receiver,
+            },
+        )
+    }
+
     pub fn poll(&mut self) -> SignedLoginPollStatus {
         match self.login_result_receiver.try_recv() {
             Ok(result) => result,
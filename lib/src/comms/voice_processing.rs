@@ -0,0 +1,156 @@
+use std::sync::RwLock;
+
+// Shared by the CommunicationManager (capture), the livekit rooms (encoding)
+//  and the AvatarScene (playback)
+pub static GLOBAL_VOICE_CHAT_SETTINGS: once_cell::sync::Lazy<RwLock<VoiceChatSettings>> =
+    once_cell::sync::Lazy::new(Default::default);
+
+// Sent to godot as i32
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum VoiceChatMode {
+    PushToTalk = 0,
+    OpenMic = 1,
+}
+
+impl VoiceChatMode {
+    pub fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(Self::PushToTalk),
+            1 => Some(Self::OpenMic),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoiceChatSettings {
+    pub mode: VoiceChatMode,
+    // rms level in the range [0, 1] to consider a frame as speech
+    pub vad_threshold: f32,
+    // frames still sent after the speech ends, so the words are not cut
+    pub vad_hangover_frames: u32,
+    // the microphone track is published again when it changes
+    pub opus_bitrate_kbps: u32,
+    // remote voices are heard at full volume until `min_distance` (in meters)
+    //  and are silent from `max_distance`
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+impl Default for VoiceChatSettings {
+    fn default() -> Self {
+        Self {
+            mode: VoiceChatMode::PushToTalk,
+            vad_threshold: 0.01,
+            vad_hangover_frames: 15,
+            opus_bitrate_kbps: 32,
+            min_distance: 3.0,
+            max_distance: 40.0,
+        }
+    }
+}
+
+pub fn get_voice_chat_settings() -> VoiceChatSettings {
+    GLOBAL_VOICE_CHAT_SETTINGS.read().unwrap().clone()
+}
+
+#[derive(Default)]
+pub struct VoiceActivityDetector {
+    hangover_left: u32,
+}
+
+impl VoiceActivityDetector {
+    // Returns true when the frame has to be sent
+    pub fn process(&mut self, samples: &[f32], threshold: f32, hangover_frames: u32) -> bool {
+        if samples.is_empty() {
+            return false;
+        }
+
+        if get_rms(samples) >= threshold {
+            self.hangover_left = hangover_frames;
+            return true;
+        }
+
+        if self.hangover_left > 0 {
+            self.hangover_left -= 1;
+            return true;
+        }
+
+        false
+    }
+
+    pub fn reset(&mut self) {
+        self.hangover_left = 0;
+    }
+}
+
+pub fn get_rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let sum: f32 = samples.iter().map(|sample| sample * sample).sum();
+    (sum / samples.len() as f32).sqrt()
+}
+
+// Inverse distance rolloff, faded to zero at `max_distance`
+pub fn get_distance_attenuation(distance: f32, min_distance: f32, max_distance: f32) -> f32 {
+    let min_distance = min_distance.max(0.01);
+    if distance <= min_distance {
+        return 1.0;
+    }
+    if distance >= max_distance {
+        return 0.0;
+    }
+
+    let fade = (max_distance - distance) / (max_distance - min_distance);
+    (min_distance / distance) * fade
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vad_skips_silence() {
+        let mut vad = VoiceActivityDetector::default();
+        assert!(!vad.process(&[0.0; 480], 0.01, 2));
+        assert!(!vad.process(&[0.001; 480], 0.01, 2));
+        assert!(!vad.process(&[], 0.01, 2));
+    }
+
+    #[test]
+    fn test_vad_hangover() {
+        let mut vad = VoiceActivityDetector::default();
+        assert!(vad.process(&[0.5; 480], 0.01, 2));
+        assert!(vad.process(&[0.0; 480], 0.01, 2));
+        assert!(vad.process(&[0.0; 480], 0.01, 2));
+        assert!(!vad.process(&[0.0; 480], 0.01, 2));
+
+        assert!(vad.process(&[0.5; 480], 0.01, 2));
+        vad.reset();
+        assert!(!vad.process(&[0.0; 480], 0.01, 2));
+    }
+
+    #[test]
+    fn test_rms() {
+        assert_eq!(get_rms(&[]), 0.0);
+        assert!((get_rms(&[0.5, -0.5]) - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_distance_attenuation() {
+        assert_eq!(get_distance_attenuation(0.0, 3.0, 40.0), 1.0);
+        assert_eq!(get_distance_attenuation(3.0, 3.0, 40.0), 1.0);
+        assert_eq!(get_distance_attenuation(40.0, 3.0, 40.0), 0.0);
+        assert_eq!(get_distance_attenuation(100.0, 3.0, 40.0), 0.0);
+
+        let mut last = 1.0;
+        for distance in 4..40 {
+            let attenuation = get_distance_attenuation(distance as f32, 3.0, 40.0);
+            assert!(attenuation < last && attenuation > 0.0);
+            last = attenuation;
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+    time::Instant,
+};
+
+use serde::Serialize;
+
+use crate::dcl::SceneId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentLoadStatus {
+    Loading,
+    // downloaded (or read from the cache) but not processed by the content provider,
+    //  e.g. the dependencies of a gltf
+    Downloaded,
+    Loaded,
+    MissingMapping,
+    HttpError,
+    DownloadError,
+    DecodeError,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ContentLoadRecord {
+    pub file_hash: String,
+    pub url: String,
+    pub status: ContentLoadStatus,
+    pub http_status: Option<u16>,
+    pub from_cache: bool,
+    pub bytes: u64,
+    pub elapsed_ms: Option<u64>,
+    pub error: Option<String>,
+    pub missing_dependencies: Vec<String>,
+    #[serde(skip)]
+    started_at: Instant,
+}
+
+impl ContentLoadRecord {
+    fn new(file_hash: &str, url: &str) -> Self {
+        Self {
+            file_hash: file_hash.to_string(),
+            url: url.to_string(),
+            status: ContentLoadStatus::Loading,
+            http_status: None,
+            from_cache: false,
+            bytes: 0,
+            elapsed_ms: None,
+            error: None,
+            missing_dependencies: Vec::new(),
+            started_at: Instant::now(),
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        !matches!(
+            self.status,
+            ContentLoadStatus::Loading | ContentLoadStatus::Downloaded | ContentLoadStatus::Loaded
+        )
+    }
+
+    fn is_download_failure(&self) -> bool {
+        matches!(
+            self.status,
+            ContentLoadStatus::HttpError | ContentLoadStatus::DownloadError
+        )
+    }
+}
+
+#[derive(Serialize)]
+pub struct SceneContentReportFile {
+    pub file_path: String,
+    #[serde(flatten)]
+    pub record: ContentLoadRecord,
+}
+
+#[derive(Serialize)]
+pub struct SceneContentReport {
+    pub scene_id: i32,
+    pub entity_id: String,
+    pub failed_count: usize,
+    pub loading_count: usize,
+    pub files: Vec<SceneContentReportFile>,
+}
+
+impl SceneContentReport {
+    pub fn new(scene_id: i32, entity_id: String, files: Vec<(String, ContentLoadRecord)>) -> Self {
+        let files = files
+            .into_iter()
+            .map(|(file_path, record)| SceneContentReportFile { file_path, record })
+            .collect::<Vec<_>>();
+        Self {
+            scene_id,
+            entity_id,
+            failed_count: files.iter().filter(|file| file.record.is_failure()).count(),
+            loading_count: files
+                .iter()
+                .filter(|file| file.record.status == ContentLoadStatus::Loading)
+                .count(),
+            files,
+        }
+    }
+}
+
+// Outcome of every file requested through the ContentProvider, keyed by file hash.
+//  The scene reports are built from the content mapping of the scene, plus the files
+//  the scene requested but are not in its mapping
+#[derive(Default)]
+pub struct ContentDiagnostics {
+    records: Mutex<HashMap<String, ContentLoadRecord>>,
+    missing_files: Mutex<HashMap<SceneId, BTreeSet<String>>>,
+}
+
+impl ContentDiagnostics {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Called when the content provider starts loading the file, the previous outcome is discarded
+    pub fn start(&self, file_hash: &str, url: &str) {
+        self.records.lock().unwrap().insert(
+            file_hash.to_string(),
+            ContentLoadRecord::new(file_hash, url),
+        );
+    }
+
+    pub fn record_download(&self, file_hash: &str, url: &str, bytes: u64, from_cache: bool) {
+        let mut records = self.records.lock().unwrap();
+        let record = records.entry(file_hash.to_string()).or_insert_with(|| {
+            let mut record = ContentLoadRecord::new(file_hash, url);
+            record.status = ContentLoadStatus::Downloaded;
+            record
+        });
+        record.bytes = bytes;
+        record.from_cache = from_cache;
+        if record.status == ContentLoadStatus::Downloaded {
+            record.elapsed_ms = Some(record.started_at.elapsed().as_millis() as u64);
+        }
+    }
+
+    pub fn record_download_error(
+        &self,
+        file_hash: &str,
+        url: &str,
+        http_status: Option<u16>,
+        error: &str,
+    ) {
+        let mut records = self.records.lock().unwrap();
+        let record = records
+            .entry(file_hash.to_string())
+            .or_insert_with(|| ContentLoadRecord::new(file_hash, url));
+        record.status = if http_status.is_some() {
+            ContentLoadStatus::HttpError
+        } else {
+            ContentLoadStatus::DownloadError
+        };
+        record.http_status = http_status;
+        record.error = Some(error.to_string());
+        record.elapsed_ms = Some(record.started_at.elapsed().as_millis() as u64);
+    }
+
+    pub fn record_missing_dependencies(&self, file_hash: &str, missing_dependencies: Vec<String>) {
+        if let Some(record) = self.records.lock().unwrap().get_mut(file_hash) {
+            record.missing_dependencies = missing_dependencies;
+        }
+    }
+
+    // Called with the result of the load, errors that didn't happen downloading the file
+    //  are considered decode errors
+    pub fn finish<T>(&self, file_hash: &str, result: &Result<T, anyhow::Error>) {
+        let mut records = self.records.lock().unwrap();
+        let Some(record) = records.get_mut(file_hash) else {
+            return;
+        };
+
+        record.elapsed_ms = Some(record.started_at.elapsed().as_millis() as u64);
+        match result {
+            Ok(_) => {
+                record.status = ContentLoadStatus::Loaded;
+                record.error = None;
+            }
+            Err(err) => {
+                if !record.missing_dependencies.is_empty() {
+                    record.status = ContentLoadStatus::MissingMapping;
+                } else if !record.is_download_failure() {
+                    record.status = ContentLoadStatus::DecodeError;
+                }
+                record.error = Some(err.to_string());
+            }
+        }
+    }
+
+    pub fn record_missing_file(&self, scene_id: SceneId, file_path: &str) {
+        self.missing_files
+            .lock()
+            .unwrap()
+            .entry(scene_id)
+            .or_default()
+            .insert(file_path.to_lowercase());
+    }
+
+    // Drops the outcomes of the killed scenes, the records are kept while the file is
+    //  still loading or is in the content mapping of a loaded scene
+    pub fn retain_scenes(
+        &self,
+        is_alive: impl Fn(&SceneId) -> bool,
+        is_file_used: impl Fn(&str) -> bool,
+    ) {
+        self.missing_files
+            .lock()
+            .unwrap()
+            .retain(|scene_id, _| is_alive(scene_id));
+        self.records.lock().unwrap().retain(|file_hash, record| {
+            record.status == ContentLoadStatus::Loading || is_file_used(file_hash)
+        });
+    }
+
+    // (file path, record) of the requested files of the scene, sorted by file path
+    pub fn get_scene_report(
+        &self,
+        scene_id: SceneId,
+        files: &HashMap<String, String>,
+    ) -> Vec<(String, ContentLoadRecord)> {
+        let records = self.records.lock().unwrap();
+        let mut report = files
+            .iter()
+            .filter_map(|(file_path, file_hash)| {
+                records
+                    .get(file_hash)
+                    .map(|record| (file_path.clone(), record.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(missing_files) = self.missing_files.lock().unwrap().get(&scene_id) {
+            report.extend(missing_files.iter().map(|file_path| {
+                let mut record = ContentLoadRecord::new("", "");
+                record.status = ContentLoadStatus::MissingMapping;
+                record.error = Some("File not found in the content mappings".to_string());
+                (file_path.clone(), record)
+            }));
+        }
+
+        report.sort_by(|a, b| a.0.cmp(&b.0));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scene_report_outcomes() {
+        let diagnostics = ContentDiagnostics::new();
+        let scene_id = SceneId(1);
+
+        diagnostics.start("hash_ok", "url_ok");
+        diagnostics.record_download("hash_ok", "url_ok", 100, false);
+        diagnostics.finish::<()>("hash_ok", &Ok(()));
+
+        diagnostics.start("hash_404", "url_404");
+        diagnostics.record_download_error("hash_404", "url_404", Some(404), "HTTP status 404");
+        diagnostics.finish::<()>("hash_404", &Err(anyhow::Error::msg("HTTP status 404")));
+
+        diagnostics.start("hash_corrupt", "url_corrupt");
+        diagnostics.record_download("hash_corrupt", "url_corrupt", 10, true);
+        diagnostics.finish::<()>("hash_corrupt", &Err(anyhow::Error::msg("Invalid png")));
+
+        diagnostics.record_missing_file(scene_id, "Models/Missing.glb");
+
+        let files = HashMap::from([
+            ("a.png".to_string(), "hash_ok".to_string()),
+            ("b.png".to_string(), "hash_404".to_string()),
+            ("c.png".to_string(), "hash_corrupt".to_string()),
+            ("d.png".to_string(), "hash_not_requested".to_string()),
+        ]);
+
+        let report = diagnostics.get_scene_report(scene_id, &files);
+        let statuses = report
+            .iter()
+            .map(|(file_path, record)| (file_path.as_str(), record.status))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            statuses,
+            vec![
+                ("a.png", ContentLoadStatus::Loaded),
+                ("b.png", ContentLoadStatus::HttpError),
+                ("c.png", ContentLoadStatus::DecodeError),
+                ("models/missing.glb", ContentLoadStatus::MissingMapping),
+            ]
+        );
+        assert_eq!(report[0].1.bytes, 100);
+        assert_eq!(report[1].1.http_status, Some(404));
+        assert!(report[2].1.from_cache);
+    }
+
+    #[test]
+    fn test_retain_scenes() {
+        let diagnostics = ContentDiagnostics::new();
+        let scene_id = SceneId(1);
+
+        diagnostics.start("hash_used", "url_used");
+        diagnostics.finish::<()>("hash_used", &Ok(()));
+        diagnostics.start("hash_unused", "url_unused");
+        diagnostics.finish::<()>("hash_unused", &Ok(()));
+        diagnostics.start("hash_loading", "url_loading");
+        diagnostics.record_missing_file(scene_id, "missing.glb");
+
+        diagnostics.retain_scenes(|_| false, |file_hash| file_hash == "hash_used");
+
+        let files = HashMap::from([
+            ("a.png".to_string(), "hash_used".to_string()),
+            ("b.png".to_string(), "hash_unused".to_string()),
+            ("c.png".to_string(), "hash_loading".to_string()),
+        ]);
+        let file_paths = diagnostics
+            .get_scene_report(scene_id, &files)
+            .into_iter()
+            .map(|(file_path, _)| file_path)
+            .collect::<Vec<_>>();
+        assert_eq!(file_paths, vec!["a.png", "c.png"]);
+    }
+}
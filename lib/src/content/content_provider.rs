@@ -8,7 +8,7 @@ use std::{
 };
 
 use godot::{
-    engine::{AudioStream, Material, Mesh, Texture2D},
+    engine::{file_access::ModeFlags, AudioStream, Material, Mesh, Texture2D},
     prelude::*,
 };
 use tokio::sync::Semaphore;
@@ -17,14 +17,16 @@ use crate::{
     auth::wallet::AsH160,
     avatars::{dcl_user_profile::DclUserProfile, item::DclItemEntityDefinition},
     content::content_mapping::DclContentMappingAndUrl,
-    dcl::common::string::FindNthChar,
+    dcl::{common::string::FindNthChar, SceneId},
     godot_classes::{
         dcl_config::{DclConfig, TextureQuality},
         dcl_global::DclGlobal,
         promise::Promise,
         resource_locker::ResourceLocker,
     },
-    http_request::http_queue_requester::HttpQueueRequester,
+    http_request::{
+        bandwidth_limiter::GLOBAL_BANDWIDTH_LIMITER, http_queue_requester::HttpQueueRequester,
+    },
     scene_runner::tokio_runtime::TokioRuntime,
 };
 
@@ -37,9 +39,10 @@ use crate::godot_classes::dcl_resource_tracker::{
 
 use super::{
     audio::load_audio,
+    content_diagnostics::{ContentDiagnostics, SceneContentReport},
     gltf::{
-        apply_update_set_mask_colliders, load_gltf_emote, load_gltf_scene_content,
-        load_gltf_wearable, DclEmoteGltf,
+        apply_update_set_mask_colliders, get_gltf_texture_memory_size, load_gltf_emote,
+        load_gltf_scene_content, load_gltf_wearable, DclEmoteGltf,
     },
     profile::{prepare_request_requirements, request_lambda_profile},
     resource_provider::ResourceProvider,
@@ -63,6 +66,7 @@ pub struct ContentEntry {
 pub struct ContentProvider {
     content_folder: Arc<String>,
     resource_provider: Arc<ResourceProvider>,
+    content_diagnostics: Arc<ContentDiagnostics>,
     #[cfg(feature = "use_resource_tracking")]
     resource_download_tracking: Arc<ResourceDownloadTracking>,
     http_queue_requester: Arc<HttpQueueRequester>,
@@ -71,16 +75,37 @@ pub struct ContentProvider {
     texture_quality: TextureQuality, // copy from DclGlobal on startup
     every_second_tick: f64,
     download_speed_mbs: f64,
+    session_content_downloaded_bytes: u64,
     loading_resources: Arc<AtomicU64>,
     loaded_resources: Arc<AtomicU64>,
+    texture_memory_budget: i64,
+    texture_memory_usage: i64,
+    // scenes using each texture hash, used to know how far a texture is from the player
+    texture_scenes: HashMap<String, HashSet<SceneId>>,
+    // memory of the textures embedded in each loaded gltf, computed once by hash
+    gltf_texture_sizes: HashMap<String, i64>,
+    // avatar textures waiting for the profile promise, by texture hash
+    pending_avatar_textures: HashMap<String, (Gd<Promise>, Gd<Promise>)>,
     #[cfg(feature = "use_resource_tracking")]
     tracking_tick: f64,
 }
 
+// the budget includes the textures embedded in the gltfs, they can't be downscaled
+const MOBILE_TEXTURE_MEMORY_BUDGET: i64 = 256 * 1024 * 1024;
+const DESKTOP_TEXTURE_MEMORY_BUDGET: i64 = 1024 * 1024 * 1024;
+// each level halves width and height, so the last level uses 1/16 of the texture memory
+const MAX_TEXTURE_DOWNSCALE_LEVEL: u32 = 2;
+// textures of scenes at this distance (in parcels) or closer are always at full size
+const TEXTURE_RESTORE_DISTANCE: f32 = 2.0;
+// far textures are only restored if the usage stays under this fraction of the budget,
+//  it avoids downscaling and restoring the same textures every second
+const TEXTURE_RESTORE_BUDGET_FRACTION: f64 = 0.8;
+
 #[derive(Clone)]
 pub struct ContentProviderContext {
     pub content_folder: Arc<String>,
     pub resource_provider: Arc<ResourceProvider>,
+    pub content_diagnostics: Arc<ContentDiagnostics>,
     pub http_queue_requester: Arc<HttpQueueRequester>,
     pub godot_single_thread: Arc<Semaphore>,
     pub texture_quality: TextureQuality, // copy from DclGlobal on startup
@@ -99,14 +124,21 @@ impl INode for ContentProvider {
         #[cfg(feature = "use_resource_tracking")]
         let resource_download_tracking = Arc::new(ResourceDownloadTracking::new());
 
+        // the config is created after the content provider, load the data saver flag here
+        DclConfig::static_get_data_saver();
+
+        let content_diagnostics = Arc::new(ContentDiagnostics::new());
+
         Self {
             resource_provider: Arc::new(ResourceProvider::new(
                 content_folder.clone().as_str(),
                 2048 * 1000 * 1000,
                 32,
+                content_diagnostics.clone(),
                 #[cfg(feature = "use_resource_tracking")]
                 resource_download_tracking.clone(),
             )),
+            content_diagnostics,
             #[cfg(feature = "use_resource_tracking")]
             resource_download_tracking,
             http_queue_requester: Arc::new(HttpQueueRequester::new(
@@ -121,6 +153,16 @@ impl INode for ContentProvider {
             loading_resources: Arc::new(AtomicU64::new(0)),
             loaded_resources: Arc::new(AtomicU64::new(0)),
             download_speed_mbs: 0.0,
+            session_content_downloaded_bytes: 0,
+            texture_memory_budget: if godot::engine::Os::singleton().has_feature("mobile".into()) {
+                MOBILE_TEXTURE_MEMORY_BUDGET
+            } else {
+                DESKTOP_TEXTURE_MEMORY_BUDGET
+            },
+            texture_memory_usage: 0,
+            texture_scenes: HashMap::new(),
+            gltf_texture_sizes: HashMap::new(),
+            pending_avatar_textures: HashMap::new(),
             #[cfg(feature = "use_resource_tracking")]
             tracking_tick: 0.0,
         }
@@ -132,6 +174,10 @@ impl INode for ContentProvider {
     }
 
     fn process(&mut self, dt: f64) {
+        if !self.pending_avatar_textures.is_empty() {
+            self.load_pending_avatar_textures();
+        }
+
         // Update resource download tracking
         #[cfg(feature = "use_resource_tracking")]
         {
@@ -162,6 +208,7 @@ impl INode for ContentProvider {
 
             let downloaded_size = self.resource_provider.consume_download_size();
             self.download_speed_mbs = (downloaded_size as f64) / 1024.0 / 1024.0;
+            self.session_content_downloaded_bytes += downloaded_size;
 
             // Clean cache
             self.cached.retain(|_hash_id, entry| {
@@ -197,6 +244,28 @@ impl INode for ContentProvider {
                 }
                 true
             });
+
+            let cached = &self.cached;
+            self.texture_scenes
+                .retain(|file_hash, _| cached.contains_key(file_hash));
+            self.gltf_texture_sizes
+                .retain(|file_hash, _| cached.contains_key(file_hash));
+
+            let mut scene_runner = DclGlobal::singleton().bind().scene_runner.clone();
+            let mut scene_runner = scene_runner.bind_mut();
+            let scenes = scene_runner.get_all_scenes();
+            let used_file_hashes = scenes
+                .values()
+                .flat_map(|scene| scene.content_mapping.files().values())
+                .map(String::as_str)
+                .collect::<HashSet<&str>>();
+            self.content_diagnostics.retain_scenes(
+                |scene_id| scenes.contains_key(scene_id),
+                |file_hash| used_file_hashes.contains(file_hash),
+            );
+            drop(scene_runner);
+
+            self.update_texture_memory_budget();
         }
     }
 }
@@ -277,6 +346,7 @@ impl ContentProvider {
         }
 
         let file_hash = file_hash.clone();
+        let url = format!("{}{}", content_mapping.base_url, file_hash);
         let (promise, get_promise) = Promise::make_to_async();
         let gltf_file_path = file_path.to_string();
         let content_provider_context = self.get_context();
@@ -285,6 +355,7 @@ impl ContentProvider {
         let loaded_resources = self.loaded_resources.clone();
         #[cfg(feature = "use_resource_tracking")]
         let hash_id = file_hash.clone();
+        let finish_diagnostics = self.start_load_diagnostics(&file_hash, &url);
         TokioRuntime::spawn(async move {
             #[cfg(feature = "use_resource_tracking")]
             report_resource_start(&hash_id);
@@ -294,6 +365,7 @@ impl ContentProvider {
             let result =
                 load_gltf_scene_content(gltf_file_path, content_mapping, content_provider_context)
                     .await;
+            finish_diagnostics(&result);
 
             #[cfg(feature = "use_resource_tracking")]
             if let Err(error) = &result {
@@ -500,6 +572,7 @@ impl ContentProvider {
         }
 
         let file_hash = file_hash.clone();
+        let url = format!("{}{}", content_mapping.base_url, file_hash);
         let (promise, get_promise) = Promise::make_to_async();
         let audio_file_path = file_path.to_string();
         let content_provider_context = self.get_context();
@@ -508,6 +581,7 @@ impl ContentProvider {
         let loaded_resources = self.loaded_resources.clone();
         #[cfg(feature = "use_resource_tracking")]
         let hash_id = file_hash.clone();
+        let finish_diagnostics = self.start_load_diagnostics(&file_hash, &url);
         TokioRuntime::spawn(async move {
             #[cfg(feature = "use_resource_tracking")]
             report_resource_start(&hash_id);
@@ -516,6 +590,7 @@ impl ContentProvider {
 
             let result =
                 load_audio(audio_file_path, content_mapping, content_provider_context).await;
+            finish_diagnostics(&result);
 
             #[cfg(feature = "use_resource_tracking")]
             if let Err(error) = &result {
@@ -593,6 +668,7 @@ impl ContentProvider {
         let loading_resources = self.loading_resources.clone();
         let loaded_resources = self.loaded_resources.clone();
         let hash_id = file_hash.clone();
+        let finish_diagnostics = self.start_load_diagnostics(&file_hash, &url);
         TokioRuntime::spawn(async move {
             #[cfg(feature = "use_resource_tracking")]
             report_resource_start(&hash_id);
@@ -600,6 +676,7 @@ impl ContentProvider {
             loading_resources.fetch_add(1, Ordering::Relaxed);
 
             let result = load_image_texture(url, hash_id.clone(), content_provider_context).await;
+            finish_diagnostics(&result);
 
             #[cfg(feature = "use_resource_tracking")]
             if let Err(error) = &result {
@@ -641,6 +718,7 @@ impl ContentProvider {
 
         #[cfg(feature = "use_resource_tracking")]
         let hash_id = file_hash.clone();
+        let finish_diagnostics = self.start_load_diagnostics(&file_hash, &url);
         TokioRuntime::spawn(async move {
             #[cfg(feature = "use_resource_tracking")]
             report_resource_start(&hash_id);
@@ -648,6 +726,7 @@ impl ContentProvider {
             loading_resources.fetch_add(1, Ordering::Relaxed);
 
             let result = load_image_texture(url, sent_file_hash, content_provider_context).await;
+            finish_diagnostics(&result);
 
             #[cfg(feature = "use_resource_tracking")]
             if let Err(error) = &result {
@@ -730,6 +809,7 @@ impl ContentProvider {
         let content_mapping = content_mapping.bind().get_content_mapping();
         let (promise, get_promise) = Promise::make_to_async();
         let file_hash = file_hash.to_string();
+        let url = format!("{}{}", content_mapping.base_url, file_hash);
         let video_file_hash = file_hash.clone();
         let content_provider_context = self.get_context();
 
@@ -737,6 +817,7 @@ impl ContentProvider {
         let loaded_resources = self.loaded_resources.clone();
         #[cfg(feature = "use_resource_tracking")]
         let hash_id = file_hash.clone();
+        let finish_diagnostics = self.start_load_diagnostics(&file_hash, &url);
         TokioRuntime::spawn(async move {
             #[cfg(feature = "use_resource_tracking")]
             report_resource_start(&hash_id);
@@ -745,6 +826,7 @@ impl ContentProvider {
 
             let result =
                 download_video(video_file_hash, content_mapping, content_provider_context).await;
+            finish_diagnostics(&result);
 
             #[cfg(feature = "use_resource_tracking")]
             if let Err(error) = &result {
@@ -949,6 +1031,84 @@ impl ContentProvider {
         self.download_speed_mbs
     }
 
+    // Bytes downloaded by the content provider (scene assets, wearables, emotes, etc) since startup
+    #[func]
+    pub fn get_session_content_downloaded_bytes(&self) -> i64 {
+        self.session_content_downloaded_bytes as i64
+    }
+
+    // Bytes downloaded by any rust requester (content and http requests) since startup
+    #[func]
+    pub fn get_session_downloaded_bytes(&self) -> i64 {
+        GLOBAL_BANDWIDTH_LIMITER.get_session_downloaded_bytes() as i64
+    }
+
+    // 0 or negative disables the limit
+    #[func]
+    pub fn set_max_download_bandwidth(&mut self, bytes_per_second: i64) {
+        GLOBAL_BANDWIDTH_LIMITER.set_max_bytes_per_second(bytes_per_second.max(0) as u64)
+    }
+
+    #[func]
+    pub fn get_max_download_bandwidth(&self) -> i64 {
+        GLOBAL_BANDWIDTH_LIMITER.get_max_bytes_per_second() as i64
+    }
+
+    // Textures of far-away scenes are downscaled when the sum of the loaded textures
+    //  exceeds this size (in bytes), and restored when the player approaches. The
+    //  textures embedded in the gltfs are counted in the usage but never downscaled
+    #[func]
+    pub fn set_texture_memory_budget(&mut self, bytes: i64) {
+        self.texture_memory_budget = bytes.max(0);
+        self.update_texture_memory_budget();
+    }
+
+    #[func]
+    pub fn get_texture_memory_budget(&self) -> i64 {
+        self.texture_memory_budget
+    }
+
+    #[func]
+    pub fn get_texture_memory_usage(&self) -> i64 {
+        self.texture_memory_usage
+    }
+
+    // Files requested by a scene that are not in its content mapping, they're reported
+    //  by the components before calling the content provider
+    #[func]
+    pub fn report_scene_missing_file(&self, scene_id: i32, file_path: GString) {
+        self.content_diagnostics
+            .record_missing_file(SceneId(scene_id), &file_path.to_string());
+    }
+
+    // Load outcome of each file requested by the scene: status, http status, bytes, time taken and error
+    #[func]
+    pub fn get_scene_content_report(&self, scene_id: i32) -> Dictionary {
+        let Some(report) = self.build_scene_content_report(scene_id) else {
+            return Dictionary::new();
+        };
+        let value = serde_json::to_string(&report).unwrap_or_default();
+        let value = godot::engine::Json::parse_string(value.into());
+        value.try_to::<Dictionary>().unwrap_or_default()
+    }
+
+    #[func]
+    pub fn export_scene_content_report(&self, scene_id: i32, file_path: GString) -> bool {
+        let Some(report) = self.build_scene_content_report(scene_id) else {
+            return false;
+        };
+        let Ok(value) = serde_json::to_string_pretty(&report) else {
+            return false;
+        };
+        let Some(mut file) = godot::engine::FileAccess::open(file_path.clone(), ModeFlags::WRITE)
+        else {
+            tracing::error!("Error opening {file_path} to export the scene content report");
+            return false;
+        };
+        file.store_string(value.into());
+        true
+    }
+
     #[func]
     pub fn count_loaded_resources(&self) -> u64 {
         self.loaded_resources.load(Ordering::Relaxed)
@@ -1012,16 +1172,261 @@ impl ContentProvider {
 
         promise
     }
+
+    // Face snapshot of the user, the promise resolves with the same data as `fetch_texture_by_url`
+    //  and it's available with `get_texture_from_hash(get_avatar_texture_hash(user_id))`
+    #[func]
+    pub fn fetch_avatar_texture(&mut self, user_id: GString) -> Gd<Promise> {
+        let hash = Self::get_avatar_texture_hash(user_id.clone()).to_string();
+        if hash.is_empty() {
+            return Promise::from_rejected("Invalid user id".to_string());
+        }
+
+        if let Some(entry) = self.cached.get_mut(&hash) {
+            entry.last_access = Instant::now();
+            return entry.promise.clone();
+        }
+
+        // the profile is shared with `fetch_profile`, the texture is loaded in the
+        //  `process` after the profile promise is resolved
+        let profile_promise = self.fetch_profile(user_id);
+        let promise = Promise::alloc_gd();
+        self.pending_avatar_textures
+            .insert(hash.clone(), (profile_promise, promise.clone()));
+
+        self.cached.insert(
+            hash,
+            ContentEntry {
+                last_access: Instant::now(),
+                promise: promise.clone(),
+            },
+        );
+
+        promise
+    }
+
+    #[func]
+    pub fn get_avatar_texture_hash(user_id: GString) -> GString {
+        match user_id.to_string().as_str().as_h160() {
+            Some(user_id) => GString::from(format!("avatar_texture_{:x}", user_id)),
+            None => GString::default(),
+        }
+    }
 }
 
 impl ContentProvider {
+    fn load_pending_avatar_textures(&mut self) {
+        let ready_hashes: Vec<String> = self
+            .pending_avatar_textures
+            .iter()
+            .filter(|(_, (profile_promise, _))| profile_promise.bind().is_resolved())
+            .map(|(hash, _)| hash.clone())
+            .collect();
+
+        for hash in ready_hashes {
+            let Some((profile_promise, mut promise)) = self.pending_avatar_textures.remove(&hash)
+            else {
+                continue;
+            };
+
+            let profile = profile_promise
+                .bind()
+                .get_data()
+                .try_to::<Gd<DclUserProfile>>()
+                .map(|profile| profile.bind().inner.clone());
+            let Ok(profile) = profile else {
+                promise
+                    .bind_mut()
+                    .reject("The profile couldn't be fetched".into());
+                continue;
+            };
+
+            let Some(face256) = profile
+                .content
+                .avatar
+                .snapshots
+                .map(|snapshots| snapshots.face256)
+                .filter(|face256| !face256.is_empty())
+            else {
+                promise
+                    .bind_mut()
+                    .reject("The profile has no face snapshot".into());
+                continue;
+            };
+
+            let url = format!("{}{}", profile.base_url, face256);
+            let promise_instance_id = promise.instance_id();
+            let get_promise = move || Gd::<Promise>::try_from_instance_id(promise_instance_id).ok();
+            let content_provider_context = self.get_context();
+            let loading_resources = self.loading_resources.clone();
+            let loaded_resources = self.loaded_resources.clone();
+            TokioRuntime::spawn(async move {
+                loading_resources.fetch_add(1, Ordering::Relaxed);
+
+                let result = load_image_texture(url, face256, content_provider_context).await;
+                then_promise(get_promise, result);
+
+                loaded_resources.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+    }
+
+    fn build_scene_content_report(&self, scene_id: i32) -> Option<SceneContentReport> {
+        let scene_runner = DclGlobal::singleton().bind().scene_runner.clone();
+        let scene_runner = scene_runner.bind();
+        let scene = scene_runner.get_scene(&SceneId(scene_id))?;
+        let files = self
+            .content_diagnostics
+            .get_scene_report(SceneId(scene_id), scene.content_mapping.files());
+        Some(SceneContentReport::new(
+            scene_id,
+            scene.scene_entity_definition.id.clone(),
+            files,
+        ))
+    }
+
+    // Starts the diagnostics record of the file, the returned function must be called with the load result
+    fn start_load_diagnostics(
+        &self,
+        file_hash: &str,
+        url: &str,
+    ) -> impl FnOnce(&Result<Option<Variant>, anyhow::Error>) + Send + 'static {
+        self.content_diagnostics.start(file_hash, url);
+        let content_diagnostics = self.content_diagnostics.clone();
+        let file_hash = file_hash.to_string();
+        move |result| content_diagnostics.finish(&file_hash, result)
+    }
+
+    // Called by the material component, the scenes using a texture define how far it is from the player
+    pub fn register_texture_scene(&mut self, file_hash: &str, scene_id: SceneId) {
+        self.texture_scenes
+            .entry(file_hash.to_string())
+            .or_default()
+            .insert(scene_id);
+    }
+
+    fn update_texture_memory_budget(&mut self) {
+        let scene_distances = DclGlobal::singleton()
+            .bind()
+            .scene_runner
+            .bind()
+            .get_scene_distances();
+
+        let mut usage = 0;
+        // (distance, texture entry) of the textures that can change their size
+        let mut scalable_textures = Vec::new();
+        for (file_hash, entry) in self.cached.iter() {
+            if !entry.promise.bind().is_resolved() {
+                continue;
+            }
+
+            let data = entry.promise.bind().get_data();
+            if let Ok(gltf_node) = data.try_to::<Gd<Node3D>>() {
+                usage += *self
+                    .gltf_texture_sizes
+                    .entry(file_hash.clone())
+                    .or_insert_with(|| get_gltf_texture_memory_size(gltf_node.upcast()));
+                continue;
+            }
+
+            let Ok(texture_entry) = data.try_to::<Gd<TextureEntry>>() else {
+                continue;
+            };
+
+            usage += texture_entry.bind().memory_size();
+
+            // textures without a scene (e.g. UI) are never downscaled
+            let Some(scene_ids) = self.texture_scenes.get(file_hash) else {
+                continue;
+            };
+
+            if !texture_entry.bind().can_downscale() {
+                continue;
+            }
+
+            // a texture of a killed scene is considered as far as it can be
+            let distance = scene_ids
+                .iter()
+                .filter_map(|scene_id| scene_distances.get(scene_id))
+                .fold(f32::MAX, |min_distance, distance| {
+                    min_distance.min(*distance)
+                });
+
+            scalable_textures.push((distance, texture_entry));
+        }
+
+        // nearest first
+        scalable_textures.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // the nearby scenes always get their textures at full size
+        for (_, texture_entry) in scalable_textures
+            .iter_mut()
+            .take_while(|(distance, _)| *distance <= TEXTURE_RESTORE_DISTANCE)
+        {
+            usage += set_texture_downscale_level(texture_entry, 0);
+        }
+
+        let budget = self.texture_memory_budget;
+        if usage > budget {
+            // downscale from the farthest until the usage fits in the budget
+            for (_, texture_entry) in scalable_textures
+                .iter_mut()
+                .rev()
+                .take_while(|(distance, _)| *distance > TEXTURE_RESTORE_DISTANCE)
+            {
+                if usage <= budget {
+                    break;
+                }
+                usage += set_texture_downscale_level(texture_entry, MAX_TEXTURE_DOWNSCALE_LEVEL);
+            }
+        } else {
+            // restore from the nearest while there is room enough
+            let restore_budget = (budget as f64 * TEXTURE_RESTORE_BUDGET_FRACTION) as i64;
+            for (_, texture_entry) in scalable_textures.iter_mut() {
+                let downscale_level = texture_entry.bind().downscale_level;
+                if downscale_level == 0 {
+                    continue;
+                }
+
+                let full_size = texture_entry.bind().memory_size_at(0);
+                let current_size = texture_entry.bind().memory_size();
+                if usage + full_size - current_size > restore_budget {
+                    break;
+                }
+                usage += set_texture_downscale_level(texture_entry, 0);
+            }
+        }
+
+        if usage != self.texture_memory_usage {
+            tracing::debug!(
+                "Texture memory usage: {} MB (budget {} MB)",
+                usage / 1024 / 1024,
+                budget / 1024 / 1024
+            );
+        }
+        self.texture_memory_usage = usage;
+    }
+
     fn get_context(&self) -> ContentProviderContext {
         ContentProviderContext {
             content_folder: self.content_folder.clone(),
             http_queue_requester: self.http_queue_requester.clone(),
             resource_provider: self.resource_provider.clone(),
+            content_diagnostics: self.content_diagnostics.clone(),
             godot_single_thread: self.godot_single_thread.clone(),
-            texture_quality: self.texture_quality.clone(),
+            texture_quality: self
+                .texture_quality
+                .capped_by_data_saver(DclConfig::is_data_saver_enabled()),
         }
     }
 }
+
+// Returns the change in memory usage
+fn set_texture_downscale_level(texture_entry: &mut Gd<TextureEntry>, level: u32) -> i64 {
+    let previous_size = texture_entry.bind().memory_size();
+    if texture_entry.bind_mut().set_downscale_level(level) {
+        texture_entry.bind().memory_size() - previous_size
+    } else {
+        0
+    }
+}
@@ -1,4 +1,7 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use godot::{
     bind::GodotClass,
@@ -10,10 +13,12 @@ use godot::{
         node::ProcessMode,
         AnimatableBody3D, Animation, AnimationLibrary, AnimationPlayer, BaseMaterial3D,
         CollisionShape3D, ConcavePolygonShape3D, GltfDocument, GltfState, ImageTexture,
-        MeshInstance3D, Node, Node3D, NodeExt, StaticBody3D,
+        MeshInstance3D, Node, Node3D, NodeExt, PackedScene, ResourceLoader, ResourceSaver,
+        StaticBody3D,
     },
     obj::{EngineEnum, Gd, InstanceId},
 };
+use multihash_codetable::MultihashDigest;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
 use crate::{content::texture::resize_image, godot_classes::resource_locker::ResourceLocker};
@@ -27,15 +32,30 @@ use super::{
     thread_safety::GodotSingleThreadSafety,
 };
 
+// Bump this version when the import process changes (post_import_process, create_colliders, etc)
+//  so the processed gltfs stored in the cache folder are not used anymore
+const GLTF_IMPORTER_VERSION: u32 = 1;
+
 pub async fn internal_load_gltf(
     file_path: String,
     content_mapping: ContentMappingAndUrlRef,
     ctx: ContentProviderContext,
 ) -> Result<(Gd<Node3D>, GodotSingleThreadSafety), anyhow::Error> {
-    let base_path = Arc::new(get_base_dir(&file_path));
+    let (absolute_file_path, dependencies_hash) =
+        resolve_gltf_dependencies(&file_path, &content_mapping, &ctx).await?;
+    import_gltf(absolute_file_path, dependencies_hash, content_mapping, ctx).await
+}
+
+// Fetch the gltf file and resolve the hash of each dependency (images and buffers)
+async fn resolve_gltf_dependencies(
+    file_path: &str,
+    content_mapping: &ContentMappingAndUrlRef,
+    ctx: &ContentProviderContext,
+) -> Result<(String, Vec<(String, String)>), anyhow::Error> {
+    let base_path = Arc::new(get_base_dir(file_path));
 
     let file_hash = content_mapping
-        .get_hash(file_path.as_str())
+        .get_hash(file_path)
         .ok_or(anyhow::Error::msg("File not found in the content mappings"))?;
 
     let url = format!("{}{}", content_mapping.base_url, file_hash);
@@ -63,10 +83,20 @@ pub async fn internal_load_gltf(
         })
         .collect::<Vec<(String, Option<String>)>>();
 
-    if dependencies.iter().any(|(_, hash)| hash.is_none()) {
-        return Err(anyhow::Error::msg(
-            "There are some missing dependencies in the gltf".to_string(),
-        ));
+    let missing_dependencies = dependencies
+        .iter()
+        .filter(|(_, hash)| hash.is_none())
+        .map(|(file_path, _)| file_path.clone())
+        .collect::<Vec<String>>();
+
+    if !missing_dependencies.is_empty() {
+        let error_message = format!(
+            "There are some missing dependencies in the gltf: {}",
+            missing_dependencies.join(", ")
+        );
+        ctx.content_diagnostics
+            .record_missing_dependencies(file_hash, missing_dependencies);
+        return Err(anyhow::Error::msg(error_message));
     }
 
     let dependencies_hash = dependencies
@@ -74,6 +104,15 @@ pub async fn internal_load_gltf(
         .map(|(file_path, hash)| (file_path, hash.unwrap()))
         .collect::<Vec<(String, String)>>();
 
+    Ok((absolute_file_path, dependencies_hash))
+}
+
+async fn import_gltf(
+    absolute_file_path: String,
+    dependencies_hash: Vec<(String, String)>,
+    content_mapping: ContentMappingAndUrlRef,
+    ctx: ContentProviderContext,
+) -> Result<(Gd<Node3D>, GodotSingleThreadSafety), anyhow::Error> {
     let futures = dependencies_hash.iter().map(|(_, dependency_file_hash)| {
         let ctx = ctx.clone();
         let content_mapping = content_mapping.clone();
@@ -122,6 +161,10 @@ pub async fn internal_load_gltf(
 
     new_gltf_state.set_additional_data("base_path".into(), "some".to_variant());
     new_gltf_state.set_additional_data("mappings".into(), mappings.to_variant());
+    new_gltf_state.set_additional_data(
+        "max_texture_size".into(),
+        ctx.texture_quality.to_max_size().to_variant(),
+    );
 
     let err = new_gltf
         .append_from_file_ex(
@@ -201,16 +244,189 @@ pub fn post_import_process(node_to_inspect: Gd<Node>, max_size: i32) {
     }
 }
 
+// Estimated memory of the textures embedded in the gltf materials, as uncompressed RGBA8
+//  with mipmaps. The textures shared by several materials are counted once
+pub fn get_gltf_texture_memory_size(node: Gd<Node>) -> i64 {
+    let mut texture_ids = HashSet::new();
+    collect_gltf_texture_sizes(node, &mut texture_ids)
+}
+
+fn collect_gltf_texture_sizes(
+    node_to_inspect: Gd<Node>,
+    texture_ids: &mut HashSet<InstanceId>,
+) -> i64 {
+    let mut size = 0;
+    for child in node_to_inspect.get_children().iter_shared() {
+        if let Ok(mesh_instance_3d) = child.clone().try_cast::<MeshInstance3D>() {
+            if let Some(mesh) = mesh_instance_3d.get_mesh() {
+                for surface_index in 0..mesh.get_surface_count() {
+                    let Some(material) = mesh.surface_get_material(surface_index) else {
+                        continue;
+                    };
+                    let Ok(base_material) = material.try_cast::<BaseMaterial3D>() else {
+                        continue;
+                    };
+                    for ord in 0..TextureParam::TEXTURE_MAX.ord() {
+                        let Some(texture) = base_material.get_texture(TextureParam::from_ord(ord))
+                        else {
+                            continue;
+                        };
+                        if texture_ids.insert(texture.instance_id()) {
+                            let pixels = texture.get_width() as i64 * texture.get_height() as i64;
+                            size += pixels * 4 * 4 / 3;
+                        }
+                    }
+                }
+            }
+        }
+
+        size += collect_gltf_texture_sizes(child, texture_ids);
+    }
+    size
+}
+
 pub async fn load_gltf_scene_content(
     file_path: String,
     content_mapping: ContentMappingAndUrlRef,
     ctx: ContentProviderContext,
 ) -> Result<Option<Variant>, anyhow::Error> {
-    let (node, _thread_safe_check) = internal_load_gltf(file_path, content_mapping, ctx).await?;
+    let file_hash = content_mapping
+        .get_hash(file_path.as_str())
+        .ok_or(anyhow::Error::msg("File not found in the content mappings"))?
+        .clone();
+
+    let (absolute_file_path, dependencies_hash) =
+        resolve_gltf_dependencies(&file_path, &content_mapping, &ctx).await?;
+
+    let processed_file_path = get_processed_gltf_path(&file_hash, &dependencies_hash, &ctx);
+    if let Some(node) = load_processed_gltf(&processed_file_path, &ctx).await {
+        return Ok(Some(node.to_variant()));
+    }
+
+    let (node, _thread_safe_check) = import_gltf(
+        absolute_file_path,
+        dependencies_hash,
+        content_mapping,
+        ctx.clone(),
+    )
+    .await?;
     create_colliders(node.clone().upcast());
+
+    if save_processed_gltf(node.clone(), &processed_file_path) {
+        if let Err(err) = ctx
+            .resource_provider
+            .register_file(&processed_file_path)
+            .await
+        {
+            tracing::error!("Error registering processed gltf {processed_file_path}: {err}");
+        }
+    }
+
     Ok(Some(node.to_variant()))
 }
 
+// The processed gltf depends on the file, the dependencies it was resolved with (a gltf
+//  can be shared by scenes with different mappings), the texture quality and the importer
+//  version. The file is kept between sessions, so the dependencies hash must be stable
+fn get_processed_gltf_path(
+    file_hash: &str,
+    dependencies_hash: &[(String, String)],
+    ctx: &ContentProviderContext,
+) -> String {
+    format!(
+        "{}{}_q{}_v{}_{}.scn",
+        ctx.content_folder,
+        file_hash,
+        ctx.texture_quality.to_i32(),
+        GLTF_IMPORTER_VERSION,
+        get_dependencies_key(dependencies_hash)
+    )
+}
+
+fn get_dependencies_key(dependencies_hash: &[(String, String)]) -> String {
+    let mut data = String::new();
+    for (file_path, file_hash) in dependencies_hash {
+        data.push_str(file_path);
+        data.push('\0');
+        data.push_str(file_hash);
+        data.push('\n');
+    }
+
+    let digest = multihash_codetable::Code::Sha2_256.digest(data.as_bytes());
+    digest.digest()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+async fn load_processed_gltf(
+    processed_file_path: &str,
+    ctx: &ContentProviderContext,
+) -> Option<Gd<Node3D>> {
+    if tokio::fs::metadata(processed_file_path).await.is_err() {
+        return None;
+    }
+
+    ctx.resource_provider
+        .touch_file_by_path(processed_file_path)
+        .await;
+
+    let thread_safe_check = GodotSingleThreadSafety::acquire_owned(ctx).await?;
+
+    let node = ResourceLoader::singleton()
+        .load(GString::from(processed_file_path.as_str()))
+        .and_then(|resource| resource.try_cast::<PackedScene>().ok())
+        .and_then(|packed_scene| packed_scene.instantiate())
+        .and_then(|node| node.try_cast::<Node3D>().ok());
+
+    let Some(node) = node else {
+        drop(thread_safe_check);
+        // a corrupted or incompatible file, it will be processed and saved again
+        tracing::warn!("Error loading processed gltf {processed_file_path}, removing it");
+        ctx.resource_provider
+            .remove_file_by_path(processed_file_path)
+            .await;
+        return None;
+    };
+
+    ResourceLocker::attach_to(node.clone().upcast());
+    Some(node)
+}
+
+fn save_processed_gltf(node: Gd<Node3D>, processed_file_path: &str) -> bool {
+    // only the nodes owned by the root are packed, the generated colliders don't have owner
+    set_owner_recursive(node.clone().upcast(), node.clone().upcast());
+
+    let mut packed_scene = PackedScene::new();
+    if packed_scene.pack(node.upcast()) != Error::OK {
+        tracing::error!("Error packing processed gltf {processed_file_path}");
+        return false;
+    }
+
+    let err = ResourceSaver::singleton()
+        .save_ex(packed_scene.upcast())
+        .path(GString::from(processed_file_path))
+        .done();
+    if err != Error::OK {
+        tracing::error!(
+            "Error saving processed gltf {processed_file_path}: {:?}",
+            err
+        );
+        return false;
+    }
+
+    true
+}
+
+fn set_owner_recursive(node_to_inspect: Gd<Node>, owner: Gd<Node>) {
+    for mut child in node_to_inspect.get_children().iter_shared() {
+        if child.get_owner().is_none() {
+            child.set_owner(owner.clone());
+        }
+        set_owner_recursive(child, owner.clone());
+    }
+}
+
 pub async fn load_gltf_wearable(
     file_path: String,
     content_mapping: ContentMappingAndUrlRef,
@@ -649,3 +865,19 @@ pub struct DclEmoteGltf {
     #[var]
     prop_animation: Option<Gd<Animation>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::get_dependencies_key;
+
+    #[test]
+    fn test_dependencies_key_is_stable() {
+        let dependencies = vec![
+            ("textures/a.png".to_string(), "bafya".to_string()),
+            ("b.bin".to_string(), "bafyb".to_string()),
+        ];
+        // sha256, it must not change between builds or the cached gltfs are lost
+        assert_eq!(get_dependencies_key(&dependencies), "3e87b8eacb064525");
+        assert_eq!(get_dependencies_key(&[]), "e3b0c44298fc1c14");
+    }
+}
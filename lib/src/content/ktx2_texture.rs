@@ -0,0 +1,542 @@
+use std::{io::Read, ops::Range, sync::Once};
+
+use basis_universal::{
+    DecodeFlags, LowLevelUastcTranscoder, SliceParametersUastc, TranscodeParameters, Transcoder,
+    TranscoderBlockFormat, TranscoderTextureFormat,
+};
+use godot::{
+    engine::{
+        global::Error, image::Format, GltfDocumentExtension, GltfState, GltfTexture,
+        IGltfDocumentExtension, Image, Os, RenderingServer,
+    },
+    prelude::*,
+};
+use ktx2::{BasicDataFormatDescriptor, ColorModel, SupercompressionScheme};
+
+use crate::utils::infer_mime;
+
+use super::packed_array::PackedByteArrayFromVec;
+
+const KHR_TEXTURE_BASISU: &str = "KHR_texture_basisu";
+
+// UASTC channel ids from the KTX2 data format descriptor
+const UASTC_CHANNEL_RGBA: u32 = 3;
+const UASTC_CHANNEL_RRRG: u32 = 5;
+
+static TRANSCODER_INIT: Once = Once::new();
+
+fn init_transcoder() {
+    TRANSCODER_INIT.call_once(basis_universal::transcoder_init);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GpuTextureFormat {
+    Bc7,
+    Etc2,
+    Astc4x4,
+    Rgba8,
+}
+
+impl GpuTextureFormat {
+    // ASTC/ETC2 on mobile, BC7 on desktop, and uncompressed RGBA when nothing else is available
+    pub fn preferred() -> Self {
+        let rendering_server = RenderingServer::singleton();
+        if Os::singleton().has_feature("mobile".into()) {
+            if rendering_server.has_os_feature("astc".into()) {
+                return Self::Astc4x4;
+            }
+            if rendering_server.has_os_feature("etc2".into()) {
+                return Self::Etc2;
+            }
+        } else if rendering_server.has_os_feature("bptc".into()) {
+            return Self::Bc7;
+        }
+        Self::Rgba8
+    }
+
+    fn to_block_format(self) -> TranscoderBlockFormat {
+        match self {
+            Self::Bc7 => TranscoderBlockFormat::BC7,
+            Self::Etc2 => TranscoderBlockFormat::ETC2_RGBA,
+            Self::Astc4x4 => TranscoderBlockFormat::ASTC_4x4,
+            Self::Rgba8 => TranscoderBlockFormat::RGBA32,
+        }
+    }
+
+    fn to_texture_format(self) -> TranscoderTextureFormat {
+        match self {
+            Self::Bc7 => TranscoderTextureFormat::BC7_RGBA,
+            Self::Etc2 => TranscoderTextureFormat::ETC2_RGBA,
+            Self::Astc4x4 => TranscoderTextureFormat::ASTC_4x4_RGBA,
+            Self::Rgba8 => TranscoderTextureFormat::RGBA32,
+        }
+    }
+
+    fn to_image_format(self) -> Format {
+        match self {
+            Self::Bc7 => Format::FORMAT_BPTC_RGBA,
+            Self::Etc2 => Format::FORMAT_ETC2_RGBA8,
+            Self::Astc4x4 => Format::FORMAT_ASTC_4x4,
+            Self::Rgba8 => Format::FORMAT_RGBA8,
+        }
+    }
+}
+
+fn decompress_zstd(data: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+    let mut source = data;
+    let mut decoder = ruzstd::StreamingDecoder::new(&mut source)
+        .map_err(|err| anyhow::Error::msg(format!("Invalid zstd level data: {err:?}")))?;
+    let mut result = Vec::new();
+    decoder.read_to_end(&mut result)?;
+    Ok(result)
+}
+
+fn mip_chain_length(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+// Transcodes a KTX2 file to the best GPU format supported by the current platform,
+//  the first mip level that fits in `max_size` is used as base level, so there is
+//  no need to resize (compressed images can't be resized)
+pub fn load_ktx2_image(bytes: &[u8], max_size: i32) -> Result<Gd<Image>, anyhow::Error> {
+    let reader = ktx2::Reader::new(bytes)
+        .map_err(|err| anyhow::Error::msg(format!("Invalid KTX2 data: {err:?}")))?;
+    let header = reader.header();
+
+    let basic_dfd = reader
+        .data_format_descriptors()
+        .find_map(|dfd| BasicDataFormatDescriptor::parse(dfd.data).ok());
+    let color_model = basic_dfd.as_ref().and_then(|dfd| dfd.color_model);
+    let is_uastc = color_model == Some(ColorModel::UASTC);
+    let is_etc1s = color_model == Some(ColorModel::ETC1S)
+        && header.supercompression_scheme == Some(SupercompressionScheme::BasisLZ);
+
+    if !is_uastc && !is_etc1s {
+        // non-basis payloads are handed to the engine KTX loader
+        let mut image = Image::new();
+        let err = image.load_ktx_from_buffer(PackedByteArray::from_vec(bytes));
+        if err != Error::OK {
+            return Err(anyhow::Error::msg(format!(
+                "Error loading KTX2 texture with the engine loader: {:?}",
+                err
+            )));
+        }
+        return Ok(image);
+    }
+
+    let width = header.pixel_width.max(1);
+    let height = header.pixel_height.max(1);
+    let level_count = header.level_count.max(1);
+
+    let first_level = (0..level_count)
+        .find(|level| {
+            let level_size = (width >> level).max(1).max((height >> level).max(1));
+            level_size as i32 <= max_size
+        })
+        .unwrap_or(level_count - 1);
+
+    // Godot only accepts full mip chains, otherwise the base level is used alone
+    let base_width = (width >> first_level).max(1);
+    let base_height = (height >> first_level).max(1);
+    let use_mipmaps = level_count - first_level == mip_chain_length(base_width, base_height);
+    let last_level = if use_mipmaps {
+        level_count
+    } else {
+        first_level + 1
+    };
+
+    init_transcoder();
+
+    let gpu_format = GpuTextureFormat::preferred();
+    let data = match basic_dfd {
+        Some(basic_dfd) if is_uastc => {
+            transcode_uastc_levels(&reader, &basic_dfd, first_level..last_level, gpu_format)?
+        }
+        _ => transcode_etc1s_levels(&reader, first_level..last_level, gpu_format)?,
+    };
+
+    Image::create_from_data(
+        base_width as i32,
+        base_height as i32,
+        use_mipmaps,
+        gpu_format.to_image_format(),
+        PackedByteArray::from_vec(&data),
+    )
+    .ok_or(anyhow::Error::msg("Error creating image from KTX2 data"))
+}
+
+fn transcode_uastc_levels(
+    reader: &ktx2::Reader<&[u8]>,
+    basic_dfd: &BasicDataFormatDescriptor,
+    levels_range: Range<u32>,
+    gpu_format: GpuTextureFormat,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let header = reader.header();
+    let has_alpha = basic_dfd
+        .sample_information()
+        .next()
+        .map(|sample| {
+            sample.channel_type == UASTC_CHANNEL_RGBA || sample.channel_type == UASTC_CHANNEL_RRRG
+        })
+        .unwrap_or(false);
+
+    let width = header.pixel_width.max(1);
+    let height = header.pixel_height.max(1);
+    let levels = reader.levels().collect::<Vec<&[u8]>>();
+    let transcoder = LowLevelUastcTranscoder::new();
+    let mut data = Vec::new();
+
+    for level in levels_range {
+        let level_width = (width >> level).max(1);
+        let level_height = (height >> level).max(1);
+
+        let level_data = levels
+            .get(level as usize)
+            .ok_or(anyhow::Error::msg("KTX2 level out of range"))?;
+
+        let level_data = match header.supercompression_scheme {
+            None => level_data.to_vec(),
+            Some(SupercompressionScheme::Zstandard) => decompress_zstd(level_data)?,
+            Some(other) => {
+                return Err(anyhow::Error::msg(format!(
+                    "Unsupported KTX2 supercompression scheme for UASTC: {other:?}"
+                )))
+            }
+        };
+
+        let transcoded = transcoder
+            .transcode_slice(
+                &level_data,
+                SliceParametersUastc {
+                    num_blocks_x: (level_width + 3) / 4,
+                    num_blocks_y: (level_height + 3) / 4,
+                    has_alpha,
+                    original_width: level_width,
+                    original_height: level_height,
+                },
+                DecodeFlags::HIGH_QUALITY,
+                gpu_format.to_block_format(),
+            )
+            .map_err(|err| {
+                anyhow::Error::msg(format!("Error transcoding KTX2 level {level}: {err:?}"))
+            })?;
+
+        data.extend_from_slice(&transcoded);
+    }
+
+    Ok(data)
+}
+
+// The ETC1S levels share the codebooks of the supercompression global data, they're
+//  repacked as a .basis file to use the same transcoder as the standalone basis files
+fn transcode_etc1s_levels(
+    reader: &ktx2::Reader<&[u8]>,
+    levels_range: Range<u32>,
+    gpu_format: GpuTextureFormat,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let basis_file = ktx2_to_basis_file(reader, levels_range.clone())?;
+
+    let mut transcoder = Transcoder::new();
+    transcoder
+        .prepare_transcoding(&basis_file)
+        .map_err(|_| anyhow::Error::msg("Invalid KTX2 BasisLZ data"))?;
+
+    let mut data = Vec::new();
+    for (level_index, level) in levels_range.enumerate() {
+        let transcoded = transcoder
+            .transcode_image_level(
+                &basis_file,
+                gpu_format.to_texture_format(),
+                TranscodeParameters {
+                    image_index: 0,
+                    level_index: level_index as u32,
+                    decode_flags: Some(DecodeFlags::HIGH_QUALITY),
+                    output_row_pitch_in_blocks_or_pixels: None,
+                    output_rows_in_pixels: None,
+                },
+            )
+            .map_err(|err| {
+                anyhow::Error::msg(format!("Error transcoding KTX2 level {level}: {err:?}"))
+            })?;
+        data.extend_from_slice(&transcoded);
+    }
+    transcoder.end_transcoding();
+
+    Ok(data)
+}
+
+// .basis file layout, see basisu_file_headers.h
+const BASIS_SIGNATURE: u16 = 0x4273;
+const BASIS_VERSION: u16 = 0x13;
+const BASIS_HEADER_SIZE: usize = 77;
+const BASIS_SLICE_DESC_SIZE: usize = 23;
+const BASIS_HEADER_FLAG_ETC1S: u16 = 1;
+const BASIS_HEADER_FLAG_HAS_ALPHA_SLICES: u16 = 4;
+const BASIS_SLICE_FLAG_HAS_ALPHA: u8 = 1;
+// BasisLZ global data header and per image descriptor sizes
+const BASIS_LZ_GLOBAL_HEADER_SIZE: usize = 20;
+const BASIS_LZ_IMAGE_DESC_SIZE: usize = 20;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn write_uint(buffer: &mut Vec<u8>, value: u32, size: usize) {
+    buffer.extend_from_slice(&value.to_le_bytes()[..size]);
+}
+
+// CRC-16 used by the basis file headers
+fn basis_crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = !0;
+    for byte in data {
+        let q = (*byte as u16) ^ (crc >> 8);
+        let k = (q >> 4) ^ q;
+        crc = (crc << 8) ^ k ^ (k << 5) ^ (k << 12);
+    }
+    !crc
+}
+
+struct BasisSlice<'a> {
+    level_index: u32,
+    has_alpha: bool,
+    width: u32,
+    height: u32,
+    data: &'a [u8],
+}
+
+// Builds a single image .basis file with the `levels_range` levels of a 2D BasisLZ KTX2
+fn ktx2_to_basis_file(
+    reader: &ktx2::Reader<&[u8]>,
+    levels_range: Range<u32>,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let header = reader.header();
+    if header.layer_count > 1 || header.face_count > 1 || header.pixel_depth > 1 {
+        return Err(anyhow::Error::msg(
+            "Only 2D KTX2 BasisLZ textures are supported",
+        ));
+    }
+
+    let invalid = || anyhow::Error::msg("Invalid KTX2 BasisLZ global data");
+    let global_data = reader.supercompression_global_data();
+    let endpoint_count = read_u16(global_data, 0).ok_or_else(invalid)?;
+    let selector_count = read_u16(global_data, 2).ok_or_else(invalid)?;
+    let endpoints_length = read_u32(global_data, 4).ok_or_else(invalid)? as usize;
+    let selectors_length = read_u32(global_data, 8).ok_or_else(invalid)? as usize;
+    let tables_length = read_u32(global_data, 12).ok_or_else(invalid)? as usize;
+
+    let level_count = header.level_count.max(1) as usize;
+    let endpoints_offset = BASIS_LZ_GLOBAL_HEADER_SIZE + level_count * BASIS_LZ_IMAGE_DESC_SIZE;
+    let selectors_offset = endpoints_offset + endpoints_length;
+    let tables_offset = selectors_offset + selectors_length;
+    let endpoints = global_data
+        .get(endpoints_offset..selectors_offset)
+        .ok_or_else(invalid)?;
+    let selectors = global_data
+        .get(selectors_offset..tables_offset)
+        .ok_or_else(invalid)?;
+    let tables = global_data
+        .get(tables_offset..tables_offset + tables_length)
+        .ok_or_else(invalid)?;
+
+    let width = header.pixel_width.max(1);
+    let height = header.pixel_height.max(1);
+    let levels = reader.levels().collect::<Vec<&[u8]>>();
+
+    let mut slices = Vec::new();
+    for (level_index, level) in levels_range.enumerate() {
+        let level_data = levels.get(level as usize).ok_or_else(invalid)?;
+        let image_desc = BASIS_LZ_GLOBAL_HEADER_SIZE + level as usize * BASIS_LZ_IMAGE_DESC_SIZE;
+        let rgb_offset = read_u32(global_data, image_desc + 4).ok_or_else(invalid)? as usize;
+        let rgb_length = read_u32(global_data, image_desc + 8).ok_or_else(invalid)? as usize;
+        let alpha_offset = read_u32(global_data, image_desc + 12).ok_or_else(invalid)? as usize;
+        let alpha_length = read_u32(global_data, image_desc + 16).ok_or_else(invalid)? as usize;
+
+        let level_width = (width >> level).max(1);
+        let level_height = (height >> level).max(1);
+        slices.push(BasisSlice {
+            level_index: level_index as u32,
+            has_alpha: false,
+            width: level_width,
+            height: level_height,
+            data: level_data
+                .get(rgb_offset..rgb_offset + rgb_length)
+                .ok_or_else(invalid)?,
+        });
+        if alpha_length > 0 {
+            slices.push(BasisSlice {
+                level_index: level_index as u32,
+                has_alpha: true,
+                width: level_width,
+                height: level_height,
+                data: level_data
+                    .get(alpha_offset..alpha_offset + alpha_length)
+                    .ok_or_else(invalid)?,
+            });
+        }
+    }
+
+    let has_alpha_slices = slices.iter().any(|slice| slice.has_alpha);
+    let slice_descs_offset = BASIS_HEADER_SIZE;
+    let endpoints_file_offset = slice_descs_offset + slices.len() * BASIS_SLICE_DESC_SIZE;
+    let selectors_file_offset = endpoints_file_offset + endpoints.len();
+    let tables_file_offset = selectors_file_offset + selectors.len();
+    let mut slice_data_offset = tables_file_offset + tables.len();
+
+    let mut body = Vec::new();
+    for slice in slices.iter() {
+        write_uint(&mut body, 0, 3);
+        write_uint(&mut body, slice.level_index, 1);
+        let flags = if slice.has_alpha {
+            BASIS_SLICE_FLAG_HAS_ALPHA
+        } else {
+            0
+        };
+        write_uint(&mut body, flags as u32, 1);
+        write_uint(&mut body, slice.width, 2);
+        write_uint(&mut body, slice.height, 2);
+        write_uint(&mut body, (slice.width + 3) / 4, 2);
+        write_uint(&mut body, (slice.height + 3) / 4, 2);
+        write_uint(&mut body, slice_data_offset as u32, 4);
+        write_uint(&mut body, slice.data.len() as u32, 4);
+        write_uint(&mut body, basis_crc16(slice.data) as u32, 2);
+        slice_data_offset += slice.data.len();
+    }
+    body.extend_from_slice(endpoints);
+    body.extend_from_slice(selectors);
+    body.extend_from_slice(tables);
+    for slice in slices.iter() {
+        body.extend_from_slice(slice.data);
+    }
+
+    let mut flags = BASIS_HEADER_FLAG_ETC1S;
+    if has_alpha_slices {
+        flags |= BASIS_HEADER_FLAG_HAS_ALPHA_SLICES;
+    }
+
+    let mut file = Vec::with_capacity(BASIS_HEADER_SIZE + body.len());
+    write_uint(&mut file, BASIS_SIGNATURE as u32, 2);
+    write_uint(&mut file, BASIS_VERSION as u32, 2);
+    write_uint(&mut file, BASIS_HEADER_SIZE as u32, 2);
+    write_uint(&mut file, 0, 2); // header crc16, filled below
+    write_uint(&mut file, body.len() as u32, 4);
+    write_uint(&mut file, basis_crc16(&body) as u32, 2);
+    write_uint(&mut file, slices.len() as u32, 3);
+    write_uint(&mut file, 1, 3); // total images
+    write_uint(&mut file, 0, 1); // ETC1S texture format
+    write_uint(&mut file, flags as u32, 2);
+    write_uint(&mut file, 0, 1); // 2D texture type
+    write_uint(&mut file, 0, 3); // us per frame
+    write_uint(&mut file, 0, 4); // reserved
+    write_uint(&mut file, 0, 4); // userdata0
+    write_uint(&mut file, 0, 4); // userdata1
+    write_uint(&mut file, endpoint_count as u32, 2);
+    write_uint(&mut file, endpoints_file_offset as u32, 4);
+    write_uint(&mut file, endpoints.len() as u32, 3);
+    write_uint(&mut file, selector_count as u32, 2);
+    write_uint(&mut file, selectors_file_offset as u32, 4);
+    write_uint(&mut file, selectors.len() as u32, 3);
+    write_uint(&mut file, tables_file_offset as u32, 4);
+    write_uint(&mut file, tables.len() as u32, 4);
+    write_uint(&mut file, slice_descs_offset as u32, 4);
+    write_uint(&mut file, 0, 4); // extended file offset
+    write_uint(&mut file, 0, 4); // extended file size
+
+    let header_crc16 = basis_crc16(&file[8..]);
+    file[6..8].copy_from_slice(&header_crc16.to_le_bytes());
+    file.extend_from_slice(&body);
+
+    Ok(file)
+}
+
+// Decodes the KHR_texture_basisu images embedded in GLTFs, the `max_texture_size`
+//  additional data in the GLTFState is used to select the base mip level
+#[derive(GodotClass)]
+#[class(init, base=GltfDocumentExtension)]
+pub struct DclGltfKtx2Extension {}
+
+#[godot_api]
+impl IGltfDocumentExtension for DclGltfKtx2Extension {
+    fn get_supported_extensions(&mut self) -> PackedStringArray {
+        let mut extensions = PackedStringArray::new();
+        extensions.push(KHR_TEXTURE_BASISU.into());
+        extensions
+    }
+
+    fn parse_image_data(
+        &mut self,
+        state: Gd<GltfState>,
+        image_data: PackedByteArray,
+        mime_type: GString,
+        mut ret_image: Gd<Image>,
+    ) -> Error {
+        let bytes = image_data.as_slice();
+        if mime_type.to_string() != "image/ktx2" && !infer_mime::is_ktx2(bytes) {
+            return Error::ERR_SKIP;
+        }
+
+        let max_size = state
+            .get_additional_data("max_texture_size".into())
+            .try_to::<i32>()
+            .unwrap_or(i32::MAX);
+
+        match load_ktx2_image(bytes, max_size) {
+            Ok(image) => {
+                ret_image.copy_from(image);
+                Error::OK
+            }
+            Err(err) => {
+                tracing::error!("Error loading gltf KTX2 image: {err}");
+                Error::ERR_FILE_CORRUPT
+            }
+        }
+    }
+
+    fn parse_texture_json(
+        &mut self,
+        _state: Gd<GltfState>,
+        texture_json: Dictionary,
+        mut ret_gltf_texture: Gd<GltfTexture>,
+    ) -> Error {
+        let source = texture_json
+            .get("extensions")
+            .and_then(|extensions| extensions.try_to::<Dictionary>().ok())
+            .and_then(|extensions| extensions.get(KHR_TEXTURE_BASISU))
+            .and_then(|basisu| basisu.try_to::<Dictionary>().ok())
+            .and_then(|basisu| basisu.get("source"))
+            .and_then(|source| source.try_to::<i32>().ok());
+
+        let Some(source) = source else {
+            return Error::ERR_SKIP;
+        };
+
+        ret_gltf_texture.set_src_image(source);
+        Error::OK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mip_chain_length() {
+        assert_eq!(mip_chain_length(1, 1), 1);
+        assert_eq!(mip_chain_length(256, 256), 9);
+        assert_eq!(mip_chain_length(512, 128), 10);
+        assert_eq!(mip_chain_length(300, 20), 9);
+    }
+
+    #[test]
+    fn test_basis_crc16() {
+        assert_eq!(basis_crc16(b""), 0);
+        assert_eq!(basis_crc16(b"123456789"), 0xd64e);
+    }
+}
@@ -1,9 +1,11 @@
 mod audio;
+mod content_diagnostics;
 pub mod content_mapping;
 pub mod content_notificator;
 pub mod content_provider;
 mod file_string;
 mod gltf;
+mod ktx2_texture;
 pub mod packed_array;
 mod profile;
 #[cfg(feature = "use_resource_tracking")]
@@ -9,9 +9,11 @@ use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
 use tokio::sync::{Notify, OnceCell, RwLock, Semaphore};
 use tokio::time::Instant;
 
+use super::content_diagnostics::ContentDiagnostics;
 #[cfg(feature = "use_resource_tracking")]
 use super::resource_download_tracking::ResourceDownloadTracking;
 use crate::content::semaphore_ext::SemaphoreExt;
+use crate::http_request::bandwidth_limiter::GLOBAL_BANDWIDTH_LIMITER;
 
 pub struct FileMetadata {
     file_size: i64,
@@ -27,10 +29,25 @@ pub struct ResourceProvider {
     client: Client,
     initialized: OnceCell<()>,
     semaphore: Arc<Semaphore>,
+    content_diagnostics: Arc<ContentDiagnostics>,
     #[cfg(feature = "use_resource_tracking")]
     download_tracking: Arc<ResourceDownloadTracking>,
 }
 
+struct DownloadError {
+    http_status: Option<u16>,
+    message: String,
+}
+
+impl From<String> for DownloadError {
+    fn from(message: String) -> Self {
+        Self {
+            http_status: None,
+            message,
+        }
+    }
+}
+
 const UPDATE_THRESHOLD: u64 = 1_024 * 1_024; // 1 MB threshold
 
 impl ResourceProvider {
@@ -39,6 +56,7 @@ impl ResourceProvider {
         cache_folder: &str,
         max_cache_size: i64,
         max_concurrent_downloads: usize,
+        content_diagnostics: Arc<ContentDiagnostics>,
         #[cfg(feature = "use_resource_tracking")] download_tracking: Arc<ResourceDownloadTracking>,
     ) -> Self {
         ResourceProvider {
@@ -50,6 +68,7 @@ impl ResourceProvider {
             initialized: OnceCell::new(),
             semaphore: Arc::new(Semaphore::new(max_concurrent_downloads)),
             downloaded_size: AtomicU64::new(0),
+            content_diagnostics,
             #[cfg(feature = "use_resource_tracking")]
             download_tracking,
         }
@@ -150,7 +169,7 @@ impl ResourceProvider {
         url: &str,
         dest: &Path,
         #[cfg(feature = "use_resource_tracking")] file_hash: &str,
-    ) -> Result<(), String> {
+    ) -> Result<(), DownloadError> {
         let tmp_dest = dest.with_extension("tmp");
         let response = self
             .client
@@ -159,6 +178,14 @@ impl ResourceProvider {
             .await
             .map_err(|e| format!("Request error: {:?}", e))?;
 
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DownloadError {
+                http_status: Some(status.as_u16()),
+                message: format!("HTTP status {}", status),
+            });
+        }
+
         #[cfg(feature = "use_resource_tracking")]
         self.download_tracking.start(file_hash.to_string()).await;
 
@@ -178,6 +205,8 @@ impl ResourceProvider {
                 .await
                 .map_err(|e| format!("File write error: {:?}", e))?;
 
+            GLOBAL_BANDWIDTH_LIMITER.consume(chunk.len() as u64).await;
+
             accumulated_size += chunk.len() as u64;
             if accumulated_size > UPDATE_THRESHOLD {
                 self.downloaded_size
@@ -223,7 +252,7 @@ impl ResourceProvider {
         url: &str,
         dest: &Path,
         #[cfg(feature = "use_resource_tracking")] file_hash: &str,
-    ) -> Result<Vec<u8>, String> {
+    ) -> Result<Vec<u8>, DownloadError> {
         let tmp_dest = dest.with_extension("tmp");
         let response = self
             .client
@@ -232,6 +261,14 @@ impl ResourceProvider {
             .await
             .map_err(|e| format!("Request error: {:?}", e))?;
 
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DownloadError {
+                http_status: Some(status.as_u16()),
+                message: format!("HTTP status {}", status),
+            });
+        }
+
         #[cfg(feature = "use_resource_tracking")]
         self.download_tracking.start(file_hash.to_string()).await;
         #[cfg(feature = "use_resource_tracking")]
@@ -252,6 +289,8 @@ impl ResourceProvider {
                 .map_err(|e| format!("File write error: {:?}", e))?;
             buffer.extend_from_slice(&chunk);
 
+            GLOBAL_BANDWIDTH_LIMITER.consume(chunk.len() as u64).await;
+
             accumulated_size += chunk.len() as u64;
             if accumulated_size > UPDATE_THRESHOLD {
                 self.downloaded_size
@@ -292,6 +331,16 @@ impl ResourceProvider {
         Ok(buffer)
     }
 
+    fn report_download_error(&self, file_hash: &str, url: &str, err: DownloadError) -> String {
+        self.content_diagnostics.record_download_error(
+            file_hash,
+            url,
+            err.http_status,
+            &err.message,
+        );
+        err.message
+    }
+
     async fn ensure_initialized(&self) -> Result<(), String> {
         self.initialized
             .get_or_try_init(|| async { self.initialize().await.map_err(|e| e.to_string()) })
@@ -397,19 +446,24 @@ impl ResourceProvider {
                 #[cfg(feature = "use_resource_tracking")]
                 file_hash,
             )
-            .await?;
+            .await
+            .map_err(|err| self.report_download_error(file_hash, url, err))?;
 
             let metadata = tokio::fs::metadata(absolute_file_path)
                 .await
                 .map_err(|e| format!("Failed to get metadata: {:?}", e))?;
             let file_size = metadata.len() as i64;
+            self.content_diagnostics
+                .record_download(file_hash, url, file_size as u64, false);
 
             let mut existing_files = self.existing_files.write().await;
             self.ensure_space_for(&mut existing_files, file_size).await;
             self.add_file(&mut existing_files, absolute_file_path.clone(), file_size)
                 .await;
         } else {
-            self.handle_existing_file(absolute_file_path).await?;
+            let data = self.handle_existing_file(absolute_file_path).await?;
+            self.content_diagnostics
+                .record_download(file_hash, url, data.len() as u64, true);
         }
 
         let mut pending_downloads = self.pending_downloads.write().await;
@@ -443,7 +497,10 @@ impl ResourceProvider {
                     #[cfg(feature = "use_resource_tracking")]
                     file_hash,
                 )
-                .await?;
+                .await
+                .map_err(|err| self.report_download_error(file_hash, url, err))?;
+            self.content_diagnostics
+                .record_download(file_hash, url, data.len() as u64, false);
             let metadata = tokio::fs::metadata(absolute_file_path)
                 .await
                 .map_err(|e| format!("Failed to get metadata: {:?}", e))?;
@@ -454,7 +511,10 @@ impl ResourceProvider {
                 .await;
             data
         } else {
-            self.handle_existing_file(absolute_file_path).await?
+            let data = self.handle_existing_file(absolute_file_path).await?;
+            self.content_diagnostics
+                .record_download(file_hash, url, data.len() as u64, true);
+            data
         };
 
         let mut pending_downloads = self.pending_downloads.write().await;
@@ -467,6 +527,46 @@ impl ResourceProvider {
         Ok(data)
     }
 
+    // Add a file written by other means (e.g. the processed gltfs) to the cache, so it
+    //  counts for the max cache size and it's removed when the space is needed
+    pub async fn register_file(&self, absolute_file_path: &str) -> Result<(), String> {
+        self.ensure_initialized().await?;
+
+        let metadata = tokio::fs::metadata(absolute_file_path)
+            .await
+            .map_err(|e| format!("Failed to get metadata: {:?}", e))?;
+        let file_size = metadata.len() as i64;
+
+        let mut existing_files = self.existing_files.write().await;
+        self.ensure_space_for(&mut existing_files, file_size).await;
+        self.add_file(
+            &mut existing_files,
+            absolute_file_path.to_string(),
+            file_size,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    // Removes a cached file that can't be used (e.g. a corrupted processed gltf), it's
+    //  deleted from the disk even if it wasn't registered
+    pub async fn remove_file_by_path(&self, absolute_file_path: &str) {
+        let mut existing_files = self.existing_files.write().await;
+        if self
+            .remove_file(&mut existing_files, absolute_file_path)
+            .await
+            .is_none()
+        {
+            let _ = fs::remove_file(absolute_file_path).await;
+        }
+    }
+
+    pub async fn touch_file_by_path(&self, absolute_file_path: &str) {
+        let mut existing_files = self.existing_files.write().await;
+        self.touch_file(&mut existing_files, absolute_file_path);
+    }
+
     // Method to clear the cache and delete all files from the file system
     pub async fn clear(&self) {
         if self.ensure_initialized().await.is_err() {
@@ -530,6 +630,7 @@ mod tests {
             path,
             max_cache_size,
             2,
+            Arc::new(ContentDiagnostics::new()),
             #[cfg(feature = "use_resource_tracking")]
             resource_download_tracking.clone(),
         ));
@@ -1,8 +1,8 @@
 use crate::utils::infer_mime;
 
 use super::{
-    content_provider::ContentProviderContext, packed_array::PackedByteArrayFromVec,
-    thread_safety::GodotSingleThreadSafety,
+    content_provider::ContentProviderContext, ktx2_texture::load_ktx2_image,
+    packed_array::PackedByteArrayFromVec, thread_safety::GodotSingleThreadSafety,
 };
 use godot::{
     bind::GodotClass,
@@ -23,6 +23,55 @@ pub struct TextureEntry {
     pub texture: Gd<Texture2D>,
     #[var]
     pub original_size: Vector2i,
+    // bytes used by `image`, and by `texture` at its full (loaded) size
+    #[var]
+    pub size_bytes: i64,
+    // each level halves the texture width and height, 0 is the loaded size
+    #[var]
+    pub downscale_level: u32,
+}
+
+impl TextureEntry {
+    pub fn memory_size(&self) -> i64 {
+        self.memory_size_at(self.downscale_level)
+    }
+
+    // The full size `image` stays in memory (e.g. to restore the texture when `level`
+    //  goes back to 0), so downscaling only reduces the part used by `texture`
+    pub fn memory_size_at(&self, level: u32) -> i64 {
+        self.size_bytes + (self.size_bytes >> (2 * level))
+    }
+
+    // Only uncompressed ImageTextures can be downscaled
+    pub fn can_downscale(&self) -> bool {
+        !self.image.is_compressed() && self.texture.clone().try_cast::<ImageTexture>().is_ok()
+    }
+
+    pub fn set_downscale_level(&mut self, level: u32) -> bool {
+        if level == self.downscale_level || !self.can_downscale() {
+            return false;
+        }
+
+        let Ok(mut texture) = self.texture.clone().try_cast::<ImageTexture>() else {
+            return false;
+        };
+
+        if level == 0 {
+            texture.set_image(self.image.clone());
+        } else {
+            // the resized copy is only alive until it's uploaded by `set_image`
+            let mut image = Image::new();
+            image.copy_from(self.image.clone());
+            image.resize(
+                (self.image.get_width() >> level).max(1),
+                (self.image.get_height() >> level).max(1),
+            );
+            texture.set_image(image);
+        }
+
+        self.downscale_level = level;
+        true
+    }
 }
 
 pub async fn load_image_texture(
@@ -47,8 +96,21 @@ pub async fn load_image_texture(
 
     let bytes = PackedByteArray::from_vec(&bytes_vec);
 
+    let max_size = ctx.texture_quality.to_max_size();
+
     let mut image = Image::new();
-    let err = if infer_mime::is_png(&bytes_vec) {
+    let err = if infer_mime::is_ktx2(&bytes_vec) {
+        match load_ktx2_image(&bytes_vec, max_size) {
+            Ok(ktx2_image) => {
+                image = ktx2_image;
+                Error::OK
+            }
+            Err(err) => {
+                tracing::error!("Error transcoding KTX2 texture {absolute_file_path}: {err}");
+                Error::ERR_FILE_CORRUPT
+            }
+        }
+    } else if infer_mime::is_png(&bytes_vec) {
         image.load_png_from_buffer(bytes)
     } else if infer_mime::is_jpeg(&bytes_vec) || infer_mime::is_jpeg2000(&bytes_vec) {
         image.load_jpg_from_buffer(bytes)
@@ -78,7 +140,6 @@ pub async fn load_image_texture(
 
     let original_size = image.get_size();
 
-    let max_size = ctx.texture_quality.to_max_size();
     let mut texture: Gd<Texture2D> = if std::env::consts::OS == "ios" {
         create_compressed_texture(&mut image, max_size)
     } else {
@@ -91,16 +152,26 @@ pub async fn load_image_texture(
 
     texture.set_name(GString::from(&url));
 
+    let size_bytes = image.get_data().len() as i64;
     let texture_entry = Gd::from_init_fn(|_base| TextureEntry {
         image,
         texture,
         original_size,
+        size_bytes,
+        downscale_level: 0,
     });
 
     Ok(Some(texture_entry.to_variant()))
 }
 
 pub fn create_compressed_texture(image: &mut Gd<Image>, max_size: i32) -> Gd<Texture2D> {
+    // already in a GPU format (e.g. transcoded from KTX2), it can be used as is
+    if image.is_compressed() {
+        if let Some(texture) = ImageTexture::create_from_image(image.clone()) {
+            return texture.upcast();
+        }
+    }
+
     resize_image(image, max_size);
 
     if !image.is_compressed() {
@@ -113,6 +184,11 @@ pub fn create_compressed_texture(image: &mut Gd<Image>, max_size: i32) -> Gd<Tex
 }
 
 pub fn resize_image(image: &mut Gd<Image>, max_size: i32) -> bool {
+    // compressed images can't be resized, their size is picked when they're transcoded
+    if image.is_compressed() {
+        return false;
+    }
+
     let image_width = image.get_width();
     let image_height = image.get_height();
     if image_width > image_height {
@@ -97,6 +97,7 @@ pub enum DclSourceTex {
     Texture(String),
     AvatarTexture(String),
     VideoTexture(SceneEntityId),
+    UiTexture(SceneEntityId),
 }
 
 impl Default for DclSourceTex {
@@ -115,36 +116,18 @@ pub struct DclTexture {
 impl From<&TextureUnion> for Option<DclTexture> {
     fn from(value: &TextureUnion) -> Option<DclTexture> {
         let texture = value.tex.as_ref()?;
-        let wrap_mode = match texture {
-            Tex::Texture(texture) => *texture
-                .wrap_mode
-                .as_ref()
-                .unwrap_or(&TextureWrapMode::TwmClamp.into()),
-            Tex::AvatarTexture(avatar_texture) => *avatar_texture
-                .wrap_mode
-                .as_ref()
-                .unwrap_or(&TextureWrapMode::TwmClamp.into()),
-            Tex::VideoTexture(video_texture) => *video_texture
-                .wrap_mode
-                .as_ref()
-                .unwrap_or(&TextureWrapMode::TwmClamp.into()),
-            Tex::UiTexture(_) => todo!("UI Texture not implemented"),
-        };
-        let filter_mode = match texture {
-            Tex::Texture(texture) => *texture
-                .wrap_mode
-                .as_ref()
-                .unwrap_or(&TextureFilterMode::TfmBilinear.into()),
-            Tex::AvatarTexture(avatar_texture) => *avatar_texture
-                .wrap_mode
-                .as_ref()
-                .unwrap_or(&TextureFilterMode::TfmBilinear.into()),
-            Tex::VideoTexture(video_texture) => *video_texture
-                .wrap_mode
-                .as_ref()
-                .unwrap_or(&TextureFilterMode::TfmBilinear.into()),
-            Tex::UiTexture(_) => todo!("UI Texture not implemented"),
+        let (wrap_mode, filter_mode) = match texture {
+            Tex::Texture(texture) => (texture.wrap_mode, texture.filter_mode),
+            Tex::AvatarTexture(avatar_texture) => {
+                (avatar_texture.wrap_mode, avatar_texture.filter_mode)
+            }
+            Tex::VideoTexture(video_texture) => {
+                (video_texture.wrap_mode, video_texture.filter_mode)
+            }
+            Tex::UiTexture(ui_texture) => (ui_texture.wrap_mode, ui_texture.filter_mode),
         };
+        let wrap_mode = wrap_mode.unwrap_or(TextureWrapMode::TwmClamp.into());
+        let filter_mode = filter_mode.unwrap_or(TextureFilterMode::TfmBilinear.into());
 
         let wrap_mode = TextureWrapMode::from_i32(wrap_mode).unwrap_or(TextureWrapMode::TwmClamp);
         let filter_mode =
@@ -171,7 +154,13 @@ impl From<&TextureUnion> for Option<DclTexture> {
                     video_texture.video_player_entity as i32,
                 )),
             }),
-            Tex::UiTexture(_) => todo!("UI Texture not implemented"),
+            Tex::UiTexture(ui_texture) => Some(DclTexture {
+                wrap_mode,
+                filter_mode,
+                source: DclSourceTex::UiTexture(SceneEntityId::from_i32(
+                    ui_texture.ui_canvas_entity as i32,
+                )),
+            }),
         }
     }
 }
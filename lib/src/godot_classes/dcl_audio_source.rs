@@ -1,8 +1,15 @@
-use godot::engine::AudioStreamPlayer3D;
+use godot::engine::audio_stream_player_3d::AttenuationModel;
+use godot::engine::{AudioStreamPlayer3D, IAudioStreamPlayer3D};
+use godot::obj::EngineEnum;
 use godot::prelude::*;
 
+// Positional audio is heard at full volume until the min distance and fades out until
+//  the max distance, the audio_source.tscn can override them
+const DEFAULT_MIN_DISTANCE: f32 = 1.0;
+const DEFAULT_MAX_DISTANCE: f32 = 64.0;
+
 #[derive(GodotClass)]
-#[class(init, base=AudioStreamPlayer3D)]
+#[class(base=AudioStreamPlayer3D)]
 pub struct DclAudioSource {
     #[var]
     dcl_enable: bool,
@@ -30,15 +37,100 @@ pub struct DclAudioSource {
     #[var]
     dcl_current_time: f32,
 
+    /// whether the audio is heard everywhere in the scene instead of from the entity.
     #[var]
     dcl_global: bool,
 
+    /// distance where the positional audio starts to fade out (default: 1.0).
+    #[export]
+    dcl_min_distance: f32,
+
+    /// distance where the positional audio can't be heard anymore (default: 64.0).
+    #[export]
+    dcl_max_distance: f32,
+
+    /// the AttenuationModel of the positional audio (default: inverse distance).
+    #[export]
+    dcl_attenuation_model: i32,
+
     #[var]
     dcl_scene_id: i32,
 
     #[base]
-    _base: Base<AudioStreamPlayer3D>,
+    base: Base<AudioStreamPlayer3D>,
 }
 
 #[godot_api]
-impl DclAudioSource {}
+impl IAudioStreamPlayer3D for DclAudioSource {
+    fn init(base: Base<AudioStreamPlayer3D>) -> Self {
+        Self {
+            dcl_enable: false,
+            dcl_playing: false,
+            dcl_volume: 1.0,
+            dcl_loop_activated: false,
+            dcl_pitch: 1.0,
+            dcl_audio_clip_url: GString::new(),
+            dcl_current_time: 0.0,
+            dcl_global: false,
+            dcl_min_distance: DEFAULT_MIN_DISTANCE,
+            dcl_max_distance: DEFAULT_MAX_DISTANCE,
+            dcl_attenuation_model: AttenuationModel::ATTENUATION_INVERSE_DISTANCE.ord(),
+            dcl_scene_id: 0,
+            base,
+        }
+    }
+}
+
+#[godot_api]
+impl DclAudioSource {
+    // Applies the volume, pitch and spatial properties, the playback is handled by the script
+    #[func]
+    pub fn apply_spatial_props(&mut self) {
+        let pitch = self.dcl_pitch.max(0.01);
+        self.base.set_pitch_scale(pitch);
+
+        if self.dcl_global {
+            self.base
+                .set_attenuation_model(AttenuationModel::ATTENUATION_DISABLED);
+            self.base.set_panning_strength(0.0);
+            self.base.set_max_distance(0.0);
+        } else {
+            let attenuation_model = AttenuationModel::try_from_ord(self.dcl_attenuation_model)
+                .unwrap_or(AttenuationModel::ATTENUATION_INVERSE_DISTANCE);
+            self.base.set_attenuation_model(attenuation_model);
+            self.base.set_panning_strength(1.0);
+            self.base.set_unit_size(self.dcl_min_distance.max(0.1));
+            self.base
+                .set_max_distance(self.dcl_max_distance.max(self.dcl_min_distance));
+        }
+
+        // muted when the player is not in the scene
+        let volume_db = if self.dcl_enable {
+            volume_to_db(self.dcl_volume)
+        } else {
+            -80.0
+        };
+        self.base.set_volume_db(volume_db);
+    }
+}
+
+fn volume_to_db(volume: f32) -> f32 {
+    // -80 = 20 log 0.0001, so muted is when (volume <= 0.0001)
+    if volume <= 0.0001 {
+        -80.0
+    } else {
+        20.0 * f32::log10(volume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::volume_to_db;
+
+    #[test]
+    fn test_volume_to_db() {
+        assert_eq!(volume_to_db(1.0), 0.0);
+        assert_eq!(volume_to_db(0.0), -80.0);
+        assert!((volume_to_db(0.5) + 6.0206).abs() < 0.001);
+    }
+}
@@ -1,6 +1,7 @@
 use godot::prelude::*;
 
 use crate::avatars::avatar_type::DclAvatarWireFormat;
+use crate::avatars::position_buffer::{InterpolationSettings, PositionBuffer, PositionSample};
 use crate::dcl::SceneId;
 
 use super::dcl_global::DclGlobal;
@@ -10,6 +11,8 @@ use super::dcl_global::DclGlobal;
 pub enum AvatarMovementType {
     ExternalController = 0,
     LerpTwoPoints = 1,
+    // remote avatars, driven by the positions received from comms
+    Interpolated = 2,
 }
 
 #[derive(Default)]
@@ -52,6 +55,11 @@ pub struct DclAvatar {
     land: bool,
 
     lerp_state: LerpState,
+
+    position_buffer: PositionBuffer,
+    // seconds accumulated by `process`, the time base of the position buffer
+    clock: f64,
+
     #[base]
     base: Base<Node3D>,
 }
@@ -64,6 +72,8 @@ impl INode3D for DclAvatar {
             current_parcel_scene_id: SceneId::INVALID.0,
             current_parcel_position: Vector2i::new(i32::MAX, i32::MAX),
             lerp_state: Default::default(),
+            position_buffer: Default::default(),
+            clock: 0.0,
             base,
             walk: false,
             run: false,
@@ -94,14 +104,7 @@ impl DclAvatar {
         let y_velocity = 10.0 * diff_xz_plane.y; // divide by 0.1s
         diff_xz_plane.y = 0.0;
         let target_forward_distance = diff_xz_plane.length();
-
-        // TODO: define const with these values
-        self.walk = target_forward_distance < 0.4 && target_forward_distance > 0.01;
-        self.run = target_forward_distance >= 0.65;
-        self.jog = !(self.walk || self.run) && target_forward_distance > 0.01;
-        self.rise = y_velocity > 1.0;
-        self.fall = y_velocity < -1.0;
-        self.land = !self.rise && !self.fall;
+        self.update_movement_flags(target_forward_distance, y_velocity);
 
         self.lerp_state.initial_position = self.lerp_state.target_position;
         self.lerp_state.target_position = new_target.origin;
@@ -117,6 +120,44 @@ impl DclAvatar {
         self.update_parcel_position(self.lerp_state.target_position);
     }
 
+    // `index` is the rfc4 position index, older or repeated ones are discarded,
+    //  without it the sample goes after the last one
+    pub fn push_position_sample(&mut self, index: Option<u32>, transform: Transform3D) {
+        let index = index.unwrap_or_else(|| {
+            self.position_buffer
+                .last_index()
+                .map_or(0, |last_index| last_index.wrapping_add(1))
+        });
+        let pushed = self.position_buffer.push(PositionSample {
+            index,
+            time: self.clock,
+            position: transform.origin,
+            rotation: transform.basis.to_quat(),
+        });
+
+        if pushed && self.position_buffer.len() == 1 {
+            // first position or snapped, there is nothing to interpolate from
+            self.base.set_global_position(transform.origin);
+            self.base
+                .set_global_rotation(transform.basis.to_euler(EulerOrder::YXZ));
+        }
+    }
+
+    pub fn set_interpolation_settings(&mut self, settings: InterpolationSettings) {
+        self.position_buffer.set_settings(settings);
+    }
+
+    // `forward_distance` and `y_velocity` are measured along 0.1s
+    fn update_movement_flags(&mut self, forward_distance: f32, y_velocity: f32) {
+        // TODO: define const with these values
+        self.walk = forward_distance < 0.4 && forward_distance > 0.01;
+        self.run = forward_distance >= 0.65;
+        self.jog = !(self.walk || self.run) && forward_distance > 0.01;
+        self.rise = y_velocity > 1.0;
+        self.fall = y_velocity < -1.0;
+        self.land = !self.rise && !self.fall;
+    }
+
     // This function is called when a parcel scene is created,
     //  it handles the corner case where the avatar is already in the parcel
     //  that is being created
@@ -228,6 +269,22 @@ impl DclAvatar {
                     );
                 }
             }
+            AvatarMovementType::Interpolated => {
+                self.clock += dt;
+                let Some(sampled) = self.position_buffer.sample(self.clock) else {
+                    return;
+                };
+
+                let mut velocity_xz_plane = sampled.velocity;
+                velocity_xz_plane.y = 0.0;
+                self.update_movement_flags(0.1 * velocity_xz_plane.length(), sampled.velocity.y);
+
+                self.base.set_global_position(sampled.position);
+                self.base.set_global_rotation(
+                    Basis::from_quat(sampled.rotation).to_euler(EulerOrder::YXZ),
+                );
+                self.update_parcel_position(sampled.position);
+            }
         }
     }
 }
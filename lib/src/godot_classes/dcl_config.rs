@@ -1,5 +1,10 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use godot::{engine::ConfigFile, prelude::*};
 
+// Read from the content and scene components, so it's kept outside of the DclConfig instance
+static DATA_SAVER_ENABLED: AtomicBool = AtomicBool::new(false);
+
 #[repr(i32)]
 #[derive(Clone, Property, Export, PartialEq, Debug)]
 pub enum TextureQuality {
@@ -29,6 +34,15 @@ impl TextureQuality {
         }
     }
 
+    // Data saver caps the quality to the lowest one
+    pub fn capped_by_data_saver(&self, data_saver: bool) -> Self {
+        if data_saver {
+            Self::Low
+        } else {
+            self.clone()
+        }
+    }
+
     pub fn to_max_size(&self) -> i32 {
         match self {
             Self::Low => 256,
@@ -64,6 +78,12 @@ impl IRefCounted for DclConfig {
             .try_to::<i32>()
             .unwrap_or(TextureQuality::Medium.to_i32());
 
+        let data_saver = settings_file
+            .get_value("config".to_godot(), "data_saver".to_godot())
+            .try_to::<bool>()
+            .unwrap_or(false);
+        DATA_SAVER_ENABLED.store(data_saver, Ordering::Relaxed);
+
         Self {
             _base: base,
             settings_file,
@@ -93,6 +113,34 @@ impl DclConfig {
         TextureQuality::from_i32(texture_quality)
     }
 
+    pub fn static_get_data_saver() -> bool {
+        let mut settings_file: Gd<ConfigFile> = ConfigFile::new();
+        settings_file.load(DclConfig::get_settings_file_path());
+        let data_saver = settings_file
+            .get_value_ex("config".to_godot(), "data_saver".to_godot())
+            .default(Variant::from(false))
+            .done()
+            .try_to::<bool>()
+            .unwrap_or(false);
+        DATA_SAVER_ENABLED.store(data_saver, Ordering::Relaxed);
+        data_saver
+    }
+
+    pub fn is_data_saver_enabled() -> bool {
+        DATA_SAVER_ENABLED.load(Ordering::Relaxed)
+    }
+
+    #[func]
+    pub fn get_data_saver(&self) -> bool {
+        DclConfig::is_data_saver_enabled()
+    }
+
+    // When enabled, video streams are not played and textures are capped to the low quality
+    #[func]
+    pub fn set_data_saver(&mut self, enabled: bool) {
+        DATA_SAVER_ENABLED.store(enabled, Ordering::Relaxed);
+    }
+
     #[func]
     pub fn generate_uuid_v4() -> GString {
         uuid::Uuid::new_v4().to_string().to_godot()
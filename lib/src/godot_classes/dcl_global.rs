@@ -15,7 +15,10 @@ use crate::{
     http_request::rust_http_queue_requester::RustHttpQueueRequester,
     scene_runner::{scene_manager::SceneManager, tokio_runtime::TokioRuntime},
     test_runner::testing_tools::DclTestingTools,
-    tools::network_inspector::{NetworkInspector, NetworkInspectorSender},
+    tools::{
+        comms_inspector::CommsInspector,
+        network_inspector::{NetworkInspector, NetworkInspectorSender},
+    },
 };
 
 use super::{
@@ -92,6 +95,9 @@ pub struct DclGlobal {
 
     #[var]
     pub network_inspector: Gd<NetworkInspector>,
+
+    #[var]
+    pub comms_inspector: Gd<CommsInspector>,
 }
 
 #[godot_api]
@@ -152,6 +158,7 @@ impl INode for DclGlobal {
             metrics: Metrics::alloc_gd(),
             renderer_version: env!("GODOT_EXPLORER_VERSION").into(),
             network_inspector: NetworkInspector::alloc_gd(),
+            comms_inspector: CommsInspector::new_gd(),
 
             #[cfg(feature = "enable_inspector")]
             has_javascript_debugger: true,
@@ -304,6 +304,10 @@ impl DclUiBackground {
                     DclSourceTex::VideoTexture(_) => {
                         // TODO: implement video texture
                     }
+                    DclSourceTex::UiTexture(_) => {
+                        // the ui can't be rendered inside itself
+                        self._set_white_pixel();
+                    }
                     DclSourceTex::AvatarTexture(user_id) => {
                         let global = DclGlobal::singleton();
                         let mut content_provider = global.bind().get_content_provider();
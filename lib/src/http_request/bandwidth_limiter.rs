@@ -0,0 +1,128 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+use std::time::{Duration, Instant};
+
+// Shared by the ResourceProvider and every HttpQueueRequester, so the cap applies
+// to the whole download traffic of the explorer and not per requester
+pub static GLOBAL_BANDWIDTH_LIMITER: once_cell::sync::Lazy<BandwidthLimiter> =
+    once_cell::sync::Lazy::new(BandwidthLimiter::new);
+
+struct BucketState {
+    // it can be negative, in that case it's the debt that must be waited before
+    //  the next chunk of data is allowed
+    available_bytes: f64,
+    last_refill: Instant,
+}
+
+pub struct BandwidthLimiter {
+    // 0 means unlimited
+    max_bytes_per_second: AtomicU64,
+    session_downloaded_bytes: AtomicU64,
+    bucket: Mutex<BucketState>,
+}
+
+impl Default for BandwidthLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BandwidthLimiter {
+    pub fn new() -> Self {
+        Self {
+            max_bytes_per_second: AtomicU64::new(0),
+            session_downloaded_bytes: AtomicU64::new(0),
+            bucket: Mutex::new(BucketState {
+                available_bytes: 0.0,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn set_max_bytes_per_second(&self, max_bytes_per_second: u64) {
+        self.max_bytes_per_second
+            .store(max_bytes_per_second, Ordering::Relaxed);
+
+        // reset the bucket, the debt with the old limit is no longer meaningful
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.available_bytes = max_bytes_per_second as f64;
+        bucket.last_refill = Instant::now();
+    }
+
+    pub fn get_max_bytes_per_second(&self) -> u64 {
+        self.max_bytes_per_second.load(Ordering::Relaxed)
+    }
+
+    pub fn get_session_downloaded_bytes(&self) -> u64 {
+        self.session_downloaded_bytes.load(Ordering::Relaxed)
+    }
+
+    // Account `bytes` as downloaded and wait until the rate limit allows to continue
+    pub async fn consume(&self, bytes: u64) {
+        let wait_time = self.reserve(bytes, Instant::now());
+        if !wait_time.is_zero() {
+            tokio::time::sleep(wait_time).await;
+        }
+    }
+
+    fn reserve(&self, bytes: u64, now: Instant) -> Duration {
+        self.session_downloaded_bytes
+            .fetch_add(bytes, Ordering::Relaxed);
+
+        let max_bytes_per_second = self.max_bytes_per_second.load(Ordering::Relaxed);
+        if max_bytes_per_second == 0 {
+            return Duration::ZERO;
+        }
+
+        let rate = max_bytes_per_second as f64;
+        let mut bucket = self.bucket.lock().unwrap();
+        let elapsed = now
+            .saturating_duration_since(bucket.last_refill)
+            .as_secs_f64();
+
+        // the bucket can hold at most one second of traffic
+        bucket.available_bytes = (bucket.available_bytes + elapsed * rate).min(rate);
+        bucket.last_refill = now;
+        bucket.available_bytes -= bytes as f64;
+
+        if bucket.available_bytes >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.available_bytes / rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_never_waits() {
+        let limiter = BandwidthLimiter::new();
+        let now = Instant::now();
+        assert!(limiter.reserve(100 * 1024 * 1024, now).is_zero());
+        assert_eq!(limiter.get_session_downloaded_bytes(), 100 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_limited_waits_for_debt() {
+        let limiter = BandwidthLimiter::new();
+        limiter.set_max_bytes_per_second(1000);
+        let now = limiter.bucket.lock().unwrap().last_refill;
+
+        // the first second of traffic is already available
+        assert!(limiter.reserve(1000, now).is_zero());
+
+        // 500 bytes over the limit is half a second of waiting
+        let wait_time = limiter.reserve(500, now);
+        assert_eq!(wait_time, Duration::from_millis(500));
+
+        // after a second, the debt is paid and there is room for 500 bytes more
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.reserve(500, later).is_zero());
+        assert_eq!(limiter.get_session_downloaded_bytes(), 2000);
+    }
+}
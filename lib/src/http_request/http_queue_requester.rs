@@ -1,3 +1,4 @@
+use futures_util::StreamExt;
 use reqwest::Client;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
@@ -10,6 +11,7 @@ use crate::tools::network_inspector::{
     NetworkInspectorId, NetworkInspectorSender, NETWORK_INSPECTOR_ENABLE,
 };
 
+use super::bandwidth_limiter::GLOBAL_BANDWIDTH_LIMITER;
 use super::request_response::{
     RequestOption, RequestResponse, RequestResponseError, ResponseEnum, ResponseType,
 };
@@ -225,26 +227,49 @@ impl HttpQueueRequester {
 
         let response_data = match request_option.response_type.clone() {
             ResponseType::AsString => {
-                ResponseEnum::String(response.text().await.map_err(map_err_func)?)
+                let content_type = response
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from);
+                let content = read_throttled(response).await.map_err(map_err_func)?;
+                let text = decode_text(&content, content_type.as_deref()).map_err(|e| {
+                    RequestResponseError {
+                        id: request_option.id,
+                        error_message: e,
+                    }
+                })?;
+                ResponseEnum::String(text)
             }
             ResponseType::AsBytes => {
-                ResponseEnum::Bytes(response.bytes().await.map_err(map_err_func)?.to_vec())
+                let content = read_throttled(response).await.map_err(map_err_func)?;
+                ResponseEnum::Bytes(content)
             }
             ResponseType::ToFile(file_path) => {
-                let content = response.bytes().await.map_err(map_err_func)?.to_vec();
+                let map_io_err_func = |e: std::io::Error| RequestResponseError {
+                    id: request_option.id,
+                    error_message: e.to_string(),
+                };
                 let mut file = tokio::fs::File::create(file_path.clone())
                     .await
-                    .map_err(|e| RequestResponseError {
-                        id: request_option.id,
-                        error_message: e.to_string(),
-                    })?;
-                let result = file.write_all(&content).await;
+                    .map_err(map_io_err_func)?;
+
+                let mut stream = response.bytes_stream();
+                let mut result = Ok(());
+                while let Some(chunk) = stream.next().await {
+                    let chunk = chunk.map_err(map_err_func)?;
+                    GLOBAL_BANDWIDTH_LIMITER.consume(chunk.len() as u64).await;
+                    result = file.write_all(&chunk).await;
+                    if result.is_err() {
+                        break;
+                    }
+                }
                 let result = result.map(|_| file_path);
                 ResponseEnum::ToFile(result)
             }
             ResponseType::AsJson => {
-                let json_string = &response.text().await.map_err(map_err_func)?;
-                ResponseEnum::Json(serde_json::from_str(json_string))
+                let content = read_throttled(response).await.map_err(map_err_func)?;
+                ResponseEnum::Json(serde_json::from_slice(&content))
             }
         };
 
@@ -256,3 +281,64 @@ impl HttpQueueRequester {
         })
     }
 }
+
+// The bandwidth limiter is applied to each chunk, so a big download is throttled
+//  while it's received and not after
+async fn read_throttled(response: reqwest::Response) -> Result<Vec<u8>, reqwest::Error> {
+    let mut content = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        GLOBAL_BANDWIDTH_LIMITER.consume(chunk.len() as u64).await;
+        content.extend_from_slice(&chunk);
+    }
+    Ok(content)
+}
+
+// Decodes the body with the charset declared in the Content-Type header, UTF-8 when
+//  there isn't any. The invalid sequences are replaced like `Response::text` does.
+fn decode_text(content: &[u8], content_type: Option<&str>) -> Result<String, String> {
+    let charset = content_type.and_then(|content_type| {
+        content_type.split(';').skip(1).find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches('"').to_string())
+        })
+    });
+
+    let encoding = match charset {
+        Some(charset) => encoding_rs::Encoding::for_label(charset.as_bytes())
+            .ok_or_else(|| format!("unsupported charset in the response: {charset}"))?,
+        None => encoding_rs::UTF_8,
+    };
+    let (text, _, _) = encoding.decode(content);
+    Ok(text.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(decode_text("hola ñ".as_bytes(), None).unwrap(), "hola ñ");
+        assert_eq!(
+            decode_text("hola ñ".as_bytes(), Some("text/plain")).unwrap(),
+            "hola ñ"
+        );
+
+        // ISO-8859-1 ñ is a single 0xF1 byte
+        let latin1 = b"hola \xF1";
+        assert_eq!(
+            decode_text(latin1, Some("text/plain; charset=ISO-8859-1")).unwrap(),
+            "hola ñ"
+        );
+        assert_eq!(
+            decode_text(latin1, Some("text/html;Charset=\"latin1\"")).unwrap(),
+            "hola ñ"
+        );
+
+        assert!(decode_text(latin1, Some("text/plain; charset=not-a-charset")).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod bandwidth_limiter;
 pub mod http_queue_requester;
 pub mod request_response;
 pub mod rust_http_queue_requester;
@@ -71,7 +71,8 @@ pub fn update_audio_source(
                 audio_source.set_dcl_playing(new_value.playing.unwrap_or(false));
                 audio_source.set_dcl_pitch(new_value.pitch.unwrap_or(1.0));
                 audio_source.set_dcl_volume(new_value.volume.unwrap_or(1.0).clamp(0.0, 1.0));
-                audio_source.set_dcl_current_time(new_value.current_time.unwrap_or(0.0));
+                audio_source.set_dcl_current_time(new_value.current_time.unwrap_or(0.0).max(0.0));
+                audio_source.set_dcl_global(new_value.global.unwrap_or(false));
                 audio_source.set_dcl_scene_id(scene.scene_id.0);
 
                 let dcl_enable = if let SceneType::Parcel = scene.scene_type {
@@ -84,3 +85,95 @@ pub fn update_audio_source(
         }
     }
 }
+
+// The itests run inside Godot with the debug build of the library
+#[cfg(debug_assertions)]
+mod itests {
+    use godot::{engine::audio_stream_player_3d::AttenuationModel, prelude::*};
+
+    use crate::{
+        dcl::{
+            components::{
+                proto_components::sdk::components::PbAudioSource, SceneComponentId, SceneEntityId,
+            },
+            crdt::{
+                last_write_wins::LastWriteWinsComponentOperation, SceneCrdtStateProtoComponents,
+            },
+        },
+        framework::TestContext,
+        godot_classes::dcl_audio_source::DclAudioSource,
+        scene_runner::scene::Scene,
+    };
+
+    use super::update_audio_source;
+
+    fn spawn_audio_source(
+        scene_context: &TestContext,
+        audio_source: PbAudioSource,
+    ) -> (Scene, Gd<DclAudioSource>) {
+        let mut scene = Scene::unsafe_default();
+        let crdt = scene.dcl_scene.scene_crdt.clone();
+        let mut crdt_state = crdt.try_lock().unwrap();
+        scene_context
+            .scene_tree
+            .clone()
+            .add_child(scene.godot_dcl_scene.root_node_3d.clone().upcast());
+
+        let entity = SceneEntityId::new(1333, 0);
+        SceneCrdtStateProtoComponents::get_audio_source_mut(&mut crdt_state)
+            .put(entity, Some(audio_source));
+        scene
+            .current_dirty
+            .lww_components
+            .insert(SceneComponentId::AUDIO_SOURCE, vec![entity]);
+        let current_parcel_scene_id = scene.scene_id;
+        update_audio_source(&mut scene, &mut crdt_state, &current_parcel_scene_id);
+        drop(crdt_state);
+
+        let mut audio_source = scene.audio_sources.get(&entity).unwrap().clone();
+        audio_source.bind_mut().apply_spatial_props();
+        (scene, audio_source)
+    }
+
+    #[godot::test::itest]
+    fn test_audio_source_spatial_props(scene_context: &TestContext) {
+        let (_scene, audio_source) = spawn_audio_source(
+            scene_context,
+            PbAudioSource {
+                audio_clip_url: "sound.mp3".into(),
+                pitch: Some(1.5),
+                ..Default::default()
+            },
+        );
+
+        // the default distances and rolloff of the DclAudioSource
+        assert_eq!(audio_source.bind().get_dcl_min_distance(), 1.0);
+        assert_eq!(audio_source.bind().get_dcl_max_distance(), 64.0);
+        assert_eq!(audio_source.get_unit_size(), 1.0);
+        assert_eq!(audio_source.get_max_distance(), 64.0);
+        assert_eq!(
+            audio_source.get_attenuation_model(),
+            AttenuationModel::ATTENUATION_INVERSE_DISTANCE
+        );
+        assert_eq!(audio_source.get_pitch_scale(), 1.5);
+    }
+
+    #[godot::test::itest]
+    fn test_audio_source_global(scene_context: &TestContext) {
+        let (_scene, audio_source) = spawn_audio_source(
+            scene_context,
+            PbAudioSource {
+                audio_clip_url: "sound.mp3".into(),
+                global: Some(true),
+                ..Default::default()
+            },
+        );
+
+        assert!(audio_source.bind().get_dcl_global());
+        assert_eq!(
+            audio_source.get_attenuation_model(),
+            AttenuationModel::ATTENUATION_DISABLED
+        );
+        assert_eq!(audio_source.get_panning_strength(), 0.0);
+    }
+}
@@ -1,17 +1,130 @@
 use crate::{
     dcl::{
-        components::SceneComponentId,
+        components::{SceneComponentId, SceneEntityId},
         crdt::{
             last_write_wins::LastWriteWinsComponentOperation, SceneCrdtState,
             SceneCrdtStateProtoComponents,
         },
     },
-    scene_runner::scene::Scene,
+    godot_classes::dcl_global::DclGlobal,
+    scene_runner::{components::transform_and_parent::set_node_3d_transform, scene::Scene},
 };
-use godot::prelude::*;
+use godot::{engine::Skeleton3D, prelude::*};
+
+// AvatarAnchorPointType values resolved without a bone
+const AAPT_POSITION: i32 = 0;
+const AAPT_NAME_TAG: i32 = 1;
+
+const SKELETON_PATH: &str = "Armature/Skeleton3D";
+const NAME_TAG_PATH: &str = "Armature/Skeleton3D/BoneAttachment3D_Name/Label3D_Name";
+const COLLIDER_FILTER_NAME: &str = "AvatarAttachColliderFilter";
+
+// Bone of the avatar skeleton for each AvatarAnchorPointType
+fn get_anchor_point_bone(anchor_point_id: i32) -> Option<&'static str> {
+    let bone = match anchor_point_id {
+        2 => "Avatar_LeftHand",
+        3 => "Avatar_RightHand",
+        4 => "Avatar_Head",
+        5 => "Avatar_Neck",
+        6 => "Avatar_Spine",
+        7 => "Avatar_Spine1",
+        8 => "Avatar_Spine2",
+        9 => "Avatar_Hips",
+        10 => "Avatar_LeftShoulder",
+        11 => "Avatar_LeftArm",
+        12 => "Avatar_LeftForeArm",
+        13 => "Avatar_LeftHandIndex1",
+        14 => "Avatar_RightShoulder",
+        15 => "Avatar_RightArm",
+        16 => "Avatar_RightForeArm",
+        17 => "Avatar_RightHandIndex1",
+        18 => "Avatar_LeftUpLeg",
+        19 => "Avatar_LeftLeg",
+        20 => "Avatar_LeftFoot",
+        21 => "Avatar_LeftToeBase",
+        22 => "Avatar_RightUpLeg",
+        23 => "Avatar_RightLeg",
+        24 => "Avatar_RightFoot",
+        25 => "Avatar_RightToeBase",
+        _ => return None,
+    };
+    Some(bone)
+}
+
+// An empty avatar_id is the primary player
+fn get_avatar_node(player_avatar: Option<&Gd<Node3D>>, avatar_id: &str) -> Option<Gd<Node3D>> {
+    let global = DclGlobal::try_singleton()?;
+    let primary_player_address = global
+        .bind()
+        .player_identity
+        .bind()
+        .get_address_str()
+        .to_string();
+
+    if avatar_id.is_empty() || avatar_id.eq_ignore_ascii_case(&primary_player_address) {
+        player_avatar.cloned()
+    } else {
+        global
+            .bind()
+            .get_avatars()
+            .bind()
+            .get_avatar_by_address(GString::from(avatar_id))
+            .map(|avatar| avatar.upcast())
+    }
+}
+
+// The entity only takes the position and rotation of the anchor point, the avatar
+//  skeleton is scaled
+fn get_anchor_point_transform(avatar: &Gd<Node3D>, anchor_point_id: i32) -> Option<Transform3D> {
+    let transform = match anchor_point_id {
+        AAPT_POSITION => avatar.get_global_transform(),
+        AAPT_NAME_TAG => avatar
+            .try_get_node_as::<Node3D>(NodePath::from(NAME_TAG_PATH))?
+            .get_global_transform(),
+        _ => {
+            let bone_name = get_anchor_point_bone(anchor_point_id)?;
+            let skeleton = avatar.try_get_node_as::<Skeleton3D>(NodePath::from(SKELETON_PATH))?;
+            let bone_idx = skeleton.find_bone(GString::from(bone_name));
+            if bone_idx < 0 {
+                return None;
+            }
+            skeleton.get_global_transform() * skeleton.get_bone_global_pose(bone_idx)
+        }
+    };
+
+    Some(Transform3D::new(
+        transform.basis.orthonormalized(),
+        transform.origin,
+    ))
+}
+
+// The colliders of the attached entity don't collide with the player
+fn add_collider_filter(node_3d: &mut Gd<Node3D>) {
+    if node_3d.has_node(NodePath::from(COLLIDER_FILTER_NAME)) {
+        return;
+    }
+
+    let mut player_collider_filter = godot::engine::load::<GdScript>(
+        "res://src/decentraland_components/player_collider_filter.gd",
+    )
+    .instantiate(&[])
+    .to::<Gd<Node>>();
+    player_collider_filter.set_name(COLLIDER_FILTER_NAME.into());
+
+    node_3d.add_child(player_collider_filter.clone());
+    player_collider_filter.call("init_player_collider_filter".into(), &[]);
+}
+
+fn remove_collider_filter(node_3d: &mut Gd<Node3D>) {
+    if let Some(mut player_collider_filter) =
+        node_3d.try_get_node_as::<Node>(NodePath::from(COLLIDER_FILTER_NAME))
+    {
+        node_3d.remove_child(player_collider_filter.clone());
+        player_collider_filter.queue_free();
+    }
+}
 
 pub fn update_avatar_attach(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
-    let godot_dcl_scene = &mut scene.godot_dcl_scene;
     let dirty_lww_components = &scene.current_dirty.lww_components;
     let avatar_attach_component = SceneCrdtStateProtoComponents::get_avatar_attach(crdt_state);
 
@@ -22,44 +135,136 @@ pub fn update_avatar_attach(scene: &mut Scene, crdt_state: &mut SceneCrdtState)
                 continue;
             }
 
-            let new_value = new_value.unwrap();
-            let (_godot_entity_node, mut node_3d) = godot_dcl_scene.ensure_node_3d(entity);
+            let new_value = new_value.unwrap().value.clone();
+            let (_godot_entity_node, mut node_3d) = scene.godot_dcl_scene.ensure_node_3d(entity);
 
-            let new_value = new_value.value.clone();
-            let existing = node_3d.try_get_node_as::<Node>(NodePath::from("AvatarAttach"));
+            if let Some(new_value) = new_value {
+                add_collider_filter(&mut node_3d);
+                scene.avatar_attaches.insert(*entity, new_value);
+            } else if scene.avatar_attaches.remove(entity).is_some() {
+                remove_collider_filter(&mut node_3d);
 
-            if new_value.is_none() {
-                if let Some(mut avatar_attach_node) = existing {
-                    avatar_attach_node.queue_free();
-                    node_3d.remove_child(avatar_attach_node);
-                    // TODO: resolve the current transform
-                }
-            } else if let Some(new_value) = new_value {
-                let mut avatar_attach_node = if let Some(avatar_attach_node) = existing {
-                    avatar_attach_node
-                } else {
-                    godot::engine::load::<PackedScene>(
-                        "res://src/decentraland_components/avatar_attach.tscn",
-                    )
-                    .instantiate()
-                    .unwrap()
-                };
-
-                avatar_attach_node.set(
-                    StringName::from("user_id"),
-                    Variant::from(new_value.avatar_id.unwrap_or_default()),
-                );
-
-                avatar_attach_node.set(
-                    StringName::from("attach_point"),
-                    Variant::from(new_value.anchor_point_id),
-                );
-
-                avatar_attach_node.set_name(GString::from("AvatarAttach"));
-
-                node_3d.add_child(avatar_attach_node.clone());
-                avatar_attach_node.call("init".into(), &[]);
+                // back to the entity own transform
+                let transform = crdt_state
+                    .get_transform()
+                    .get(entity)
+                    .and_then(|entry| entry.value.clone())
+                    .unwrap_or_default();
+                set_node_3d_transform(&mut node_3d, &transform);
             }
         }
     }
 }
+
+// Called every frame by the SceneManager, the avatars move and animate between
+//  the scene ticks
+pub fn update_avatar_attach_transforms(scene: &Scene, player_avatar: Option<&Gd<Node3D>>) {
+    for (entity, avatar_attach) in scene.avatar_attaches.iter() {
+        let Some(mut node_3d) = scene.godot_dcl_scene.get_node_3d(entity).cloned() else {
+            continue;
+        };
+
+        let avatar_id = avatar_attach.avatar_id.as_deref().unwrap_or_default();
+        // the avatar could be not loaded yet or out of range, the entity stays in place
+        let Some(avatar) = get_avatar_node(player_avatar, avatar_id) else {
+            continue;
+        };
+        let Some(anchor_transform) =
+            get_anchor_point_transform(&avatar, avatar_attach.anchor_point_id)
+        else {
+            continue;
+        };
+
+        let original_scale = node_3d.get_scale();
+        node_3d.set_global_transform(anchor_transform);
+        node_3d.set_scale(original_scale);
+    }
+}
+
+// The itests run inside Godot with the debug build of the library
+#[cfg(debug_assertions)]
+mod itests {
+    use godot::prelude::*;
+
+    use crate::{
+        dcl::{
+            components::{
+                proto_components::sdk::components::PbAvatarAttach,
+                transform_and_parent::DclTransformAndParent, SceneComponentId, SceneEntityId,
+            },
+            crdt::{
+                last_write_wins::LastWriteWinsComponentOperation, SceneCrdtStateProtoComponents,
+            },
+        },
+        framework::TestContext,
+        scene_runner::scene::Scene,
+    };
+
+    use super::update_avatar_attach;
+
+    #[godot::test::itest]
+    fn test_avatar_attach_restore_transform(scene_context: &TestContext) {
+        let mut scene = Scene::unsafe_default();
+        let crdt = scene.dcl_scene.scene_crdt.clone();
+        let mut crdt_state = crdt.try_lock().unwrap();
+        scene_context
+            .scene_tree
+            .clone()
+            .add_child(scene.godot_dcl_scene.root_node_3d.clone().upcast());
+
+        let entity = SceneEntityId::new(1333, 0);
+        let transform = DclTransformAndParent {
+            translation: Vector3::new(1.0, 2.0, 3.0),
+            ..Default::default()
+        };
+        crdt_state
+            .get_transform_mut()
+            .put(entity, Some(transform.clone()));
+
+        SceneCrdtStateProtoComponents::get_avatar_attach_mut(&mut crdt_state).put(
+            entity,
+            Some(PbAvatarAttach {
+                avatar_id: None,
+                anchor_point_id: 3,
+            }),
+        );
+        scene
+            .current_dirty
+            .lww_components
+            .insert(SceneComponentId::AVATAR_ATTACH, vec![entity]);
+        update_avatar_attach(&mut scene, &mut crdt_state);
+        assert!(scene.avatar_attaches.contains_key(&entity));
+
+        // the entity is moved somewhere else while attached
+        scene
+            .godot_dcl_scene
+            .get_node_3d(&entity)
+            .unwrap()
+            .clone()
+            .set_position(Vector3::new(10.0, 10.0, 10.0));
+
+        SceneCrdtStateProtoComponents::get_avatar_attach_mut(&mut crdt_state).put(entity, None);
+        update_avatar_attach(&mut scene, &mut crdt_state);
+        assert!(!scene.avatar_attaches.contains_key(&entity));
+
+        let node = scene.godot_dcl_scene.get_node_3d(&entity).unwrap();
+        assert!(node
+            .get_transform()
+            .is_equal_approx(transform.to_godot_transform_3d_without_scaled()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_anchor_point_bone;
+
+    #[test]
+    fn test_avatar_attach_anchor_points() {
+        for anchor_point_id in 2..=25 {
+            assert!(get_anchor_point_bone(anchor_point_id).is_some());
+        }
+        assert!(get_anchor_point_bone(0).is_none());
+        assert!(get_anchor_point_bone(1).is_none());
+        assert!(get_anchor_point_bone(26).is_none());
+    }
+}
@@ -4,20 +4,61 @@ use crate::{
 };
 use godot::prelude::*;
 
-pub enum Billboard {
-    None,
-    Y,
-    YX,
-    All,
+// The BillboardMode is a bitmask of the axes that rotate towards the camera
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Billboard {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+}
+
+impl Billboard {
+    const BM_X: i32 = 1;
+    const BM_Y: i32 = 2;
+    const BM_Z: i32 = 4;
+    const BM_ALL: i32 = 7;
+
+    pub fn is_none(&self) -> bool {
+        !self.x && !self.y && !self.z
+    }
+
+    // Euler angles (YXZ) of the entity looking at the camera, the disabled axes stay at zero
+    pub fn get_rotation(&self, direction: Vector3, camera_roll: f32) -> Option<Vector3> {
+        if direction.is_zero_approx() {
+            return None;
+        }
+
+        let yaw = if self.y {
+            (-direction.x).atan2(-direction.z)
+        } else {
+            0.0
+        };
+
+        // without yaw, the pitch is only in the YZ plane of the entity
+        let pitch = if self.x {
+            let horizontal_distance = if self.y {
+                Vector2::new(direction.x, direction.z).length()
+            } else {
+                -direction.z
+            };
+            direction.y.atan2(horizontal_distance)
+        } else {
+            0.0
+        };
+
+        let roll = if self.z { camera_roll } else { 0.0 };
+
+        Some(Vector3::new(pitch, yaw, roll))
+    }
 }
 
 impl From<Option<i32>> for Billboard {
     fn from(value: Option<i32>) -> Self {
-        match value {
-            Some(0) => Billboard::None,
-            Some(2) => Billboard::Y,
-            Some(3) => Billboard::YX,
-            _ => Billboard::All,
+        let value = value.unwrap_or(Self::BM_ALL);
+        Billboard {
+            x: value & Self::BM_X != 0,
+            y: value & Self::BM_Y != 0,
+            z: value & Self::BM_Z != 0,
         }
     }
 }
@@ -29,46 +70,34 @@ pub fn update_billboard(
 ) {
     let billboard_component = SceneCrdtStateProtoComponents::get_billboard(crdt_state);
     let camera_position = camera_global_transform.origin;
+    let camera_roll = camera_global_transform.basis.to_euler(EulerOrder::YXZ).z;
 
     for (entity, entry) in billboard_component.values.iter() {
         if let Some(billboard) = entry.value.as_ref() {
             let billboard_mode = Billboard::from(billboard.billboard_mode);
-            if let Billboard::None = billboard_mode {
+            if billboard_mode.is_none() {
                 continue;
             }
 
             let (_, mut node_3d) = scene.godot_dcl_scene.ensure_node_3d(entity);
-            let original_scale = node_3d.get_scale();
+            let origin = node_3d.get_global_position();
+            let direction = origin - camera_position;
 
-            match Billboard::from(billboard.billboard_mode) {
-                Billboard::None => {}
-                Billboard::Y => {
-                    let origin = node_3d.get_global_position();
-                    let direction = node_3d.get_global_position() - camera_position;
-                    let basis = Basis::new_looking_at(direction, Vector3::UP, false);
-
-                    let mut euler_vector = basis.to_euler(EulerOrder::YXZ);
-                    euler_vector.z = 0.0;
-                    euler_vector.x = 0.0;
-                    let basis = Basis::from_euler(EulerOrder::YXZ, euler_vector);
-
-                    node_3d.set_global_transform(Transform3D { basis, origin });
-                }
-                // TODO: we do not distinguish between YX and All for now
-                Billboard::All | Billboard::YX => {
-                    let origin = node_3d.get_global_position();
-                    let direction = node_3d.get_global_position() - camera_position;
-                    let basis = Basis::new_looking_at(direction, Vector3::UP, false);
-                    node_3d.set_global_transform(Transform3D { basis, origin });
-                }
-            }
+            // the camera is at the same position, there is no direction to look at
+            let Some(rotation) = billboard_mode.get_rotation(direction, camera_roll) else {
+                continue;
+            };
+
+            let original_scale = node_3d.get_scale();
+            let basis = Basis::from_euler(EulerOrder::YXZ, rotation);
+            node_3d.set_global_transform(Transform3D { basis, origin });
             node_3d.set_scale(original_scale);
         }
     }
 }
 
 mod test {
-    use godot::prelude::{Basis, Transform3D, Vector3};
+    use godot::prelude::{Basis, EulerOrder, Transform3D, Vector3};
 
     use crate::{
         dcl::{
@@ -122,13 +151,88 @@ mod test {
         update_billboard(&mut scene, &mut crdt_state, &camera_global_transform);
 
         let node = scene.godot_dcl_scene.get_node_3d(&entity).unwrap();
-        assert_eq!(
-            node.get_global_rotation(),
-            Vector3 {
-                x: 0.0,
-                y: std::f32::consts::FRAC_PI_4,
-                z: 0.0
-            }
+        assert!(node.get_global_rotation().is_equal_approx(Vector3 {
+            x: 0.0,
+            y: std::f32::consts::FRAC_PI_4,
+            z: 0.0
+        }));
+    }
+
+    const CAMERA_ROLL: f32 = 0.3;
+
+    // The camera is above the entity at 45 degrees in both yaw and pitch
+    fn get_billboard_rotation(scene_context: &TestContext, billboard_mode: Option<i32>) -> Vector3 {
+        let mut scene = Scene::unsafe_default();
+        let crdt = scene.dcl_scene.scene_crdt.clone();
+        let mut crdt_state = crdt.try_lock().unwrap();
+        scene_context
+            .scene_tree
+            .clone()
+            .add_child(scene.godot_dcl_scene.root_node_3d.clone().upcast());
+
+        let camera_global_transform = Transform3D::new(
+            Basis::from_euler(EulerOrder::YXZ, Vector3::new(0.0, 0.0, CAMERA_ROLL)),
+            Vector3::new(1.0, std::f32::consts::SQRT_2, 1.0),
         );
+
+        let entity = SceneEntityId::new(1333, 0);
+        scene.godot_dcl_scene.ensure_node_3d(&entity);
+        SceneCrdtStateProtoComponents::get_billboard_mut(&mut crdt_state)
+            .put(entity, Some(PbBillboard { billboard_mode }));
+
+        update_billboard(&mut scene, &mut crdt_state, &camera_global_transform);
+
+        let node = scene.godot_dcl_scene.get_node_3d(&entity).unwrap();
+        node.get_global_rotation()
+    }
+
+    #[godot::test::itest]
+    fn test_billboard_modes(scene_context: &TestContext) {
+        use std::f32::consts::FRAC_PI_4;
+        let x_only_pitch = -std::f32::consts::SQRT_2.atan();
+
+        let cases = [
+            (Some(0), Vector3::ZERO),
+            (Some(1), Vector3::new(x_only_pitch, 0.0, 0.0)),
+            (Some(2), Vector3::new(0.0, FRAC_PI_4, 0.0)),
+            (Some(3), Vector3::new(-FRAC_PI_4, FRAC_PI_4, 0.0)),
+            (Some(4), Vector3::new(0.0, 0.0, CAMERA_ROLL)),
+            (Some(6), Vector3::new(0.0, FRAC_PI_4, CAMERA_ROLL)),
+            (Some(7), Vector3::new(-FRAC_PI_4, FRAC_PI_4, CAMERA_ROLL)),
+            (None, Vector3::new(-FRAC_PI_4, FRAC_PI_4, CAMERA_ROLL)),
+        ];
+
+        for (billboard_mode, expected) in cases {
+            let rotation = get_billboard_rotation(scene_context, billboard_mode);
+            assert!(
+                rotation.is_equal_approx(expected),
+                "billboard mode {billboard_mode:?}: {rotation:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[godot::test::itest]
+    fn test_billboard_camera_at_entity(scene_context: &TestContext) {
+        let mut scene = Scene::unsafe_default();
+        let crdt = scene.dcl_scene.scene_crdt.clone();
+        let mut crdt_state = crdt.try_lock().unwrap();
+        scene_context
+            .scene_tree
+            .clone()
+            .add_child(scene.godot_dcl_scene.root_node_3d.clone().upcast());
+
+        let entity = SceneEntityId::new(1333, 0);
+        scene.godot_dcl_scene.ensure_node_3d(&entity);
+        SceneCrdtStateProtoComponents::get_billboard_mut(&mut crdt_state).put(
+            entity,
+            Some(PbBillboard {
+                billboard_mode: None,
+            }),
+        );
+
+        update_billboard(&mut scene, &mut crdt_state, &Transform3D::IDENTITY);
+
+        let node = scene.godot_dcl_scene.get_node_3d(&entity).unwrap();
+        assert_eq!(node.get_global_rotation(), Vector3::ZERO);
     }
 }
@@ -0,0 +1,205 @@
+use godot::{
+    engine::{AnimatableBody3D, CollisionShape3D, Mesh, MeshInstance3D, Shape3D},
+    prelude::*,
+};
+
+use crate::{
+    content::content_mapping::DclContentMappingAndUrl, dcl::components::SceneEntityId,
+    godot_classes::dcl_global::DclGlobal, scene_runner::scene::Scene,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GltfMeshTarget {
+    MeshRenderer,
+    MeshCollider,
+}
+
+impl GltfMeshTarget {
+    fn node_name(&self) -> &'static str {
+        match self {
+            GltfMeshTarget::MeshRenderer => "MeshRenderer",
+            GltfMeshTarget::MeshCollider => "MeshCollider",
+        }
+    }
+}
+
+// A mesh of a gltf waiting for the gltf to be loaded by the ContentProvider
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingGltfMesh {
+    pub gltf_src: String,
+    pub mesh_name: String,
+    pub file_hash: String,
+}
+
+// Starts loading the gltf of a GltfMesh, the mesh is applied by `update_gltf_meshes`
+//  once it's loaded
+pub fn request_gltf_mesh(
+    scene: &mut Scene,
+    entity: &SceneEntityId,
+    target: GltfMeshTarget,
+    gltf_src: &str,
+    mesh_name: &str,
+) {
+    let mut content_provider = DclGlobal::singleton().bind().get_content_provider();
+    let Some(file_hash) = scene.content_mapping.get_hash(gltf_src).cloned() else {
+        tracing::warn!(
+            "scene {:?} entity {:?}: gltf {} of the {} not found in the content mapping",
+            scene.scene_id,
+            entity,
+            gltf_src,
+            target.node_name()
+        );
+        content_provider
+            .bind()
+            .report_scene_missing_file(scene.scene_id.0, GString::from(gltf_src));
+        scene.pending_gltf_meshes.remove(&(*entity, target));
+        return;
+    };
+
+    let pending = PendingGltfMesh {
+        gltf_src: gltf_src.to_string(),
+        mesh_name: mesh_name.to_string(),
+        file_hash,
+    };
+
+    // the same gltf is already being loaded for this component
+    if scene.pending_gltf_meshes.get(&(*entity, target)) == Some(&pending) {
+        return;
+    }
+
+    content_provider.call_deferred(
+        "fetch_scene_gltf".into(),
+        &[
+            GString::from(gltf_src).to_variant(),
+            DclContentMappingAndUrl::from_ref(scene.content_mapping.clone()).to_variant(),
+        ],
+    );
+    scene.pending_gltf_meshes.insert((*entity, target), pending);
+}
+
+pub fn cancel_gltf_mesh(scene: &mut Scene, entity: &SceneEntityId, target: GltfMeshTarget) {
+    scene.pending_gltf_meshes.remove(&(*entity, target));
+}
+
+// The `mesh_name` is the name of the mesh in the gltf, the node name is also accepted
+//  because some exporters only name the nodes. An empty name picks the first mesh
+fn find_mesh(node: Gd<Node>, mesh_name: &str) -> Option<Gd<Mesh>> {
+    if let Ok(mesh_instance) = node.clone().try_cast::<MeshInstance3D>() {
+        if let Some(mesh) = mesh_instance.get_mesh() {
+            if mesh_name.is_empty()
+                || mesh.get_name().to_string() == mesh_name
+                || mesh_instance.get_name().to_string() == mesh_name
+            {
+                return Some(mesh);
+            }
+        }
+    }
+
+    node.get_children()
+        .iter_shared()
+        .find_map(|child| find_mesh(child, mesh_name))
+}
+
+fn apply_gltf_mesh(node_3d: &Gd<Node3D>, target: GltfMeshTarget, mesh: Gd<Mesh>) -> bool {
+    match target {
+        GltfMeshTarget::MeshRenderer => {
+            let Some(mut mesh_instance) =
+                node_3d.try_get_node_as::<MeshInstance3D>(NodePath::from(target.node_name()))
+            else {
+                return false;
+            };
+            mesh_instance.set_mesh(mesh);
+            true
+        }
+        GltfMeshTarget::MeshCollider => {
+            let Some(animatable_body_3d) =
+                node_3d.try_get_node_as::<AnimatableBody3D>(NodePath::from(target.node_name()))
+            else {
+                return false;
+            };
+            let Some(mut collision_shape) = animatable_body_3d
+                .get_child(0)
+                .and_then(|child| child.try_cast::<CollisionShape3D>().ok())
+            else {
+                return false;
+            };
+
+            // the trimesh keeps the exact shape, the convex is the fallback for meshes
+            //  without faces
+            let shape: Option<Gd<Shape3D>> = mesh
+                .create_trimesh_shape()
+                .map(|shape| shape.upcast())
+                .or_else(|| {
+                    mesh.create_convex_shape(true, false)
+                        .map(|shape| shape.upcast())
+                });
+            let Some(shape) = shape else {
+                return false;
+            };
+            collision_shape.set_shape(shape);
+            true
+        }
+    }
+}
+
+// Applies the meshes of the gltfs that finished loading, the failed ones are discarded.
+//  Returns the errors to show in the scene console
+pub fn update_gltf_meshes(scene: &mut Scene) -> Vec<String> {
+    let mut errors = Vec::new();
+    if scene.pending_gltf_meshes.is_empty() {
+        return errors;
+    }
+
+    let mut content_provider = DclGlobal::singleton().bind().get_content_provider();
+    let mut finished = Vec::new();
+
+    for ((entity, target), pending) in scene.pending_gltf_meshes.iter() {
+        let file_hash = GString::from(&pending.file_hash);
+        if !content_provider
+            .bind()
+            .is_resource_from_hash_loaded(file_hash.clone())
+        {
+            continue;
+        }
+
+        finished.push((*entity, *target));
+
+        let Some(node_3d) = scene.godot_dcl_scene.get_node_3d(entity) else {
+            continue;
+        };
+
+        // the load error is also in the scene content report
+        let Some(gltf_node) = content_provider.bind_mut().get_gltf_from_hash(file_hash) else {
+            errors.push(format!(
+                "entity {:?}: gltf {} of the {} failed to load",
+                entity,
+                pending.gltf_src,
+                target.node_name()
+            ));
+            continue;
+        };
+
+        let Some(mesh) = find_mesh(gltf_node.upcast(), &pending.mesh_name) else {
+            errors.push(format!(
+                "entity {:?}: mesh '{}' not found in the gltf {}",
+                entity, pending.mesh_name, pending.gltf_src
+            ));
+            continue;
+        };
+
+        if !apply_gltf_mesh(node_3d, *target, mesh) {
+            errors.push(format!(
+                "entity {:?}: couldn't apply the mesh '{}' of the gltf {} to the {}",
+                entity,
+                pending.mesh_name,
+                pending.gltf_src,
+                target.node_name()
+            ));
+        }
+    }
+
+    for key in finished {
+        scene.pending_gltf_meshes.remove(&key);
+    }
+    errors
+}
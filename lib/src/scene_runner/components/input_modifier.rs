@@ -0,0 +1,69 @@
+use crate::{
+    dcl::{
+        components::{
+            proto_components::sdk::components::pb_input_modifier, SceneComponentId, SceneEntityId,
+        },
+        crdt::{
+            last_write_wins::LastWriteWinsComponentOperation, SceneCrdtState,
+            SceneCrdtStateProtoComponents,
+        },
+    },
+    scene_runner::scene::Scene,
+};
+use godot::prelude::*;
+
+// Restrictions of the player input, `disable_all` is already folded into each flag
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputModifier {
+    pub disable_walk: bool,
+    pub disable_jog: bool,
+    pub disable_run: bool,
+    pub disable_jump: bool,
+    pub disable_emote: bool,
+}
+
+impl InputModifier {
+    fn from_standard_input(standard: &pb_input_modifier::StandardInput) -> Self {
+        let disable_all = standard.disable_all.unwrap_or(false);
+        Self {
+            disable_walk: disable_all || standard.disable_walk.unwrap_or(false),
+            disable_jog: disable_all || standard.disable_jog.unwrap_or(false),
+            disable_run: disable_all || standard.disable_run.unwrap_or(false),
+            disable_jump: disable_all || standard.disable_jump.unwrap_or(false),
+            disable_emote: disable_all || standard.disable_emote.unwrap_or(false),
+        }
+    }
+
+    pub fn to_dictionary(self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        dict.set("disable_walk", self.disable_walk);
+        dict.set("disable_jog", self.disable_jog);
+        dict.set("disable_run", self.disable_run);
+        dict.set("disable_jump", self.disable_jump);
+        dict.set("disable_emote", self.disable_emote);
+        dict
+    }
+}
+
+pub fn update_input_modifier(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
+    let dirty_lww_components = &scene.current_dirty.lww_components;
+    let Some(input_modifier_dirty) = dirty_lww_components.get(&SceneComponentId::INPUT_MODIFIER)
+    else {
+        return;
+    };
+
+    // the input modifier is only read from the player entity
+    if !input_modifier_dirty.contains(&SceneEntityId::PLAYER) {
+        return;
+    }
+
+    scene.input_modifier = SceneCrdtStateProtoComponents::get_input_modifier(crdt_state)
+        .get(&SceneEntityId::PLAYER)
+        .and_then(|entry| entry.value.as_ref())
+        .and_then(|input_modifier| input_modifier.mode.as_ref())
+        .map(|mode| match mode {
+            pb_input_modifier::Mode::Standard(standard) => {
+                InputModifier::from_standard_input(standard)
+            }
+        });
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::{
+    dcl::{
+        components::{
+            proto_components::{
+                sdk::components::{pb_light_source, PbLightSource},
+                WrapToGodot,
+            },
+            SceneComponentId,
+        },
+        crdt::{
+            last_write_wins::LastWriteWinsComponentOperation, SceneCrdtState,
+            SceneCrdtStateProtoComponents,
+        },
+        SceneId,
+    },
+    scene_runner::scene::Scene,
+};
+use godot::{
+    engine::{light_3d::Param, Light3D, OmniLight3D, Os, SpotLight3D},
+    prelude::*,
+};
+
+// brightness that maps to the default energy (1.0) of the godot lights
+const DEFAULT_BRIGHTNESS: f32 = 16000.0;
+// range used for the default brightness when the scene doesn't set it
+const DEFAULT_RANGE: f32 = 10.0;
+const DEFAULT_SPOT_INNER_ANGLE: f32 = 21.8;
+const DEFAULT_SPOT_OUTER_ANGLE: f32 = 30.0;
+
+const MAX_ACTIVE_LIGHTS_PER_SCENE: usize = 8;
+const MAX_ACTIVE_LIGHTS_MOBILE: usize = 8;
+const MAX_ACTIVE_LIGHTS_DESKTOP: usize = 32;
+
+const LIGHT_SOURCE_NODE_NAME: &str = "LightSource";
+
+fn create_light_node(light_type: &pb_light_source::Type) -> Gd<Light3D> {
+    let mut light: Gd<Light3D> = match light_type {
+        pb_light_source::Type::Point(_) => OmniLight3D::new_alloc().upcast(),
+        pb_light_source::Type::Spot(_) => SpotLight3D::new_alloc().upcast(),
+    };
+    light.set_name(LIGHT_SOURCE_NODE_NAME.into());
+    // it's shown by the budget, once it knows if there is room for it
+    light.set_visible(false);
+    light
+}
+
+fn is_same_light_type(light: &Gd<Light3D>, light_type: &pb_light_source::Type) -> bool {
+    match light_type {
+        pb_light_source::Type::Point(_) => light.clone().try_cast::<OmniLight3D>().is_ok(),
+        pb_light_source::Type::Spot(_) => light.clone().try_cast::<SpotLight3D>().is_ok(),
+    }
+}
+
+fn apply_light_source(light: &mut Gd<Light3D>, light_source: &PbLightSource) {
+    let energy = light_source
+        .brightness
+        .unwrap_or(DEFAULT_BRIGHTNESS)
+        .max(0.0)
+        / DEFAULT_BRIGHTNESS;
+    // a negative or zero range means that it's computed from the brightness
+    let range = match light_source.range {
+        Some(range) if range > 0.0 => range,
+        _ => DEFAULT_RANGE * energy.sqrt(),
+    };
+
+    light.set_color(light_source.color.to_godot_or_else(Color::WHITE));
+    light.set_param(Param::PARAM_ENERGY, energy);
+
+    match light_source.r#type.as_ref() {
+        Some(pb_light_source::Type::Point(point)) => {
+            light.set_param(Param::PARAM_RANGE, range);
+            light.set_shadow(point.shadow.unwrap_or(false));
+        }
+        Some(pb_light_source::Type::Spot(spot)) => {
+            // the sdk angles are the full cone, godot uses the half
+            let outer_angle = spot
+                .outer_angle
+                .unwrap_or(DEFAULT_SPOT_OUTER_ANGLE)
+                .clamp(1.0, 179.0);
+            let inner_angle = spot
+                .inner_angle
+                .unwrap_or(DEFAULT_SPOT_INNER_ANGLE)
+                .clamp(0.0, outer_angle);
+
+            light.set_param(Param::PARAM_RANGE, range);
+            light.set_param(Param::PARAM_SPOT_ANGLE, outer_angle * 0.5);
+            // approximation of the inner cone: the closer the inner angle is to the outer one,
+            //  the harder the edge of the light
+            light.set_param(
+                Param::PARAM_SPOT_ATTENUATION,
+                ((outer_angle - inner_angle) / outer_angle).clamp(0.01, 1.0),
+            );
+            light.set_shadow(spot.shadow.unwrap_or(false));
+        }
+        None => {}
+    }
+}
+
+pub fn update_light_source(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
+    let godot_dcl_scene = &mut scene.godot_dcl_scene;
+    let dirty_lww_components = &scene.current_dirty.lww_components;
+    let Some(light_source_dirty) = dirty_lww_components.get(&SceneComponentId::LIGHT_SOURCE) else {
+        return;
+    };
+
+    let light_source_component = SceneCrdtStateProtoComponents::get_light_source(crdt_state);
+
+    for entity in light_source_dirty {
+        let Some(new_value) = light_source_component.get(entity) else {
+            continue;
+        };
+
+        let (_godot_entity_node, mut node_3d) = godot_dcl_scene.ensure_node_3d(entity);
+        let existing = node_3d.try_get_node_as::<Light3D>(NodePath::from(LIGHT_SOURCE_NODE_NAME));
+
+        let light_source = new_value
+            .value
+            .as_ref()
+            .filter(|light_source| light_source.r#type.is_some());
+
+        let Some(light_source) = light_source else {
+            if let Some(mut light) = existing {
+                light.queue_free();
+                node_3d.remove_child(light.upcast());
+            }
+            scene.light_sources.remove(entity);
+            continue;
+        };
+
+        let light_type = light_source.r#type.as_ref().unwrap();
+        let mut light = match existing {
+            Some(light) if is_same_light_type(&light, light_type) => light,
+            existing => {
+                if let Some(mut light) = existing {
+                    light.queue_free();
+                    node_3d.remove_child(light.clone().upcast());
+                }
+                let light = create_light_node(light_type);
+                node_3d.add_child(light.clone().upcast());
+                light
+            }
+        };
+
+        apply_light_source(&mut light, light_source);
+        scene
+            .light_sources
+            .insert(*entity, light_source.active.unwrap_or(true));
+    }
+}
+
+// Only the lights nearest the camera are enabled: up to MAX_ACTIVE_LIGHTS_PER_SCENE per scene
+//  and a global limit that depends on the platform
+pub fn update_light_budget(
+    scenes: &mut HashMap<SceneId, Scene>,
+    camera_global_transform: &Transform3D,
+) {
+    let camera_position = camera_global_transform.origin;
+    let mut candidates = Vec::new();
+
+    for scene in scenes.values_mut() {
+        if scene.light_sources.is_empty() {
+            continue;
+        }
+
+        let mut scene_lights = Vec::new();
+        scene.light_sources.retain(|entity, active| {
+            let Some(node_3d) = scene.godot_dcl_scene.get_node_3d(entity) else {
+                return false;
+            };
+            let Some(mut light) =
+                node_3d.try_get_node_as::<Light3D>(NodePath::from(LIGHT_SOURCE_NODE_NAME))
+            else {
+                return false;
+            };
+
+            // lights hidden by the Visibility component of an ancestor don't count
+            if *active && node_3d.is_visible_in_tree() {
+                let distance = light
+                    .get_global_position()
+                    .distance_squared_to(camera_position);
+                scene_lights.push((distance, light));
+            } else {
+                light.set_visible(false);
+            }
+            true
+        });
+
+        scene_lights.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (index, (distance, mut light)) in scene_lights.into_iter().enumerate() {
+            if index < MAX_ACTIVE_LIGHTS_PER_SCENE {
+                candidates.push((distance, light));
+            } else {
+                light.set_visible(false);
+            }
+        }
+    }
+
+    let max_active_lights = if Os::singleton().has_feature("mobile".into()) {
+        MAX_ACTIVE_LIGHTS_MOBILE
+    } else {
+        MAX_ACTIVE_LIGHTS_DESKTOP
+    };
+
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (index, (_, mut light)) in candidates.into_iter().enumerate() {
+        let visible = index < max_active_lights;
+        if light.is_visible() != visible {
+            light.set_visible(visible);
+        }
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
     dcl::{
         components::{
             material::{DclMaterial, DclSourceTex, DclTexture},
-            SceneComponentId,
+            SceneComponentId, SceneEntityId,
         },
         crdt::{
             last_write_wins::LastWriteWinsComponentOperation, SceneCrdtState,
@@ -20,12 +20,42 @@ use godot::{
     prelude::{utilities::weakref, *},
 };
 
+// The UI entities used as texture by the materials of the alive entities
+fn get_referenced_ui_textures(
+    scene: &Scene,
+    crdt_state: &SceneCrdtState,
+) -> HashSet<SceneEntityId> {
+    let material_component = SceneCrdtStateProtoComponents::get_material(crdt_state);
+    let mut referenced = HashSet::new();
+    for (entity, entry) in material_component.values.iter() {
+        if crdt_state.entities.is_dead(entity) {
+            continue;
+        }
+        let Some(material) = entry
+            .value
+            .as_ref()
+            .and_then(|value| value.material.as_ref())
+        else {
+            continue;
+        };
+
+        let dcl_material = DclMaterial::from_proto(material, &scene.content_mapping);
+        for tex in dcl_material.get_textures().into_iter().flatten() {
+            if let DclSourceTex::UiTexture(ui_entity) = &tex.source {
+                referenced.insert(*ui_entity);
+            }
+        }
+    }
+    referenced
+}
+
 pub fn update_material(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
     let godot_dcl_scene = &mut scene.godot_dcl_scene;
     let dirty_lww_components = &scene.current_dirty.lww_components;
     let material_component = SceneCrdtStateProtoComponents::get_material(crdt_state);
     let mut content_provider = DclGlobal::singleton().bind().get_content_provider();
 
+    let mut ui_texture_entities = Vec::new();
     if let Some(material_dirty) = dirty_lww_components.get(&SceneComponentId::MATERIAL) {
         for entity in material_dirty {
             let new_value = material_component.get(entity);
@@ -68,21 +98,40 @@ pub fn update_material(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
 
                 if existing_material.is_none() {
                     for tex in dcl_material.get_textures().into_iter().flatten() {
-                        if let DclSourceTex::Texture(hash) = &tex.source {
-                            content_provider.call_deferred(
-                                "fetch_texture_by_hash".into(),
-                                &[
-                                    GString::from(hash).to_variant(),
-                                    DclContentMappingAndUrl::from_ref(
-                                        scene.content_mapping.clone(),
-                                    )
-                                    .to_variant(),
-                                ],
-                            );
+                        match &tex.source {
+                            DclSourceTex::Texture(hash) => {
+                                content_provider
+                                    .bind_mut()
+                                    .register_texture_scene(hash, scene.scene_id);
+                                content_provider.call_deferred(
+                                    "fetch_texture_by_hash".into(),
+                                    &[
+                                        GString::from(hash).to_variant(),
+                                        DclContentMappingAndUrl::from_ref(
+                                            scene.content_mapping.clone(),
+                                        )
+                                        .to_variant(),
+                                    ],
+                                );
+                            }
+                            DclSourceTex::AvatarTexture(user_id) => {
+                                content_provider.call_deferred(
+                                    "fetch_avatar_texture".into(),
+                                    &[GString::from(user_id).to_variant()],
+                                );
+                            }
+                            DclSourceTex::UiTexture(_) | DclSourceTex::VideoTexture(_) => {}
                         }
                     }
                 }
 
+                // a cached material can reference a UI texture that was freed
+                for tex in dcl_material.get_textures().into_iter().flatten() {
+                    if let DclSourceTex::UiTexture(ui_entity) = &tex.source {
+                        ui_texture_entities.push(*ui_entity);
+                    }
+                }
+
                 let mut godot_material = if let Some(material) = existing_material {
                     material.to::<Gd<StandardMaterial3D>>()
                 } else {
@@ -141,6 +190,11 @@ pub fn update_material(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
 
                 if let Some(mut mesh_renderer) = mesh_renderer {
                     mesh_renderer.set_surface_override_material(0, godot_material.upcast());
+
+                    // the texture tween modifies a copy of the material, it's copied again
+                    if let Some(tween) = scene.tweens.get_mut(entity) {
+                        tween.texture_uv_applied = false;
+                    }
                 }
             } else {
                 let mesh_renderer =
@@ -159,6 +213,29 @@ pub fn update_material(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
         scene.dirty_materials = true;
     }
 
+    for ui_entity in ui_texture_entities {
+        // the root entity is the scene UI canvas, it can't be moved into a texture
+        if ui_entity != SceneEntityId::ROOT {
+            scene.godot_dcl_scene.ensure_ui_texture(&ui_entity);
+        }
+    }
+
+    let materials_changed = dirty_lww_components.contains_key(&SceneComponentId::MATERIAL)
+        || !scene.current_dirty.entities.died.is_empty();
+    if materials_changed && !scene.godot_dcl_scene.ui_textures.is_empty() {
+        let referenced_ui_textures = get_referenced_ui_textures(scene, crdt_state);
+        let unreferenced_ui_textures: Vec<SceneEntityId> = scene
+            .godot_dcl_scene
+            .ui_textures
+            .keys()
+            .filter(|entity| !referenced_ui_textures.contains(entity))
+            .copied()
+            .collect();
+        for ui_entity in unreferenced_ui_textures {
+            scene.godot_dcl_scene.remove_ui_texture(&ui_entity);
+        }
+    }
+
     if scene.dirty_materials {
         let mut keep_dirty = false;
         let mut dead_materials = HashSet::with_capacity(scene.materials.capacity());
@@ -265,8 +342,32 @@ fn check_texture(
                 return false;
             }
         }
-        DclSourceTex::AvatarTexture(_user_id) => {
-            // TODO: implement load avatar texture
+        DclSourceTex::AvatarTexture(user_id) => {
+            let avatar_texture_hash =
+                ContentProvider::get_avatar_texture_hash(GString::from(user_id));
+            if avatar_texture_hash.is_empty() {
+                // invalid user id, the material stays without texture
+                return true;
+            }
+            if !content_provider.is_resource_from_hash_loaded(avatar_texture_hash.clone()) {
+                return false;
+            }
+            // the user could have no snapshots, then it stays without texture
+            if let Some(resource) = content_provider.get_texture_from_hash(avatar_texture_hash) {
+                material.set_texture(param, resource.upcast());
+            }
+        }
+        DclSourceTex::UiTexture(ui_entity) => {
+            if *ui_entity == SceneEntityId::ROOT {
+                return true;
+            }
+            // the UI entity died, the material stays without texture
+            let Some(viewport) = _scene.godot_dcl_scene.ui_textures.get(ui_entity) else {
+                return true;
+            };
+            if let Some(texture) = viewport.get_texture() {
+                material.set_texture(param, texture.upcast());
+            }
         }
 
         #[cfg(not(feature = "use_ffmpeg"))]
@@ -9,7 +9,10 @@ use crate::{
             SceneCrdtStateProtoComponents,
         },
     },
-    scene_runner::scene::Scene,
+    scene_runner::{
+        components::gltf_mesh::{cancel_gltf_mesh, request_gltf_mesh, GltfMeshTarget},
+        scene::Scene,
+    },
 };
 use godot::{
     engine::{
@@ -284,8 +287,11 @@ pub fn create_or_update_mesh(
                 box_shape.upcast()
             }
             pb_mesh_collider::Mesh::Gltf(_) => {
-                // TODO: Implement Gltf
-                todo!("Implement Gltf Mesh Collider");
+                // the shape is set once the gltf is loaded (see gltf_mesh.rs)
+                collision_shape.call("set_shape".into(), &[Variant::nil()]);
+                animatable_body_3d.set_collision_layer(collision_mask);
+                animatable_body_3d.set_collision_mask(0);
+                return;
             }
         },
         _ => {
@@ -307,6 +313,7 @@ pub fn create_or_update_mesh(
 pub fn update_mesh_collider(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
     let godot_dcl_scene = &mut scene.godot_dcl_scene;
     let dirty_lww_components = &scene.current_dirty.lww_components;
+    let mut gltf_meshes = Vec::new();
     if let Some(mesh_collider_dirty) = dirty_lww_components.get(&SceneComponentId::MESH_COLLIDER) {
         let mesh_collider_component = SceneCrdtStateProtoComponents::get_mesh_collider(crdt_state);
 
@@ -323,6 +330,14 @@ pub fn update_mesh_collider(scene: &mut Scene, crdt_state: &mut SceneCrdtState)
             let existing =
                 node_3d.try_get_node_as::<AnimatableBody3D>(NodePath::from("MeshCollider"));
 
+            let gltf_mesh = new_value
+                .as_ref()
+                .and_then(|value| match value.mesh.as_ref() {
+                    Some(pb_mesh_collider::Mesh::Gltf(gltf)) => Some(gltf.clone()),
+                    _ => None,
+                });
+            gltf_meshes.push((*entity, gltf_mesh));
+
             if new_value.is_none() {
                 if let Some(mut mesh_collider_node) = existing {
                     mesh_collider_node.queue_free();
@@ -355,4 +370,17 @@ pub fn update_mesh_collider(scene: &mut Scene, crdt_state: &mut SceneCrdtState)
             }
         }
     }
+
+    for (entity, gltf_mesh) in gltf_meshes {
+        match gltf_mesh {
+            Some(gltf_mesh) => request_gltf_mesh(
+                scene,
+                &entity,
+                GltfMeshTarget::MeshCollider,
+                &gltf_mesh.gltf_src,
+                &gltf_mesh.name,
+            ),
+            None => cancel_gltf_mesh(scene, &entity, GltfMeshTarget::MeshCollider),
+        }
+    }
 }
@@ -11,7 +11,10 @@ use crate::{
             SceneCrdtStateProtoComponents,
         },
     },
-    scene_runner::scene::Scene,
+    scene_runner::{
+        components::gltf_mesh::{cancel_gltf_mesh, request_gltf_mesh, GltfMeshTarget},
+        scene::Scene,
+    },
 };
 use godot::{
     engine::{BoxMesh, MeshInstance3D},
@@ -54,7 +57,8 @@ pub fn create_or_update_mesh(mesh_instance: &mut Gd<MeshInstance3D>, mesh: &PbMe
                 mesh_instance.call("set_plane".into(), &[uvs]);
             }
             pb_mesh_renderer::Mesh::Gltf(_) => {
-                todo!("Implement Gltf Mesh Renderer")
+                // the mesh is set once the gltf is loaded (see gltf_mesh.rs)
+                mesh_instance.call("set_mesh".into(), &[Variant::nil()]);
             }
         },
         _ => {
@@ -82,6 +86,7 @@ pub fn update_mesh_renderer(
         .lww_components
         .remove(&SceneComponentId::MESH_RENDERER);
 
+    let mut gltf_meshes = Vec::new();
     if let Some(mut mesh_renderer_dirty) = mesh_renderer_dirty {
         let mesh_renderer_component = SceneCrdtStateProtoComponents::get_mesh_renderer(crdt_state);
 
@@ -98,6 +103,14 @@ pub fn update_mesh_renderer(
             let existing =
                 node_3d.try_get_node_as::<MeshInstance3D>(NodePath::from("MeshRenderer"));
 
+            let gltf_mesh = new_value
+                .as_ref()
+                .and_then(|value| match value.mesh.as_ref() {
+                    Some(pb_mesh_renderer::Mesh::Gltf(gltf)) => Some(gltf.clone()),
+                    _ => None,
+                });
+            gltf_meshes.push((*entity, gltf_mesh));
+
             if new_value.is_none() {
                 if let Some(mut mesh_renderer_node) = existing {
                     mesh_renderer_node.queue_free();
@@ -131,6 +144,19 @@ pub fn update_mesh_renderer(
             }
         }
 
+        for (entity, gltf_mesh) in gltf_meshes {
+            match gltf_mesh {
+                Some(gltf_mesh) => request_gltf_mesh(
+                    scene,
+                    &entity,
+                    GltfMeshTarget::MeshRenderer,
+                    &gltf_mesh.gltf_src,
+                    &gltf_mesh.name,
+                ),
+                None => cancel_gltf_mesh(scene, &entity, GltfMeshTarget::MeshRenderer),
+            }
+        }
+
         if updated_count < mesh_renderer_dirty.len() {
             mesh_renderer_dirty.drain(0..updated_count);
             scene
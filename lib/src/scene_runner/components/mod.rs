@@ -9,6 +9,9 @@ pub mod avatar_shape;
 pub mod billboard;
 pub mod camera_mode_area;
 pub mod gltf_container;
+pub mod gltf_mesh;
+pub mod input_modifier;
+pub mod light_source;
 pub mod material;
 pub mod mesh_collider;
 pub mod mesh_renderer;
@@ -21,4 +24,5 @@ pub mod tween;
 pub mod ui;
 #[cfg(feature = "use_ffmpeg")]
 pub mod video_player;
+pub mod virtual_camera;
 pub mod visibility;
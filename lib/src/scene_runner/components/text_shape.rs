@@ -1,7 +1,10 @@
 use crate::{
     dcl::{
         components::{
-            proto_components::sdk::components::common::{Font, TextAlignMode},
+            proto_components::sdk::components::{
+                common::{Font, TextAlignMode},
+                PbTextShape,
+            },
             SceneComponentId,
         },
         crdt::{
@@ -16,11 +19,323 @@ use godot::{
         global::{HorizontalAlignment, VerticalAlignment},
         label_3d::AlphaCutMode,
         text_server::AutowrapMode,
-        Label3D,
+        Label3D, TextParagraph,
     },
     prelude::*,
 };
 
+// The text is rendered with a Label3D, so the texts share the font atlas instead of
+//  having a texture each. The shadow is a second Label3D behind the text with its blur
+//  approximated by the outline, and the line count is applied by truncating the text.
+const PIXELS_PER_METER: f32 = 200.0;
+// pixels of each unit of `font_size`, the outline and shadow values are relative to it
+const FONT_UNIT_PIXELS: f32 = 22.0;
+// default width of the wrapped text when the scene doesn't set it
+const DEFAULT_WRAP_WIDTH: f32 = 16.0;
+// meters between the text and its shadow, it avoids the z-fighting
+const SHADOW_DEPTH_OFFSET: f32 = 0.001;
+
+const TEXT_SHAPE_NODE_NAME: &str = "TextShape";
+const SHADOW_NODE_NAME: &str = "Shadow";
+
+fn get_alignment(text_align: TextAlignMode) -> (HorizontalAlignment, VerticalAlignment) {
+    let h_align = match text_align {
+        TextAlignMode::TamTopLeft | TextAlignMode::TamMiddleLeft | TextAlignMode::TamBottomLeft => {
+            HorizontalAlignment::HORIZONTAL_ALIGNMENT_LEFT
+        }
+        TextAlignMode::TamTopRight
+        | TextAlignMode::TamMiddleRight
+        | TextAlignMode::TamBottomRight => HorizontalAlignment::HORIZONTAL_ALIGNMENT_RIGHT,
+        TextAlignMode::TamTopCenter
+        | TextAlignMode::TamMiddleCenter
+        | TextAlignMode::TamBottomCenter => HorizontalAlignment::HORIZONTAL_ALIGNMENT_CENTER,
+    };
+    let v_align = match text_align {
+        TextAlignMode::TamTopLeft | TextAlignMode::TamTopCenter | TextAlignMode::TamTopRight => {
+            VerticalAlignment::VERTICAL_ALIGNMENT_TOP
+        }
+        TextAlignMode::TamBottomLeft
+        | TextAlignMode::TamBottomCenter
+        | TextAlignMode::TamBottomRight => VerticalAlignment::VERTICAL_ALIGNMENT_BOTTOM,
+        TextAlignMode::TamMiddleLeft
+        | TextAlignMode::TamMiddleCenter
+        | TextAlignMode::TamMiddleRight => VerticalAlignment::VERTICAL_ALIGNMENT_CENTER,
+    };
+    (h_align, v_align)
+}
+
+// Side of the box where the text is aligned: x is -1 left, 1 right; y is 1 top, -1 bottom
+fn get_alignment_side(text_align: TextAlignMode) -> (f32, f32) {
+    let x = match text_align {
+        TextAlignMode::TamTopLeft | TextAlignMode::TamMiddleLeft | TextAlignMode::TamBottomLeft => {
+            -1.0
+        }
+        TextAlignMode::TamTopRight
+        | TextAlignMode::TamMiddleRight
+        | TextAlignMode::TamBottomRight => 1.0,
+        _ => 0.0,
+    };
+    let y = match text_align {
+        TextAlignMode::TamTopLeft | TextAlignMode::TamTopCenter | TextAlignMode::TamTopRight => 1.0,
+        TextAlignMode::TamBottomLeft
+        | TextAlignMode::TamBottomCenter
+        | TextAlignMode::TamBottomRight => -1.0,
+        _ => 0.0,
+    };
+    (x, y)
+}
+
+// Position (in meters) of the text in one axis, the Label3D places the aligned side
+//  (`side` -1, 1, or 0 for the center) of the text at its origin. When the scene sets
+//  the size the box is centered in the entity, otherwise the box grows from the entity
+//  origin in the opposite direction of the alignment. The padding moves the text inside the box.
+fn get_text_anchor(
+    side: f32,
+    fixed_size: Option<f32>,
+    padding_negative: f32,
+    padding_positive: f32,
+) -> f32 {
+    let box_side = fixed_size.map(|size| side * size * 0.5).unwrap_or(0.0);
+    if side < 0.0 {
+        box_side + padding_negative
+    } else if side > 0.0 {
+        box_side - padding_positive
+    } else {
+        (padding_negative - padding_positive) * 0.5
+    }
+}
+
+struct TextShapeLayout {
+    text: String,
+    // in pixels
+    font_size: f32,
+    line_spacing: f32,
+    wrap_width: Option<f32>,
+}
+
+// Size in pixels of the text with the same line breaks as the Label3D
+fn measure_text(
+    text: &str,
+    font: &Gd<godot::engine::Font>,
+    font_size: f32,
+    line_spacing: f32,
+    wrap_width: Option<f32>,
+) -> (Vector2, Gd<TextParagraph>) {
+    let mut paragraph = TextParagraph::new();
+    paragraph.add_string(GString::from(text), font.clone(), font_size.max(1.0) as i64);
+    paragraph.set_width(wrap_width.unwrap_or(-1.0));
+    let line_count = paragraph.get_line_count().max(1);
+    let size = paragraph.get_size() + Vector2::new(0.0, line_spacing * (line_count - 1) as f32);
+    (size, paragraph)
+}
+
+fn layout_text(text_shape: &PbTextShape, font: &Gd<godot::engine::Font>) -> TextShapeLayout {
+    let padding_left = text_shape.padding_left.unwrap_or(0.0).max(0.0) * PIXELS_PER_METER;
+    let padding_right = text_shape.padding_right.unwrap_or(0.0).max(0.0) * PIXELS_PER_METER;
+    let padding_top = text_shape.padding_top.unwrap_or(0.0).max(0.0) * PIXELS_PER_METER;
+    let padding_bottom = text_shape.padding_bottom.unwrap_or(0.0).max(0.0) * PIXELS_PER_METER;
+
+    let fixed_width = text_shape.width.filter(|width| *width > 0.0);
+    let fixed_height = text_shape.height.filter(|height| *height > 0.0);
+    let available_size = Vector2::new(
+        fixed_width
+            .map(|width| width * PIXELS_PER_METER - padding_left - padding_right)
+            .unwrap_or(-1.0),
+        fixed_height
+            .map(|height| height * PIXELS_PER_METER - padding_top - padding_bottom)
+            .unwrap_or(-1.0),
+    );
+    let wrap_width = if text_shape.text_wrapping.unwrap_or_default() {
+        Some(if fixed_width.is_some() {
+            available_size.x.max(1.0)
+        } else {
+            DEFAULT_WRAP_WIDTH * PIXELS_PER_METER
+        })
+    } else {
+        None
+    };
+
+    let mut font_size = FONT_UNIT_PIXELS * text_shape.font_size.unwrap_or(3.0);
+    let line_spacing_factor = text_shape.line_spacing.unwrap_or(0.0);
+    let (text_size, mut paragraph) = measure_text(
+        &text_shape.text,
+        font,
+        font_size,
+        line_spacing_factor * font_size,
+        wrap_width,
+    );
+
+    // shrinks the font until the text fits in the box
+    if text_shape.font_auto_size.unwrap_or_default() {
+        let mut ratio: f32 = 1.0;
+        if available_size.x > 0.0 && text_size.x > available_size.x && wrap_width.is_none() {
+            ratio = ratio.min(available_size.x / text_size.x);
+        }
+        if available_size.y > 0.0 && text_size.y > available_size.y {
+            ratio = ratio.min(available_size.y / text_size.y);
+        }
+        if ratio < 1.0 {
+            font_size = (font_size * ratio).max(1.0);
+            (_, paragraph) = measure_text(
+                &text_shape.text,
+                font,
+                font_size,
+                line_spacing_factor * font_size,
+                wrap_width,
+            );
+        }
+    }
+
+    // truncates the text to `line_count` lines
+    let line_count = text_shape.line_count.unwrap_or(0);
+    let text = if line_count > 0 && paragraph.get_line_count() > line_count {
+        let end = paragraph.get_line_range(line_count - 1).y.max(0) as usize;
+        text_shape
+            .text
+            .chars()
+            .take(end)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    } else {
+        text_shape.text.clone()
+    };
+
+    TextShapeLayout {
+        text,
+        font_size,
+        line_spacing: line_spacing_factor * font_size,
+        wrap_width,
+    }
+}
+
+// The text and the shadow share the layout, only the colors and the outline differ
+fn set_label_text(
+    label_3d: &mut Gd<Label3D>,
+    layout: &TextShapeLayout,
+    font: &Gd<godot::engine::Font>,
+    text_align: TextAlignMode,
+) {
+    let (h_align, v_align) = get_alignment(text_align);
+    label_3d.set_font(font.clone());
+    label_3d.set_text(GString::from(&layout.text));
+    label_3d.set_font_size(layout.font_size.max(1.0) as i32);
+    label_3d.set_line_spacing(layout.line_spacing);
+    label_3d.set_horizontal_alignment(h_align);
+    label_3d.set_vertical_alignment(v_align);
+    label_3d.set_pixel_size(1.0 / PIXELS_PER_METER);
+    match layout.wrap_width {
+        Some(wrap_width) => {
+            label_3d.set_autowrap_mode(AutowrapMode::AUTOWRAP_WORD);
+            label_3d.set_width(wrap_width);
+        }
+        None => {
+            label_3d.set_autowrap_mode(AutowrapMode::AUTOWRAP_OFF);
+        }
+    }
+}
+
+fn create_text_shape_node() -> Gd<Label3D> {
+    let mut shadow = Label3D::new_alloc();
+    shadow.set_name(SHADOW_NODE_NAME.into());
+    shadow.set_alpha_cut_mode(AlphaCutMode::ALPHA_CUT_OPAQUE_PREPASS);
+    shadow.set_visible(false);
+
+    let mut label_3d = Label3D::new_alloc();
+    label_3d.set_name(TEXT_SHAPE_NODE_NAME.into());
+    label_3d.set_alpha_cut_mode(AlphaCutMode::ALPHA_CUT_OPAQUE_PREPASS);
+    label_3d.add_child(shadow.upcast());
+    label_3d
+}
+
+fn apply_text_shape(label_3d: &mut Gd<Label3D>, text_shape: &PbTextShape) {
+    let text_align = TextAlignMode::from_i32(
+        text_shape
+            .text_align
+            .unwrap_or(TextAlignMode::TamMiddleCenter as i32),
+    )
+    .unwrap_or(TextAlignMode::TamMiddleCenter);
+
+    let font = match text_shape.font {
+        Some(1) => Font::FSerif,
+        Some(2) => Font::FMonospace,
+        _ => Font::FSansSerif,
+    }
+    .get_font_resource();
+    let layout = layout_text(text_shape, &font);
+
+    let opacity = text_shape
+        .text_color
+        .as_ref()
+        .map(|color| color.a)
+        .unwrap_or(1.0);
+    let text_color = text_shape
+        .text_color
+        .as_ref()
+        .map(|color| Color::from_rgba(color.r, color.g, color.b, opacity))
+        .unwrap_or(Color::from_rgba(1.0, 1.0, 1.0, opacity));
+    let outline_color = text_shape
+        .outline_color
+        .as_ref()
+        .map(|color| Color::from_rgba(color.r, color.g, color.b, opacity))
+        .unwrap_or(Color::from_rgba(1.0, 1.0, 1.0, opacity));
+    let shadow_color = text_shape
+        .shadow_color
+        .as_ref()
+        .map(|color| Color::from_rgba(color.r, color.g, color.b, opacity))
+        .unwrap_or(Color::from_rgba(1.0, 1.0, 1.0, opacity));
+
+    set_label_text(label_3d, &layout, &font, text_align);
+    let outline_size = (layout.font_size * text_shape.outline_width.unwrap_or(0.0)).max(0.0);
+    label_3d.set_modulate(text_color);
+    label_3d.set_outline_size(outline_size as i32);
+    label_3d.set_outline_modulate(outline_color);
+
+    let (side_x, side_y) = get_alignment_side(text_align);
+    let padding = |value: Option<f32>| value.unwrap_or(0.0).max(0.0);
+    label_3d.set_position(Vector3::new(
+        get_text_anchor(
+            side_x,
+            text_shape.width.filter(|width| *width > 0.0),
+            padding(text_shape.padding_left),
+            padding(text_shape.padding_right),
+        ),
+        get_text_anchor(
+            side_y,
+            text_shape.height.filter(|height| *height > 0.0),
+            padding(text_shape.padding_bottom),
+            padding(text_shape.padding_top),
+        ),
+        0.0,
+    ));
+
+    let Some(mut shadow) = label_3d.try_get_node_as::<Label3D>(SHADOW_NODE_NAME) else {
+        return;
+    };
+    let shadow_blur = (layout.font_size * text_shape.shadow_blur.unwrap_or(0.0)).max(0.0);
+    // in font size units, the sdk y axis goes up like the godot one
+    let shadow_offset = Vector2::new(
+        text_shape.shadow_offset_x.unwrap_or(0.0),
+        text_shape.shadow_offset_y.unwrap_or(0.0),
+    ) * layout.font_size
+        / PIXELS_PER_METER;
+    let has_shadow = shadow_blur > 0.0 || shadow_offset != Vector2::ZERO;
+    shadow.set_visible(has_shadow);
+    if !has_shadow {
+        return;
+    }
+
+    set_label_text(&mut shadow, &layout, &font, text_align);
+    shadow.set_modulate(shadow_color);
+    shadow.set_outline_size(shadow_blur as i32);
+    shadow.set_outline_modulate(shadow_color);
+    shadow.set_position(Vector3::new(
+        shadow_offset.x,
+        shadow_offset.y,
+        -SHADOW_DEPTH_OFFSET,
+    ));
+}
+
 pub fn update_text_shape(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
     let godot_dcl_scene = &mut scene.godot_dcl_scene;
     let dirty_lww_components = &scene.current_dirty.lww_components;
@@ -37,7 +352,7 @@ pub fn update_text_shape(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
             let (_godot_entity_node, mut node_3d) = godot_dcl_scene.ensure_node_3d(entity);
 
             let new_value = new_value.value.clone();
-            let existing = node_3d.try_get_node_as::<Label3D>(NodePath::from("TextShape"));
+            let existing = node_3d.try_get_node_as::<Label3D>(NodePath::from(TEXT_SHAPE_NODE_NAME));
 
             if new_value.is_none() {
                 if let Some(mut text_shape_node) = existing {
@@ -47,111 +362,52 @@ pub fn update_text_shape(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
             } else if let Some(new_value) = new_value {
                 let (mut label_3d, add_to_base) = match existing {
                     Some(label_3d) => (label_3d, false),
-                    None => (Label3D::new_alloc(), true),
+                    None => (create_text_shape_node(), true),
                 };
 
-                let text_align = TextAlignMode::from_i32(
-                    new_value
-                        .text_align
-                        .unwrap_or(TextAlignMode::TamMiddleCenter as i32),
-                )
-                .unwrap_or(TextAlignMode::TamMiddleCenter);
-                let opacity = new_value
-                    .text_color
-                    .as_ref()
-                    .map(|color| color.a)
-                    .unwrap_or(1.0);
-
-                let text_color = new_value
-                    .text_color
-                    .map(|color| Color::from_rgba(color.r, color.g, color.b, opacity))
-                    .unwrap_or(Color::from_rgba(1.0, 1.0, 1.0, opacity));
-
-                let outline_color = new_value
-                    .outline_color
-                    .map(|color| Color::from_rgba(color.r, color.g, color.b, opacity))
-                    .unwrap_or(Color::from_rgba(1.0, 1.0, 1.0, opacity));
-
-                label_3d.set_text(GString::from(new_value.text));
-                label_3d.set_modulate(text_color);
-
-                let font_size = (22.0 * new_value.font_size.unwrap_or(3.0)).max(1.0);
-                let outline_size = font_size * new_value.outline_width.unwrap_or(0.0);
-                label_3d.set_font_size(font_size as i32);
-                label_3d.set_outline_size(outline_size as i32);
-                label_3d.set_outline_modulate(outline_color);
-                label_3d.set_alpha_cut_mode(AlphaCutMode::ALPHA_CUT_OPAQUE_PREPASS);
-
-                let text_wrapping = new_value.text_wrapping.unwrap_or_default();
-                if text_wrapping {
-                    label_3d.set_autowrap_mode(AutowrapMode::AUTOWRAP_WORD_SMART);
-                    label_3d.set_width(200.0 * new_value.width.unwrap_or(16.0));
-                } else {
-                    label_3d.set_autowrap_mode(AutowrapMode::AUTOWRAP_OFF);
-                    label_3d.set_width(200.0 * new_value.width.unwrap_or(16.0));
+                if add_to_base {
+                    node_3d.add_child(label_3d.clone().upcast());
                 }
 
-                let new_font = match new_value.font {
-                    Some(0) => Font::FSansSerif,
-                    Some(1) => Font::FSerif,
-                    Some(2) => Font::FMonospace,
-                    _ => Font::FSansSerif,
-                };
+                apply_text_shape(&mut label_3d, &new_value);
+            }
+        }
+    }
+}
 
-                let (v_align, y_pos) = match text_align {
-                    TextAlignMode::TamMiddleLeft
-                    | TextAlignMode::TamMiddleRight
-                    | TextAlignMode::TamMiddleCenter => {
-                        (VerticalAlignment::VERTICAL_ALIGNMENT_CENTER, 0.0)
-                    }
-                    TextAlignMode::TamTopLeft
-                    | TextAlignMode::TamTopRight
-                    | TextAlignMode::TamTopCenter => {
-                        (VerticalAlignment::VERTICAL_ALIGNMENT_TOP, 0.5)
-                    }
-                    TextAlignMode::TamBottomLeft
-                    | TextAlignMode::TamBottomRight
-                    | TextAlignMode::TamBottomCenter => {
-                        (VerticalAlignment::VERTICAL_ALIGNMENT_BOTTOM, -0.5)
-                    }
-                };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                let (h_align, x_pos) = match text_align {
-                    TextAlignMode::TamMiddleLeft
-                    | TextAlignMode::TamTopLeft
-                    | TextAlignMode::TamBottomLeft => {
-                        (HorizontalAlignment::HORIZONTAL_ALIGNMENT_LEFT, -0.5)
-                    }
-                    TextAlignMode::TamMiddleRight
-                    | TextAlignMode::TamTopRight
-                    | TextAlignMode::TamBottomRight => {
-                        (HorizontalAlignment::HORIZONTAL_ALIGNMENT_RIGHT, 0.5)
-                    }
-                    TextAlignMode::TamMiddleCenter
-                    | TextAlignMode::TamTopCenter
-                    | TextAlignMode::TamBottomCenter => {
-                        (HorizontalAlignment::HORIZONTAL_ALIGNMENT_CENTER, 0.0)
-                    }
-                };
+    #[test]
+    fn test_text_anchor() {
+        // a box with the size set by the scene is centered in the entity
+        assert_eq!(get_text_anchor(-1.0, Some(4.0), 0.0, 0.0), -2.0);
+        assert_eq!(get_text_anchor(1.0, Some(4.0), 0.0, 0.0), 2.0);
+        assert_eq!(get_text_anchor(0.0, Some(4.0), 0.0, 0.0), 0.0);
 
-                let width_meter = new_value.width.unwrap_or(0.0);
-                let height_meter = new_value.width.unwrap_or(0.0);
-                label_3d.set_position(Vector3::new(width_meter * x_pos, height_meter * y_pos, 0.0));
-                label_3d.set_vertical_alignment(v_align);
-                label_3d.set_horizontal_alignment(h_align);
+        // otherwise it grows from the origin
+        assert_eq!(get_text_anchor(-1.0, None, 0.0, 0.0), 0.0);
+        assert_eq!(get_text_anchor(1.0, None, 0.0, 0.0), 0.0);
 
-                if add_to_base {
-                    label_3d.set_name(GString::from("TextShape"));
-                    label_3d.set_font(new_font.get_font_resource());
-                    node_3d.add_child(label_3d.upcast());
-                }
+        // the padding moves the text inside the box
+        assert_eq!(get_text_anchor(-1.0, Some(4.0), 0.5, 1.0), -1.5);
+        assert_eq!(get_text_anchor(1.0, Some(4.0), 0.5, 1.0), 1.0);
+        assert_eq!(get_text_anchor(0.0, Some(4.0), 0.5, 1.0), -0.25);
+        assert_eq!(get_text_anchor(-1.0, None, 0.5, 1.0), 0.5);
+        assert_eq!(get_text_anchor(1.0, None, 0.5, 1.0), -1.0);
+    }
 
-                // TODO: missing properties
-                // - padding (left/right/top/bottom)
-                // - shadow (offsetX/offsetY/blur/color) (on Unity: it's actually an overlay)
-                // - line_spacing (on Unity: it doesn't work)
-                // - line_count (on Unity: it truncates instead of setting up the wrapping)
-            }
-        }
+    #[test]
+    fn test_alignment_side() {
+        assert_eq!(get_alignment_side(TextAlignMode::TamTopLeft), (-1.0, 1.0));
+        assert_eq!(
+            get_alignment_side(TextAlignMode::TamMiddleCenter),
+            (0.0, 0.0)
+        );
+        assert_eq!(
+            get_alignment_side(TextAlignMode::TamBottomRight),
+            (1.0, -1.0)
+        );
     }
 }
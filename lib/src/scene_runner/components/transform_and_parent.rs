@@ -2,7 +2,7 @@ use std::time::Instant;
 
 use godot::{
     builtin::math::FloatExt,
-    prelude::{Node, Transform3D, Vector3},
+    prelude::{Gd, Node, Node3D, Transform3D, Vector3},
 };
 
 use crate::{
@@ -39,6 +39,34 @@ impl DclTransformAndParent {
     }
 }
 
+// Sets the local transform of the node, fixing the invalid rotations and zero scales
+pub fn set_node_3d_transform(node_3d: &mut Gd<Node3D>, transform: &DclTransformAndParent) {
+    let mut transform = transform.clone();
+    if !transform.rotation.is_normalized() {
+        if transform.rotation.length_squared() == 0.0 {
+            transform.rotation = godot::prelude::Quaternion::default();
+        } else {
+            transform.rotation = transform.rotation.normalized();
+        }
+    }
+
+    if !transform.rotation.is_finite() {
+        transform.rotation = godot::prelude::Quaternion::default();
+    }
+
+    node_3d.set_transform(transform.to_godot_transform_3d_without_scaled());
+    if transform.scale.x.is_zero_approx() {
+        transform.scale.x = 0.00001;
+    }
+    if transform.scale.y.is_zero_approx() {
+        transform.scale.y = 0.00001;
+    }
+    if transform.scale.z.is_zero_approx() {
+        transform.scale.z = 0.00001;
+    }
+    node_3d.set_scale(transform.scale);
+}
+
 pub fn update_transform_and_parent(
     scene: &mut Scene,
     crdt_state: &mut SceneCrdtState,
@@ -64,30 +92,8 @@ pub fn update_transform_and_parent(
             let (godot_entity_node, mut node_3d) = godot_dcl_scene.ensure_node_3d(entity);
 
             let old_parent = godot_entity_node.desired_parent_3d;
-            let mut transform = value.unwrap_or_default();
-            if !transform.rotation.is_normalized() {
-                if transform.rotation.length_squared() == 0.0 {
-                    transform.rotation = godot::prelude::Quaternion::default();
-                } else {
-                    transform.rotation = transform.rotation.normalized();
-                }
-            }
-
-            if !transform.rotation.is_finite() {
-                transform.rotation = godot::prelude::Quaternion::default();
-            }
-
-            node_3d.set_transform(transform.to_godot_transform_3d_without_scaled());
-            if transform.scale.x.is_zero_approx() {
-                transform.scale.x = 0.00001;
-            }
-            if transform.scale.y.is_zero_approx() {
-                transform.scale.y = 0.00001;
-            }
-            if transform.scale.z.is_zero_approx() {
-                transform.scale.z = 0.00001;
-            }
-            node_3d.set_scale(transform.scale);
+            let transform = value.unwrap_or_default();
+            set_node_3d_transform(&mut node_3d, &transform);
 
             godot_entity_node.desired_parent_3d = transform.parent;
             if godot_entity_node.desired_parent_3d != old_parent {
@@ -1,39 +1,141 @@
 use std::time::Duration;
 
-use godot::builtin::Basis;
+use godot::{
+    engine::{MeshInstance3D, StandardMaterial3D},
+    prelude::*,
+};
 
 use crate::{
     dcl::{
         components::{
             proto_components::sdk::components::{
-                pb_tween::Mode, EasingFunction, PbTween, PbTweenState, TweenStateStatus,
+                pb_tween::Mode, EasingFunction, PbTween, PbTweenSequence, PbTweenState,
+                TextureMovementType, TweenLoop, TweenStateStatus,
             },
             transform_and_parent::DclTransformAndParent,
-            SceneComponentId,
+            SceneComponentId, SceneEntityId,
         },
         crdt::{
             last_write_wins::LastWriteWinsComponentOperation, InsertIfNotExists, SceneCrdtState,
             SceneCrdtStateProtoComponents,
         },
     },
-    scene_runner::scene::Scene,
+    scene_runner::{godot_dcl_scene::GodotDclScene, scene::Scene},
 };
 
+// Set in the materials copied to be modified by a texture tween
+const TEXTURE_TWEEN_META: &str = "dcl_texture_tween";
+
+pub struct TextureUv {
+    pub movement_type: TextureMovementType,
+    pub value: Vector2,
+}
+
 pub struct Tween {
+    // the value of the Tween component
+    pub base: PbTween,
+    // the tween of the current step
     pub data: PbTween,
     pub ease_fn: fn(f32) -> f32,
     pub start_time: std::time::Instant,
     pub paused_time: Option<std::time::Instant>,
     pub playing: Option<bool>,
+    pub completed: bool,
+    // 0 is the Tween component, the next ones are the tweens of the TweenSequence
+    pub step: usize,
+    // the step is played from the end to the start (yoyo loop)
+    pub backwards: bool,
+    // last value of a texture tween, applied to the material once it's ready
+    pub texture_uv: Option<TextureUv>,
+    pub texture_uv_applied: bool,
 }
 
 impl Tween {
+    fn new(data: PbTween, start_time: std::time::Instant) -> Self {
+        Self {
+            ease_fn: get_ease_fn(data.easing_function),
+            base: data.clone(),
+            data,
+            start_time,
+            paused_time: None,
+            playing: None,
+            completed: false,
+            step: 0,
+            backwards: false,
+            texture_uv: None,
+            texture_uv_applied: false,
+        }
+    }
+
     fn get_progress(&self, elapsed_time: Duration) -> f32 {
         elapsed_time.as_millis() as f32 / self.data.duration // 0 to 1...
     }
+
+    fn set_data(&mut self, data: PbTween) {
+        self.ease_fn = get_ease_fn(data.easing_function);
+        self.data = data;
+    }
+
+    fn start_step(&mut self, step: usize, backwards: bool, start_time: std::time::Instant) {
+        self.step = step;
+        self.backwards = backwards;
+        self.start_time = start_time;
+        self.completed = false;
+    }
+
+    // Moves to the next tween of the sequence, returns false when there is none
+    fn next_step(&mut self, sequence: Option<&PbTweenSequence>) -> bool {
+        let Some(sequence) = sequence else {
+            return false;
+        };
+
+        let tween_loop = sequence.r#loop.and_then(TweenLoop::from_i32);
+        let last_step = sequence.sequence.len();
+        let (step, backwards) = if self.backwards {
+            if self.step > 0 {
+                (self.step - 1, true)
+            } else if tween_loop == Some(TweenLoop::TlYoyo) {
+                (0, false)
+            } else {
+                return false;
+            }
+        } else if self.step < last_step {
+            (self.step + 1, false)
+        } else {
+            match tween_loop {
+                Some(TweenLoop::TlRestart) => (0, false),
+                Some(TweenLoop::TlYoyo) => (last_step, true),
+                None => return false,
+            }
+        };
+
+        let mut data = if step == 0 {
+            self.base.clone()
+        } else {
+            let Some(data) = sequence.sequence.get(step - 1) else {
+                return false;
+            };
+            data.clone()
+        };
+        // the sequence is paused and resumed with the Tween component
+        data.playing = self.base.playing;
+
+        let duration = Duration::from_millis(self.data.duration as u64);
+        self.set_data(data);
+        self.start_step(step, backwards, self.start_time + duration);
+        true
+    }
+
+    // A TweenSequence set after the tween completed plays its next step from now
+    fn resume_sequence(&mut self, sequence: &PbTweenSequence, now: std::time::Instant) {
+        if self.completed && self.next_step(Some(sequence)) {
+            self.start_time = now;
+        }
+    }
 }
 
-fn get_ease_fn(ease_type: EasingFunction) -> fn(f32) -> f32 {
+fn get_ease_fn(easing_function: i32) -> fn(f32) -> f32 {
+    let ease_type = EasingFunction::from_i32(easing_function).unwrap_or(EasingFunction::EfLinear);
     match ease_type {
         EasingFunction::EfLinear => simple_easing::linear,
         EasingFunction::EfEaseinquad => simple_easing::quad_in,
@@ -72,11 +174,30 @@ fn get_ease_fn(ease_type: EasingFunction) -> fn(f32) -> f32 {
 pub fn update_tween(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
     let dirty_lww_components = &scene.current_dirty.lww_components;
     let tween_component = SceneCrdtStateProtoComponents::get_tween(crdt_state);
+    let tween_sequence_component = SceneCrdtStateProtoComponents::get_tween_sequence(crdt_state);
 
     let now = std::time::Instant::now();
 
     let mut tweens_to_delete = Vec::new();
 
+    if let Some(tween_sequence_dirty) = dirty_lww_components.get(&SceneComponentId::TWEEN_SEQUENCE)
+    {
+        for entity in tween_sequence_dirty {
+            let new_value = tween_sequence_component
+                .get(entity)
+                .and_then(|value| value.value.clone());
+
+            if let Some(new_value) = new_value {
+                if let Some(tween) = scene.tweens.get_mut(entity) {
+                    tween.resume_sequence(&new_value, now);
+                }
+                scene.tween_sequences.insert(*entity, new_value);
+            } else {
+                scene.tween_sequences.remove(entity);
+            }
+        }
+    }
+
     if let Some(tween_dirty) = dirty_lww_components.get(&SceneComponentId::TWEEN) {
         for entity in tween_dirty {
             let new_value = tween_component.get(entity);
@@ -101,7 +222,7 @@ pub fn update_tween(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
                 if let Some(existing_tween) = existing {
                     // update tween
 
-                    if existing_tween.data.playing != new_value.playing
+                    if existing_tween.base.playing != new_value.playing
                         && new_value.playing != Some(false)
                     {
                         if let Some(paused_time) = existing_tween.paused_time {
@@ -113,18 +234,20 @@ pub fn update_tween(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
                     }
 
                     // reset tween when the mode changes, or we have a new current time
-                    let reset_tween = existing_tween.data.mode != new_value.mode
+                    let reset_tween = existing_tween.base.mode != new_value.mode
                         || new_value.current_time.is_some();
+
+                    existing_tween.base = new_value.clone();
                     if reset_tween {
-                        existing_tween.start_time = now - offset_time;
+                        // the sequence starts again from the Tween component
+                        existing_tween.set_data(new_value);
+                        existing_tween.start_step(0, false, now - offset_time);
+                    } else if existing_tween.step == 0 {
+                        // copy new tween values
+                        existing_tween.set_data(new_value);
+                    } else {
+                        existing_tween.data.playing = new_value.playing;
                     }
-
-                    // copy new tween values
-                    existing_tween.data.current_time = new_value.current_time;
-                    existing_tween.data.mode = new_value.mode;
-                    existing_tween.data.duration = new_value.duration;
-                    existing_tween.data.easing_function = new_value.easing_function;
-                    existing_tween.data.playing = new_value.playing;
                 } else {
                     // new tween
                     let paused_time = if new_value.playing == Some(false) {
@@ -133,18 +256,9 @@ pub fn update_tween(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
                         None
                     };
 
-                    scene.tweens.insert(
-                        *entity,
-                        Tween {
-                            ease_fn: get_ease_fn(
-                                EasingFunction::from_i32(new_value.easing_function).unwrap(),
-                            ),
-                            data: new_value,
-                            start_time: now - offset_time,
-                            paused_time,
-                            playing: None,
-                        },
-                    );
+                    let mut tween = Tween::new(new_value, now - offset_time);
+                    tween.paused_time = paused_time;
+                    scene.tweens.insert(*entity, tween);
                 };
             }
         }
@@ -158,7 +272,7 @@ pub fn update_tween(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
     }
 
     for (entity, tween) in &mut scene.tweens {
-        if tween.playing == Some(false) {
+        if tween.playing == Some(false) || tween.completed {
             continue;
         }
 
@@ -168,7 +282,6 @@ pub fn update_tween(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
         let duration = std::time::Duration::from_millis(tween.data.duration as u64);
 
         let progress = if elapsed_time >= duration {
-            tween.playing = Some(false);
             current_tween_state = TweenStateStatus::TsCompleted;
             1.0 // finished
         } else {
@@ -182,7 +295,7 @@ pub fn update_tween(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
             tween.paused_time = Some(now);
         }
 
-        // update tween state
+        // update tween state, each step of a sequence is reported as completed
         SceneCrdtStateProtoComponents::get_tween_state_mut(crdt_state).put(
             *entity,
             Some(PbTweenState {
@@ -196,64 +309,30 @@ pub fn update_tween(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
             continue;
         }
 
-        // get entity transform from crdt state
-        let mut transform: DclTransformAndParent = crdt_state
-            .get_transform_mut()
-            .get(entity)
-            .and_then(|transform| transform.value.clone())
-            .unwrap_or_default();
-
-        // calculate new transform with the tween
-        let ease_value = (tween.ease_fn)(progress);
-        let new_transform = match &tween.data.mode {
-            Some(Mode::Move(data)) => {
-                let start = data.start.clone().unwrap().to_godot();
-                let end = data.end.clone().unwrap().to_godot();
-
-                if data.face_direction == Some(true) {
-                    let direction = (end - start).normalized();
-                    let basis = if direction.is_zero_approx() {
-                        Basis::IDENTITY
-                    } else {
-                        let v_x = godot::builtin::Vector3::UP.cross(direction);
-                        let v_x = if v_x.is_zero_approx() {
-                            // same workaround as bevy-explorer
-                            // when the direction is colinear to the up vector, we use the forward vector as up+
-                            godot::builtin::Vector3::FORWARD.cross(direction)
-                        } else {
-                            v_x
-                        }
-                        .normalized();
-                        let v_y = direction.cross(v_x);
-
-                        let mut basis = Basis::IDENTITY;
-                        basis.set_col_a(v_x);
-                        basis.set_col_b(v_y);
-                        basis.set_col_c(direction);
-                        basis
-                    };
+        // calculate new value with the tween, the yoyo loop plays the steps backwards
+        let ease_value = if tween.backwards {
+            (tween.ease_fn)(1.0 - progress)
+        } else {
+            (tween.ease_fn)(progress)
+        };
+        let new_transform = get_tween_transform(&tween.data, ease_value, entity, crdt_state);
+
+        if let Some(Mode::TextureMove(data)) = &tween.data.mode {
+            let start = data.start.clone().unwrap_or_default().to_godot();
+            let end = data.end.clone().unwrap_or_default().to_godot();
+            tween.texture_uv = Some(TextureUv {
+                movement_type: data.movement_type(),
+                value: start + ((end - start) * ease_value),
+            });
+            tween.texture_uv_applied = false;
+        }
 
-                    transform.rotation = basis.to_quat();
-                }
+        if current_tween_state == TweenStateStatus::TsCompleted {
+            tween.completed = !tween.next_step(scene.tween_sequences.get(entity));
+        }
 
-                transform.translation = start + ((end - start) * ease_value);
-                transform
-            }
-            Some(Mode::Rotate(data)) => {
-                let start = data.start.clone().unwrap().to_godot();
-                let end = data.end.clone().unwrap().to_godot();
-                transform.rotation = start + ((end - start) * ease_value);
-                transform
-            }
-            Some(Mode::Scale(data)) => {
-                let start = data.start.clone().unwrap().to_godot();
-                let end = data.end.clone().unwrap().to_godot();
-                transform.scale = start + ((end - start) * ease_value);
-                transform
-            }
-            _ => {
-                continue;
-            }
+        let Some(new_transform) = new_transform else {
+            continue;
         };
 
         // set new transform to the entity
@@ -275,4 +354,233 @@ pub fn update_tween(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
                 .insert(SceneComponentId::TRANSFORM, vec![*entity]);
         }
     }
+
+    // the texture tweens wait until the materials have their textures, so the copy
+    //  of the material gets them too
+    if !scene.dirty_materials {
+        for (entity, tween) in scene.tweens.iter_mut() {
+            if tween.texture_uv_applied {
+                continue;
+            }
+            let Some(texture_uv) = tween.texture_uv.as_ref() else {
+                continue;
+            };
+            tween.texture_uv_applied = apply_texture_uv(&scene.godot_dcl_scene, entity, texture_uv);
+        }
+    }
+}
+
+fn get_tween_transform(
+    data: &PbTween,
+    ease_value: f32,
+    entity: &SceneEntityId,
+    crdt_state: &mut SceneCrdtState,
+) -> Option<DclTransformAndParent> {
+    // get entity transform from crdt state
+    let mut transform: DclTransformAndParent = crdt_state
+        .get_transform_mut()
+        .get(entity)
+        .and_then(|transform| transform.value.clone())
+        .unwrap_or_default();
+
+    match &data.mode {
+        Some(Mode::Move(data)) => {
+            let start = data.start.clone().unwrap().to_godot();
+            let end = data.end.clone().unwrap().to_godot();
+
+            if data.face_direction == Some(true) {
+                let direction = (end - start).normalized();
+                let basis = if direction.is_zero_approx() {
+                    Basis::IDENTITY
+                } else {
+                    let v_x = godot::builtin::Vector3::UP.cross(direction);
+                    let v_x = if v_x.is_zero_approx() {
+                        // same workaround as bevy-explorer
+                        // when the direction is colinear to the up vector, we use the forward vector as up+
+                        godot::builtin::Vector3::FORWARD.cross(direction)
+                    } else {
+                        v_x
+                    }
+                    .normalized();
+                    let v_y = direction.cross(v_x);
+
+                    let mut basis = Basis::IDENTITY;
+                    basis.set_col_a(v_x);
+                    basis.set_col_b(v_y);
+                    basis.set_col_c(direction);
+                    basis
+                };
+
+                transform.rotation = basis.to_quat();
+            }
+
+            transform.translation = start + ((end - start) * ease_value);
+            Some(transform)
+        }
+        Some(Mode::Rotate(data)) => {
+            let start = data.start.clone().unwrap().to_godot();
+            let end = data.end.clone().unwrap().to_godot();
+            transform.rotation = start + ((end - start) * ease_value);
+            Some(transform)
+        }
+        Some(Mode::Scale(data)) => {
+            let start = data.start.clone().unwrap().to_godot();
+            let end = data.end.clone().unwrap().to_godot();
+            transform.scale = start + ((end - start) * ease_value);
+            Some(transform)
+        }
+        _ => None,
+    }
+}
+
+// The materials are shared between the entities, the entity gets its own copy
+//  before the uv is modified
+fn apply_texture_uv(
+    godot_dcl_scene: &GodotDclScene,
+    entity: &SceneEntityId,
+    texture_uv: &TextureUv,
+) -> bool {
+    let Some(node_3d) = godot_dcl_scene.get_node_3d(entity) else {
+        return false;
+    };
+    let Some(mut mesh_renderer) =
+        node_3d.try_get_node_as::<MeshInstance3D>(NodePath::from("MeshRenderer"))
+    else {
+        return false;
+    };
+    let Some(material) = mesh_renderer
+        .get_surface_override_material(0)
+        .and_then(|material| material.try_cast::<StandardMaterial3D>().ok())
+    else {
+        return false;
+    };
+
+    let mut material = if material.has_meta(TEXTURE_TWEEN_META.into()) {
+        material
+    } else {
+        let Some(mut material) = material
+            .duplicate()
+            .and_then(|material| material.try_cast::<StandardMaterial3D>().ok())
+        else {
+            return false;
+        };
+        material.set_meta(TEXTURE_TWEEN_META.into(), true.to_variant());
+        mesh_renderer.set_surface_override_material(0, material.clone().upcast());
+        material
+    };
+
+    let value = texture_uv.value;
+    match texture_uv.movement_type {
+        TextureMovementType::TmtOffset => {
+            material.set_uv1_offset(Vector3::new(value.x, value.y, 0.0));
+        }
+        TextureMovementType::TmtTiling => {
+            material.set_uv1_scale(Vector3::new(value.x, value.y, 1.0));
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tween_with_duration(duration: f32) -> PbTween {
+        PbTween {
+            duration,
+            ..Default::default()
+        }
+    }
+
+    fn sequence(tween_loop: Option<TweenLoop>) -> PbTweenSequence {
+        PbTweenSequence {
+            sequence: vec![tween_with_duration(2.0), tween_with_duration(3.0)],
+            r#loop: tween_loop.map(|tween_loop| tween_loop as i32),
+        }
+    }
+
+    fn steps(tween: &mut Tween, sequence: &PbTweenSequence, count: usize) -> Vec<(usize, bool)> {
+        (0..count)
+            .map_while(|_| {
+                tween
+                    .next_step(Some(sequence))
+                    .then_some((tween.step, tween.backwards))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sequence_without_loop() {
+        let mut tween = Tween::new(tween_with_duration(1.0), std::time::Instant::now());
+        let sequence = sequence(None);
+
+        assert_eq!(
+            steps(&mut tween, &sequence, 5),
+            vec![(1, false), (2, false)]
+        );
+        assert_eq!(tween.data.duration, 3.0);
+        assert!(!tween.next_step(None));
+    }
+
+    #[test]
+    fn test_sequence_restart_loop() {
+        let mut tween = Tween::new(tween_with_duration(1.0), std::time::Instant::now());
+        let sequence = sequence(Some(TweenLoop::TlRestart));
+
+        assert_eq!(
+            steps(&mut tween, &sequence, 4),
+            vec![(1, false), (2, false), (0, false), (1, false)]
+        );
+        assert_eq!(tween.data.duration, 2.0);
+    }
+
+    #[test]
+    fn test_sequence_yoyo_loop() {
+        let mut tween = Tween::new(tween_with_duration(1.0), std::time::Instant::now());
+        let sequence = sequence(Some(TweenLoop::TlYoyo));
+
+        assert_eq!(
+            steps(&mut tween, &sequence, 6),
+            vec![
+                (1, false),
+                (2, false),
+                (2, true),
+                (1, true),
+                (0, true),
+                (0, false)
+            ]
+        );
+        assert_eq!(tween.data.duration, 1.0);
+    }
+
+    #[test]
+    fn test_sequence_keeps_timing() {
+        let start_time = std::time::Instant::now();
+        let mut tween = Tween::new(tween_with_duration(1000.0), start_time);
+        let sequence = sequence(None);
+
+        assert!(tween.next_step(Some(&sequence)));
+        assert_eq!(tween.start_time, start_time + Duration::from_millis(1000));
+        assert!(!tween.completed);
+    }
+
+    #[test]
+    fn test_sequence_set_after_completed() {
+        let start_time = std::time::Instant::now();
+        let mut tween = Tween::new(tween_with_duration(1000.0), start_time);
+        // the tween finished without sequence
+        tween.completed = !tween.next_step(None);
+        assert!(tween.completed);
+
+        let now = start_time + Duration::from_millis(5000);
+        tween.resume_sequence(&sequence(None), now);
+        assert!(!tween.completed);
+        assert_eq!(tween.step, 1);
+        assert_eq!(tween.start_time, now);
+
+        // a running tween is not affected
+        tween.resume_sequence(&sequence(None), now + Duration::from_millis(10));
+        assert_eq!(tween.step, 1);
+        assert_eq!(tween.start_time, now);
+    }
 }
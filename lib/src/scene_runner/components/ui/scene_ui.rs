@@ -10,6 +10,7 @@ use godot::{
         NodeExt,
     },
     obj::Gd,
+    prelude::{Vector2, Vector2i},
 };
 
 use crate::{
@@ -32,6 +33,7 @@ use crate::{
             ui_background::update_ui_background, ui_text::update_ui_text,
             ui_transform::update_ui_transform,
         },
+        godot_dcl_scene::GodotDclScene,
         scene::{Scene, SceneType},
     },
 };
@@ -237,7 +239,12 @@ fn update_layout(
             .get_mut(&ui_node.ui_transform.parent)
             .expect("parent not found, it was processed before");
 
-        if let Some(parent) = godot_dcl_scene.get_node_ui(&ui_node.ui_transform.parent) {
+        // the entities rendered into a texture are not children of their parent control
+        let in_ui_texture = godot_dcl_scene.ui_textures.contains_key(entity);
+        if let Some(parent) = godot_dcl_scene
+            .get_node_ui(&ui_node.ui_transform.parent)
+            .filter(|_| !in_ui_texture)
+        {
             parent.base_control.clone().move_child(
                 ui_node.base_control.clone().upcast(),
                 parent_node.1 + parent.control_offset(),
@@ -263,6 +270,33 @@ fn update_layout(
     }
 }
 
+// Moves the subtree of the UI entities used as texture into their viewports, the viewport
+//  takes the size computed by the layout
+fn update_ui_textures(godot_dcl_scene: &mut GodotDclScene) {
+    for (entity, viewport) in godot_dcl_scene.ui_textures.iter() {
+        let Some(ui_node) = godot_dcl_scene.get_node_ui(entity) else {
+            continue;
+        };
+
+        let mut control = ui_node.base_control.clone();
+        let mut viewport = viewport.clone();
+        let is_in_viewport = control
+            .get_parent()
+            .map(|parent| parent == viewport.clone().upcast::<godot::engine::Node>())
+            .unwrap_or(false);
+        if !is_in_viewport {
+            control.reparent(viewport.clone().upcast());
+        }
+
+        let size = control.get_size();
+        control.set_position(Vector2::ZERO);
+        viewport.set_size(Vector2i::new(
+            (size.x.round() as i32).max(1),
+            (size.y.round() as i32).max(1),
+        ));
+    }
+}
+
 pub fn update_scene_ui(
     scene: &mut Scene,
     crdt_state: &mut SceneCrdtState,
@@ -310,6 +344,9 @@ pub fn update_scene_ui(
                 });
             }
         }
+
+        // the textures are still rendered in the 3d world
+        update_ui_textures(&mut scene.godot_dcl_scene);
         return;
     } else if !scene.godot_dcl_scene.hidden_dirty.is_empty() {
         for component_id in UI_COMPONENT_IDS {
@@ -361,6 +398,9 @@ pub fn update_scene_ui(
 
         update_input_result(scene, crdt_state);
     }
+
+    // it runs even without UI changes, the material step can add new textures
+    update_ui_textures(&mut scene.godot_dcl_scene);
 }
 
 fn update_input_result(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
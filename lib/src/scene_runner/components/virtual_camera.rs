@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use crate::{
+    dcl::{
+        components::{
+            proto_components::sdk::components::{
+                common::camera_transition::TransitionMode, PbVirtualCamera,
+            },
+            SceneComponentId, SceneEntityId,
+        },
+        crdt::{
+            last_write_wins::LastWriteWinsComponentOperation, SceneCrdtState,
+            SceneCrdtStateProtoComponents,
+        },
+        SceneId,
+    },
+    scene_runner::scene::Scene,
+};
+use godot::prelude::*;
+
+pub fn update_virtual_camera(scene: &mut Scene, crdt_state: &mut SceneCrdtState) {
+    let dirty_lww_components = &scene.current_dirty.lww_components;
+
+    if let Some(virtual_camera_dirty) = dirty_lww_components.get(&SceneComponentId::VIRTUAL_CAMERA)
+    {
+        let virtual_camera_component =
+            SceneCrdtStateProtoComponents::get_virtual_camera(crdt_state);
+        for entity in virtual_camera_dirty {
+            let new_value = virtual_camera_component
+                .get(entity)
+                .and_then(|entry| entry.value.clone());
+
+            if let Some(new_value) = new_value {
+                scene.virtual_cameras.insert(*entity, new_value);
+            } else {
+                scene.virtual_cameras.remove(entity);
+            }
+        }
+    }
+
+    if let Some(main_camera_dirty) = dirty_lww_components.get(&SceneComponentId::MAIN_CAMERA) {
+        // the main camera is only read from the camera entity
+        if main_camera_dirty.contains(&SceneEntityId::CAMERA) {
+            scene.main_camera_entity = SceneCrdtStateProtoComponents::get_main_camera(crdt_state)
+                .get(&SceneEntityId::CAMERA)
+                .and_then(|entry| entry.value.as_ref())
+                .and_then(|main_camera| main_camera.virtual_camera_entity)
+                .map(|entity| SceneEntityId::from_i32(entity as i32));
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum CameraTransition {
+    Time(f32),
+    Speed(f32),
+}
+
+impl CameraTransition {
+    fn from_virtual_camera(virtual_camera: &PbVirtualCamera) -> Option<Self> {
+        match virtual_camera
+            .default_transition
+            .as_ref()?
+            .transition_mode
+            .as_ref()?
+        {
+            TransitionMode::Time(time) => Some(Self::Time(*time)),
+            TransitionMode::Speed(speed) => Some(Self::Speed(*speed)),
+        }
+    }
+
+    // `distance` in meters, and speed in meters per second
+    fn duration(transition: Option<Self>, distance: f32) -> f32 {
+        match transition {
+            Some(Self::Time(time)) => time.max(0.0),
+            Some(Self::Speed(speed)) if speed > 0.0 => distance / speed,
+            _ => 0.0,
+        }
+    }
+}
+
+type ActiveVirtualCamera = (SceneId, SceneEntityId);
+
+// Drives a cinematic camera from the virtual camera selected by the MainCamera component
+//  of the scene where the player is. The transitions blend from the camera that was
+//  rendering, and when the MainCamera is cleared, it blends back to the player camera
+pub struct VirtualCameraController {
+    camera: Gd<Camera3D>,
+    active: Option<ActiveVirtualCamera>,
+    // transition used to return to the player camera
+    last_transition: Option<CameraTransition>,
+    blend_from: Transform3D,
+    blend_duration: f32,
+    blend_elapsed: f32,
+    returning_to_player: bool,
+}
+
+impl Default for VirtualCameraController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VirtualCameraController {
+    pub fn new() -> Self {
+        let mut camera = Camera3D::new_alloc();
+        camera.set_name("VirtualCamera".into());
+        Self {
+            camera,
+            active: None,
+            last_transition: None,
+            blend_from: Transform3D::IDENTITY,
+            blend_duration: 0.0,
+            blend_elapsed: 0.0,
+            returning_to_player: false,
+        }
+    }
+
+    pub fn get_camera(&self) -> Gd<Camera3D> {
+        self.camera.clone()
+    }
+
+    pub fn is_controlling_camera(&self) -> bool {
+        self.active.is_some() || self.returning_to_player
+    }
+
+    fn get_target_transform(
+        scene: &Scene,
+        entity: &SceneEntityId,
+        virtual_camera: &PbVirtualCamera,
+        player_global_transform: &Transform3D,
+    ) -> Option<Transform3D> {
+        let node_3d = scene.godot_dcl_scene.get_node_3d(entity)?;
+        let global_transform = node_3d.get_global_transform();
+        let origin = global_transform.origin;
+
+        let look_at_position = virtual_camera
+            .look_at_entity
+            .map(|entity| SceneEntityId::from_i32(entity as i32))
+            .and_then(|look_at_entity| {
+                if look_at_entity == SceneEntityId::PLAYER {
+                    Some(player_global_transform.origin)
+                } else {
+                    scene
+                        .godot_dcl_scene
+                        .get_node_3d(&look_at_entity)
+                        .map(|node| node.get_global_position())
+                }
+            });
+
+        let basis = match look_at_position {
+            Some(look_at_position) if !look_at_position.is_equal_approx(origin) => {
+                Basis::new_looking_at(look_at_position - origin, Vector3::UP, false)
+            }
+            _ => global_transform.basis.orthonormalized(),
+        };
+
+        Some(Transform3D { basis, origin })
+    }
+
+    // Returns the global transform of the camera that is rendering
+    pub fn process(
+        &mut self,
+        delta: f32,
+        scenes: &HashMap<SceneId, Scene>,
+        current_parcel_scene_id: &SceneId,
+        player_camera: &mut Gd<Camera3D>,
+        player_global_transform: &Transform3D,
+    ) -> Transform3D {
+        let player_camera_transform = player_camera.get_global_transform();
+
+        let desired = scenes.get(current_parcel_scene_id).and_then(|scene| {
+            let entity = scene.main_camera_entity?;
+            let virtual_camera = scene.virtual_cameras.get(&entity)?;
+            let target_transform = Self::get_target_transform(
+                scene,
+                &entity,
+                virtual_camera,
+                player_global_transform,
+            )?;
+            Some((
+                (*current_parcel_scene_id, entity),
+                virtual_camera,
+                target_transform,
+            ))
+        });
+
+        let current_transform = if self.is_controlling_camera() {
+            self.camera.get_global_transform()
+        } else {
+            player_camera_transform
+        };
+
+        let desired_active = desired.as_ref().map(|(active, _, _)| *active);
+        if desired_active != self.active {
+            self.blend_from = current_transform;
+            self.blend_elapsed = 0.0;
+
+            if let Some((active, virtual_camera, target_transform)) = desired.as_ref() {
+                let transition = CameraTransition::from_virtual_camera(virtual_camera);
+                let distance = current_transform
+                    .origin
+                    .distance_to(target_transform.origin);
+                self.blend_duration = CameraTransition::duration(transition, distance);
+                self.last_transition = transition;
+                self.active = Some(*active);
+                self.returning_to_player = false;
+
+                if !self.camera.is_current() {
+                    self.camera.set_fov(player_camera.get_fov());
+                    self.camera.set_global_transform(current_transform);
+                    self.camera.make_current();
+                }
+            } else {
+                let distance = current_transform
+                    .origin
+                    .distance_to(player_camera_transform.origin);
+                self.blend_duration = CameraTransition::duration(self.last_transition, distance);
+                self.active = None;
+                self.returning_to_player = true;
+            }
+        }
+
+        if !self.is_controlling_camera() {
+            return player_camera_transform;
+        }
+
+        let target_transform = desired
+            .map(|(_, _, target_transform)| target_transform)
+            .unwrap_or(player_camera_transform);
+
+        self.blend_elapsed += delta;
+        let weight = if self.blend_duration > 0.0 {
+            (self.blend_elapsed / self.blend_duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let transform = if weight >= 1.0 {
+            target_transform
+        } else {
+            let from_rotation = self.blend_from.basis.to_quat();
+            let to_rotation = target_transform.basis.to_quat();
+            Transform3D {
+                basis: Basis::from_quat(from_rotation.slerp(to_rotation, weight)),
+                origin: self.blend_from.origin.lerp(target_transform.origin, weight),
+            }
+        };
+
+        if self.returning_to_player && weight >= 1.0 {
+            self.returning_to_player = false;
+            self.last_transition = None;
+            player_camera.make_current();
+            return player_camera_transform;
+        }
+
+        self.camera.set_global_transform(transform);
+        transform
+    }
+}
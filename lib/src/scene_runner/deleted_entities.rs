@@ -35,6 +35,7 @@ pub fn update_deleted_entities(scene: &mut Scene) {
         }
 
         godot_dcl_scene.entities.remove(deleted_entity);
+        godot_dcl_scene.remove_ui_texture(deleted_entity);
 
         scene.audio_sources.remove(deleted_entity);
         scene.audio_streams.remove(deleted_entity);
@@ -42,6 +43,9 @@ pub fn update_deleted_entities(scene: &mut Scene) {
         scene.dup_animator.remove(deleted_entity);
         scene.gltf_loading.remove(deleted_entity);
         scene.continuos_raycast.remove(deleted_entity);
+        scene.avatar_attaches.remove(deleted_entity);
+        scene.tween_sequences.remove(deleted_entity);
+        scene.data_saver_skipped_videos.remove(deleted_entity);
 
         scene.pointer_events_result = scene
             .pointer_events_result
@@ -9,7 +9,10 @@ use crate::{
     },
     realm::scene_definition::SceneEntityDefinition,
 };
-use godot::{engine::GdScript, prelude::*};
+use godot::{
+    engine::{GdScript, SubViewport},
+    prelude::*,
+};
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
@@ -35,6 +38,9 @@ pub struct GodotDclScene {
     pub ui_entities: HashSet<SceneEntityId>,
     pub hidden_dirty: HashMap<SceneComponentId, HashSet<SceneEntityId>>,
     pub ui_visible: bool,
+    // UI entities rendered into a texture (UiTexture of the materials), the subtree of the
+    //  entity is moved into the viewport
+    pub ui_textures: HashMap<SceneEntityId, Gd<SubViewport>>,
 
     pub ui_results: Rc<RefCell<UiResults>>,
 }
@@ -136,6 +142,7 @@ impl GodotDclScene {
             ui_entities: HashSet::new(),
             hidden_dirty: HashMap::new(),
             ui_visible: false,
+            ui_textures: HashMap::new(),
             parent_node_ui,
             ui_results: UiResults::new_shared(),
         }
@@ -256,6 +263,40 @@ impl GodotDclScene {
         (godot_entity_node, control)
     }
 
+    pub fn ensure_ui_texture(&mut self, entity: &SceneEntityId) -> Gd<SubViewport> {
+        if let Some(viewport) = self.ui_textures.get(entity) {
+            return viewport.clone();
+        }
+
+        let mut viewport = SubViewport::new_alloc();
+        viewport.set_name(GString::from(format!("ui_texture_{}", entity.as_i32())));
+        viewport.set_transparent_background(true);
+        viewport.set_size(Vector2i::new(1, 1));
+        self.root_node_3d.add_child(viewport.clone().upcast());
+        self.ui_textures.insert(*entity, viewport.clone());
+        viewport
+    }
+
+    // The control goes back to its parent, the UI layout is dirtied to place it again
+    pub fn remove_ui_texture(&mut self, entity: &SceneEntityId) {
+        let Some(mut viewport) = self.ui_textures.remove(entity) else {
+            return;
+        };
+
+        if let Some(ui_node) = self.get_node_ui(entity) {
+            let mut control = ui_node.base_control.clone();
+            if let Some(parent) = self.get_node_ui(&ui_node.ui_transform.parent) {
+                control.reparent(parent.base_control.clone().upcast());
+            }
+            self.hidden_dirty
+                .entry(SceneComponentId::UI_TRANSFORM)
+                .or_default()
+                .insert(*entity);
+        }
+
+        viewport.queue_free();
+    }
+
     #[allow(dead_code)]
     pub fn exist_node(&self, entity: &SceneEntityId) -> bool {
         self.entities.contains_key(entity)
@@ -14,8 +14,9 @@ use crate::{
             internal_player_data::InternalPlayerData,
             material::DclMaterial,
             proto_components::sdk::components::{
-                common::RaycastHit, PbAnimator, PbAvatarBase, PbAvatarEmoteCommand,
-                PbAvatarEquippedData, PbPlayerIdentityData, PbPointerEventsResult,
+                common::RaycastHit, PbAnimator, PbAvatarAttach, PbAvatarBase, PbAvatarEmoteCommand,
+                PbAvatarEquippedData, PbPlayerIdentityData, PbPointerEventsResult, PbTweenSequence,
+                PbVirtualCamera,
             },
             transform_and_parent::DclTransformAndParent,
             SceneEntityId,
@@ -31,7 +32,14 @@ use crate::{
     realm::scene_definition::SceneEntityDefinition,
 };
 
-use super::{components::tween::Tween, godot_dcl_scene::GodotDclScene};
+use super::{
+    components::{
+        gltf_mesh::{GltfMeshTarget, PendingGltfMesh},
+        input_modifier::InputModifier,
+        tween::Tween,
+    },
+    godot_dcl_scene::GodotDclScene,
+};
 
 pub struct Dirty {
     pub waiting_process: bool,
@@ -77,6 +85,7 @@ pub enum SceneUpdateState {
     TextShape,
     Billboard,
     MeshCollider,
+    GltfMesh,
     GltfContainer,
     SyncGltfContainer,
     NftShape,
@@ -91,8 +100,11 @@ pub enum SceneUpdateState {
     #[cfg(feature = "use_ffmpeg")]
     AudioStream,
     AvatarModifierArea,
+    InputModifier,
     CameraModeArea,
+    VirtualCamera,
     AudioSource,
+    LightSource,
     ProcessRpcs,
     ComputeCrdtState,
     SendToThread,
@@ -113,7 +125,8 @@ impl SceneUpdateState {
             &Self::Material => Self::TextShape,
             &Self::TextShape => Self::Billboard,
             &Self::Billboard => Self::MeshCollider,
-            &Self::MeshCollider => Self::GltfContainer,
+            &Self::MeshCollider => Self::GltfMesh,
+            &Self::GltfMesh => Self::GltfContainer,
             &Self::GltfContainer => Self::SyncGltfContainer,
             &Self::SyncGltfContainer => Self::NftShape,
             &Self::NftShape => Self::Animator,
@@ -128,9 +141,12 @@ impl SceneUpdateState {
             &Self::AudioStream => Self::AvatarModifierArea,
             #[cfg(not(feature = "use_ffmpeg"))]
             &Self::Raycasts => Self::AvatarModifierArea,
-            &Self::AvatarModifierArea => Self::CameraModeArea,
-            &Self::CameraModeArea => Self::AudioSource,
-            &Self::AudioSource => Self::AvatarAttach,
+            &Self::AvatarModifierArea => Self::InputModifier,
+            &Self::InputModifier => Self::CameraModeArea,
+            &Self::CameraModeArea => Self::VirtualCamera,
+            &Self::VirtualCamera => Self::AudioSource,
+            &Self::AudioSource => Self::LightSource,
+            &Self::LightSource => Self::AvatarAttach,
             &Self::AvatarAttach => Self::SceneUi,
             &Self::SceneUi => Self::ProcessRpcs,
             &Self::ProcessRpcs => Self::ComputeCrdtState,
@@ -198,6 +214,8 @@ pub struct Scene {
     // Used by VideoPlayer and AudioStream
     pub audio_streams: HashMap<SceneEntityId, Gd<DclAudioStream>>,
     pub video_players: HashMap<SceneEntityId, Gd<DclVideoPlayer>>,
+    // Video streams skipped by the data saver, they're started once it's disabled
+    pub data_saver_skipped_videos: HashSet<SceneEntityId>,
 
     pub avatar_scene_updates: SceneAvatarUpdates,
     pub scene_tests: HashMap<String, Option<SceneTestResult>>,
@@ -205,9 +223,26 @@ pub struct Scene {
 
     // Tween
     pub tweens: HashMap<SceneEntityId, Tween>,
+    // The tweens chained after the Tween component of the entity
+    pub tween_sequences: HashMap<SceneEntityId, PbTweenSequence>,
+    // Entities attached to an avatar, their transform is updated every frame
+    pub avatar_attaches: HashMap<SceneEntityId, PbAvatarAttach>,
     // Duplicated value to async-access the animator
     pub dup_animator: HashMap<SceneEntityId, PbAnimator>,
 
+    // Entities with a LightSource and its `active` value, used by the light budget
+    pub light_sources: HashMap<SceneEntityId, bool>,
+
+    // Duplicated values to drive the camera every frame
+    pub virtual_cameras: HashMap<SceneEntityId, PbVirtualCamera>,
+    pub main_camera_entity: Option<SceneEntityId>,
+
+    // Meshes of MeshRenderer and MeshCollider waiting for their gltf to be loaded
+    pub pending_gltf_meshes: HashMap<(SceneEntityId, GltfMeshTarget), PendingGltfMesh>,
+
+    // Duplicated value of the InputModifier of the player entity
+    pub input_modifier: Option<InputModifier>,
+
     pub paused: bool,
 }
 
@@ -293,12 +328,20 @@ impl Scene {
             audio_sources: HashMap::new(),
             audio_streams: HashMap::new(),
             video_players: HashMap::new(),
+            data_saver_skipped_videos: HashSet::new(),
             scene_type,
             avatar_scene_updates: Default::default(),
             scene_tests: HashMap::new(),
             scene_test_plan_received: false,
             tweens: HashMap::new(),
+            tween_sequences: HashMap::new(),
+            avatar_attaches: HashMap::new(),
             dup_animator: HashMap::new(),
+            light_sources: HashMap::new(),
+            virtual_cameras: HashMap::new(),
+            main_camera_entity: None,
+            pending_gltf_meshes: HashMap::new(),
+            input_modifier: None,
             paused: false,
         }
     }
@@ -355,11 +398,19 @@ impl Scene {
             audio_sources: HashMap::new(),
             audio_streams: HashMap::new(),
             video_players: HashMap::new(),
+            data_saver_skipped_videos: HashSet::new(),
             avatar_scene_updates: Default::default(),
             scene_tests: HashMap::new(),
             scene_test_plan_received: false,
             tweens: HashMap::new(),
+            tween_sequences: HashMap::new(),
+            avatar_attaches: HashMap::new(),
             dup_animator: HashMap::new(),
+            light_sources: HashMap::new(),
+            virtual_cameras: HashMap::new(),
+            main_camera_entity: None,
+            pending_gltf_meshes: HashMap::new(),
+            input_modifier: None,
             paused: false,
         }
     }
@@ -37,7 +37,13 @@ use std::{
 };
 
 use super::{
-    components::pointer_events::{get_entity_pointer_event, pointer_events_system},
+    components::{
+        avatar_attach::update_avatar_attach_transforms,
+        input_modifier::InputModifier,
+        light_source::update_light_budget,
+        pointer_events::{get_entity_pointer_event, pointer_events_system},
+        virtual_camera::VirtualCameraController,
+    },
     input::InputState,
     scene::{
         Dirty, GlobalSceneType, GodotDclRaycastResult, Scene, SceneState, SceneType,
@@ -88,6 +94,8 @@ pub struct SceneManager {
     input_state: InputState,
     last_raycast_result: Option<GodotDclRaycastResult>,
 
+    virtual_camera: VirtualCameraController,
+
     #[export]
     pointer_tooltips: VariantArray,
 }
@@ -236,6 +244,24 @@ impl SceneManager {
         }
     }
 
+    // Restrictions of the scene where the player is, the other scenes can't restrict the input
+    fn get_current_input_modifier(&self) -> InputModifier {
+        self.scenes
+            .get(&self.current_parcel_scene_id)
+            .and_then(|scene| scene.input_modifier)
+            .unwrap_or_default()
+    }
+
+    #[func]
+    fn get_player_input_modifier(&self) -> Dictionary {
+        self.get_current_input_modifier().to_dictionary()
+    }
+
+    #[func]
+    fn is_player_emote_disabled(&self) -> bool {
+        self.get_current_input_modifier().disable_emote
+    }
+
     #[func]
     fn on_primary_player_trigger_emote(&mut self, emote_id: GString, looping: bool) {
         let emote_command = PbAvatarEmoteCommand {
@@ -368,10 +394,17 @@ impl SceneManager {
         if self.camera_node.is_none() {
             return;
         }
-        let camera_node = self.camera_node.clone().unwrap();
+        let mut camera_node = self.camera_node.clone().unwrap();
 
         let player_global_transform = self.player_node.get_global_transform();
-        let camera_global_transform = camera_node.get_global_transform();
+        // the scenes see the camera that is rendering, it can be a virtual camera
+        let camera_global_transform = self.virtual_camera.process(
+            delta as f32,
+            &self.scenes,
+            &self.current_parcel_scene_id,
+            &mut camera_node,
+            &player_global_transform,
+        );
 
         let camera_node = camera_node.try_cast::<DclCamera3D>();
         let camera_mode = if let Ok(camera_node) = camera_node {
@@ -471,6 +504,8 @@ impl SceneManager {
             }
         }
 
+        update_light_budget(&mut self.scenes, &camera_global_transform);
+
         for scene_id in self.dying_scene_ids.iter() {
             let scene = self.scenes.get_mut(scene_id).unwrap();
             match scene.state {
@@ -672,12 +707,18 @@ impl SceneManager {
         self.pause
     }
 
+    // the virtual camera replaces the player camera while a scene is controlling it
+    fn get_rendering_camera(&self) -> Option<Gd<Camera3D>> {
+        if self.virtual_camera.is_controlling_camera() {
+            return Some(self.virtual_camera.get_camera());
+        }
+        self.camera_node.clone()
+    }
+
     fn get_current_mouse_entity(&mut self) -> Option<GodotDclRaycastResult> {
         const RAY_LENGTH: f32 = 100.0;
 
-        self.camera_node.as_ref()?;
-
-        let camera_node = self.camera_node.clone().unwrap();
+        let camera_node = self.get_rendering_camera()?;
 
         let raycast_from = camera_node.project_ray_origin(self.cursor_position);
         let raycast_to =
@@ -848,6 +889,22 @@ impl SceneManager {
         self.scenes.get(scene_id)
     }
 
+    // distance in parcels from the player to the nearest parcel of each scene
+    pub fn get_scene_distances(&self) -> HashMap<SceneId, f32> {
+        self.scenes
+            .iter()
+            .map(|(scene_id, scene)| (*scene_id, scene.distance))
+            .collect()
+    }
+
+    // None until the player node is in the tree
+    pub fn get_player_global_position(&self) -> Option<Vector3> {
+        if !self.player_node.is_inside_tree() {
+            return None;
+        }
+        Some(self.player_node.get_global_position())
+    }
+
     // this could be cached
     pub fn get_global_scene_ids(&self) -> Vec<SceneId> {
         self.scenes
@@ -975,6 +1032,7 @@ impl INode for SceneManager {
             console: Callable::invalid(),
             input_state: InputState::default(),
             last_raycast_result: None,
+            virtual_camera: VirtualCameraController::new(),
             pointer_tooltips: VariantArray::new(),
             interactable_area: Rect2i::from_components(
                 0,
@@ -987,6 +1045,9 @@ impl INode for SceneManager {
     }
 
     fn ready(&mut self) {
+        let virtual_camera = self.virtual_camera.get_camera();
+        self.base.add_child(virtual_camera.upcast());
+
         self.base_ui
             .connect("resized".into(), self.base.callable("_on_ui_resize"));
         self.base_ui.set_name("scenes_ui".into());
@@ -1002,6 +1063,13 @@ impl INode for SceneManager {
     fn process(&mut self, delta: f64) {
         self.scene_runner_update(delta);
 
+        let player_avatar = self
+            .player_node
+            .try_get_node_as::<Node3D>(NodePath::from("Avatar"));
+        for scene in self.scenes.values() {
+            update_avatar_attach_transforms(scene, player_avatar.as_ref());
+        }
+
         let changed_inputs = self.input_state.get_new_inputs();
         let current_pointer_raycast_result = self.get_current_mouse_entity();
 
@@ -1075,10 +1143,9 @@ impl INode for SceneManager {
         self.set_pointer_tooltips(tooltips);
         self.base.emit_signal("pointer_tooltip_changed".into(), &[]);
 
-        if self.camera_node.is_none() {
+        let Some(player_camera_node) = self.get_rendering_camera() else {
             return;
-        }
-        let player_camera_node = self.camera_node.clone().unwrap();
+        };
 
         // This update the mirror node that copies every frame the global transform of the player/camera
         //  every entity attached to the player/camera is really attached to these mirror nodes
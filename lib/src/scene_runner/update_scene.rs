@@ -16,6 +16,9 @@ use super::{
         billboard::update_billboard,
         camera_mode_area::update_camera_mode_area,
         gltf_container::{sync_gltf_loading_state, update_gltf_container},
+        gltf_mesh::update_gltf_meshes,
+        input_modifier::update_input_modifier,
+        light_source::update_light_source,
         material::update_material,
         mesh_collider::update_mesh_collider,
         mesh_renderer::update_mesh_renderer,
@@ -26,6 +29,7 @@ use super::{
         transform_and_parent::update_transform_and_parent,
         tween::update_tween,
         ui::scene_ui::update_scene_ui,
+        virtual_camera::update_virtual_camera,
         visibility::update_visibility,
     },
     deleted_entities::update_deleted_entities,
@@ -34,6 +38,7 @@ use super::{
 };
 use crate::{
     dcl::{
+        common::SceneLogLevel,
         components::{
             proto_components::sdk::components::{
                 PbCameraMode, PbEngineInfo, PbPointerLock, PbUiCanvasInformation,
@@ -196,6 +201,19 @@ pub fn _process_scene(
                 update_mesh_collider(scene, crdt_state);
                 false
             }
+            SceneUpdateState::GltfMesh => {
+                // the gltf meshes of the MeshRenderer and MeshCollider
+                for error in update_gltf_meshes(scene) {
+                    tracing::warn!("scene {:?} {}", scene.scene_id, error);
+                    let mut arguments = VariantArray::new();
+                    arguments.push((scene.scene_id.0).to_variant());
+                    arguments.push((SceneLogLevel::SystemError as i32).to_variant());
+                    arguments.push(scene.start_time.elapsed().as_secs_f32().to_variant());
+                    arguments.push(GString::from(&error).to_variant());
+                    console.callv(arguments);
+                }
+                false
+            }
             SceneUpdateState::GltfContainer => {
                 !update_gltf_container(scene, crdt_state, ref_time, end_time_us)
             }
@@ -240,14 +258,26 @@ pub fn _process_scene(
                 update_avatar_modifier_area(scene, crdt_state);
                 false
             }
+            SceneUpdateState::InputModifier => {
+                update_input_modifier(scene, crdt_state);
+                false
+            }
             SceneUpdateState::CameraModeArea => {
                 update_camera_mode_area(scene, crdt_state);
                 false
             }
+            SceneUpdateState::VirtualCamera => {
+                update_virtual_camera(scene, crdt_state);
+                false
+            }
             SceneUpdateState::AudioSource => {
                 update_audio_source(scene, crdt_state, current_parcel_scene_id);
                 false
             }
+            SceneUpdateState::LightSource => {
+                update_light_source(scene, crdt_state);
+                false
+            }
             SceneUpdateState::SceneUi => {
                 update_scene_ui(
                     scene,
@@ -0,0 +1,510 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use ethers_core::types::H160;
+use godot::prelude::*;
+
+use crate::dcl::components::proto_components::kernel::comms::rfc4;
+
+pub static COMMS_INSPECTOR_ENABLE: AtomicBool = AtomicBool::new(false);
+
+static COMMS_STATS: once_cell::sync::Lazy<Mutex<CommsStats>> =
+    once_cell::sync::Lazy::new(Default::default);
+
+static COMMS_INSPECTOR_START: once_cell::sync::Lazy<Instant> =
+    once_cell::sync::Lazy::new(Instant::now);
+
+const MAX_EVENTS: usize = 500;
+const DEFAULT_EVENT_SAMPLE_RATE: u32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Sent => "sent",
+            Direction::Received => "received",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TrafficCounter {
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PeerStats {
+    pub connected: bool,
+    pub first_seen_ms: u64,
+    pub last_seen_ms: u64,
+    pub packets_received: u64,
+    pub announced_profile_version: Option<u32>,
+    pub profile_version: Option<u32>,
+}
+
+#[derive(Default)]
+pub struct AdapterStats {
+    pub sent: HashMap<&'static str, TrafficCounter>,
+    pub received: HashMap<&'static str, TrafficCounter>,
+    pub peers: HashMap<H160, PeerStats>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommsEvent {
+    pub id: u64,
+    pub time_ms: u64,
+    pub adapter: String,
+    pub direction: Direction,
+    pub message_type: &'static str,
+    pub address: Option<H160>,
+    pub bytes: usize,
+}
+
+pub struct CommsStats {
+    adapters: HashMap<String, AdapterStats>,
+    events: VecDeque<CommsEvent>,
+    last_event_id: u64,
+    // the positions and voice frames are logged one of each `event_sample_rate`
+    event_sample_rate: u32,
+    sample_counters: HashMap<&'static str, u32>,
+}
+
+impl Default for CommsStats {
+    fn default() -> Self {
+        Self {
+            adapters: HashMap::new(),
+            events: VecDeque::new(),
+            last_event_id: 0,
+            event_sample_rate: DEFAULT_EVENT_SAMPLE_RATE,
+            sample_counters: HashMap::new(),
+        }
+    }
+}
+
+impl CommsStats {
+    pub fn record_packet(
+        &mut self,
+        now_ms: u64,
+        adapter: &str,
+        direction: Direction,
+        message: Option<&rfc4::packet::Message>,
+        address: Option<H160>,
+        bytes: usize,
+    ) {
+        let message_type = get_message_type(message);
+        let adapter_stats = self.adapters.entry(adapter.to_string()).or_default();
+        let counters = match direction {
+            Direction::Sent => &mut adapter_stats.sent,
+            Direction::Received => &mut adapter_stats.received,
+        };
+        let counter = counters.entry(message_type).or_default();
+        counter.packets += 1;
+        counter.bytes += bytes as u64;
+
+        if let Some(address) = address {
+            let peer = get_peer(adapter_stats, address, now_ms);
+            peer.last_seen_ms = now_ms;
+            peer.packets_received += 1;
+            if let Some(rfc4::packet::Message::ProfileVersion(announce)) = message {
+                peer.announced_profile_version = Some(announce.profile_version);
+            }
+        }
+
+        if self.should_log(message_type) {
+            self.push_event(CommsEvent {
+                id: 0,
+                time_ms: now_ms,
+                adapter: adapter.to_string(),
+                direction,
+                message_type,
+                address,
+                bytes,
+            });
+        }
+    }
+
+    pub fn record_peer_connection(
+        &mut self,
+        now_ms: u64,
+        adapter: &str,
+        address: H160,
+        connected: bool,
+    ) {
+        let adapter_stats = self.adapters.entry(adapter.to_string()).or_default();
+        let peer = get_peer(adapter_stats, address, now_ms);
+        peer.connected = connected;
+        peer.last_seen_ms = now_ms;
+
+        self.push_event(CommsEvent {
+            id: 0,
+            time_ms: now_ms,
+            adapter: adapter.to_string(),
+            direction: Direction::Received,
+            message_type: if connected { "peer_join" } else { "peer_leave" },
+            address: Some(address),
+            bytes: 0,
+        });
+    }
+
+    pub fn record_profile_version(
+        &mut self,
+        now_ms: u64,
+        adapter: &str,
+        address: H160,
+        version: u32,
+    ) {
+        let adapter_stats = self.adapters.entry(adapter.to_string()).or_default();
+        get_peer(adapter_stats, address, now_ms).profile_version = Some(version);
+    }
+
+    pub fn set_event_sample_rate(&mut self, event_sample_rate: u32) {
+        self.event_sample_rate = event_sample_rate.max(1);
+    }
+
+    pub fn adapters(&self) -> &HashMap<String, AdapterStats> {
+        &self.adapters
+    }
+
+    pub fn events_after(&self, id: u64) -> impl Iterator<Item = &CommsEvent> {
+        self.events.iter().filter(move |event| event.id > id)
+    }
+
+    pub fn clear(&mut self) {
+        self.adapters.clear();
+        self.events.clear();
+        self.sample_counters.clear();
+    }
+
+    fn should_log(&mut self, message_type: &'static str) -> bool {
+        if !matches!(message_type, "position" | "voice") {
+            return true;
+        }
+
+        let counter = self.sample_counters.entry(message_type).or_default();
+        let log = *counter % self.event_sample_rate == 0;
+        *counter = counter.wrapping_add(1);
+        log
+    }
+
+    fn push_event(&mut self, mut event: CommsEvent) {
+        self.last_event_id += 1;
+        event.id = self.last_event_id;
+        self.events.push_back(event);
+        while self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+    }
+}
+
+fn get_peer(adapter_stats: &mut AdapterStats, address: H160, now_ms: u64) -> &mut PeerStats {
+    adapter_stats
+        .peers
+        .entry(address)
+        .or_insert_with(|| PeerStats {
+            connected: true,
+            first_seen_ms: now_ms,
+            ..Default::default()
+        })
+}
+
+fn get_message_type(message: Option<&rfc4::packet::Message>) -> &'static str {
+    match message {
+        Some(rfc4::packet::Message::Position(_)) => "position",
+        Some(rfc4::packet::Message::ProfileVersion(_)) => "profile_version",
+        Some(rfc4::packet::Message::ProfileRequest(_)) => "profile_request",
+        Some(rfc4::packet::Message::ProfileResponse(_)) => "profile_response",
+        Some(rfc4::packet::Message::Chat(_)) => "chat",
+        Some(rfc4::packet::Message::Scene(_)) => "scene",
+        Some(rfc4::packet::Message::Voice(_)) => "voice",
+        Some(rfc4::packet::Message::PlayerEmote(_)) => "player_emote",
+        #[allow(unreachable_patterns)]
+        Some(_) => "other",
+        None => "empty",
+    }
+}
+
+fn is_enabled() -> bool {
+    COMMS_INSPECTOR_ENABLE.load(Ordering::Relaxed)
+}
+
+fn now_ms() -> u64 {
+    COMMS_INSPECTOR_START.elapsed().as_millis() as u64
+}
+
+// The adapters report their traffic with these functions, they do nothing
+//  while the inspector is not active
+pub fn inspect_sent(adapter: &str, message: Option<&rfc4::packet::Message>, bytes: usize) {
+    if is_enabled() {
+        COMMS_STATS.lock().unwrap().record_packet(
+            now_ms(),
+            adapter,
+            Direction::Sent,
+            message,
+            None,
+            bytes,
+        );
+    }
+}
+
+pub fn inspect_received(
+    adapter: &str,
+    address: H160,
+    message: Option<&rfc4::packet::Message>,
+    bytes: usize,
+) {
+    if is_enabled() {
+        COMMS_STATS.lock().unwrap().record_packet(
+            now_ms(),
+            adapter,
+            Direction::Received,
+            message,
+            Some(address),
+            bytes,
+        );
+    }
+}
+
+pub fn inspect_peer_connection(adapter: &str, address: H160, connected: bool) {
+    if is_enabled() {
+        COMMS_STATS
+            .lock()
+            .unwrap()
+            .record_peer_connection(now_ms(), adapter, address, connected);
+    }
+}
+
+pub fn inspect_profile_version(adapter: &str, address: H160, version: u32) {
+    if is_enabled() {
+        COMMS_STATS
+            .lock()
+            .unwrap()
+            .record_profile_version(now_ms(), adapter, address, version);
+    }
+}
+
+fn counters_to_dictionary(counters: &HashMap<&'static str, TrafficCounter>) -> Dictionary {
+    let mut dict = Dictionary::new();
+    for (message_type, counter) in counters {
+        let mut counter_dict = Dictionary::new();
+        counter_dict.set("packets", counter.packets as i64);
+        counter_dict.set("bytes", counter.bytes as i64);
+        dict.set(*message_type, counter_dict);
+    }
+    dict
+}
+
+#[derive(GodotClass)]
+#[class(base=RefCounted)]
+pub struct CommsInspector {}
+
+#[godot_api]
+impl IRefCounted for CommsInspector {
+    fn init(_base: Base<RefCounted>) -> Self {
+        Self {}
+    }
+}
+
+#[godot_api]
+impl CommsInspector {
+    #[func]
+    fn set_is_active(&mut self, value: bool) {
+        COMMS_INSPECTOR_ENABLE.store(value, Ordering::Relaxed);
+    }
+
+    #[func]
+    fn is_active(&self) -> bool {
+        is_enabled()
+    }
+
+    // The times of the stats and events are relative to this one
+    #[func]
+    fn get_now_ms(&self) -> i64 {
+        now_ms() as i64
+    }
+
+    #[func]
+    fn set_event_sample_rate(&mut self, event_sample_rate: u32) {
+        COMMS_STATS
+            .lock()
+            .unwrap()
+            .set_event_sample_rate(event_sample_rate);
+    }
+
+    // { adapter: { "sent": { message_type: { "packets", "bytes" } }, "received": ... } }
+    #[func]
+    fn get_traffic(&self) -> Dictionary {
+        let stats = COMMS_STATS.lock().unwrap();
+        let mut dict = Dictionary::new();
+        for (adapter, adapter_stats) in stats.adapters() {
+            let mut adapter_dict = Dictionary::new();
+            adapter_dict.set("sent", counters_to_dictionary(&adapter_stats.sent));
+            adapter_dict.set("received", counters_to_dictionary(&adapter_stats.received));
+            dict.set(adapter.clone(), adapter_dict);
+        }
+        dict
+    }
+
+    #[func]
+    fn get_peers(&self, adapter: GString) -> Array<Dictionary> {
+        let stats = COMMS_STATS.lock().unwrap();
+        let mut peers = Array::new();
+        let Some(adapter_stats) = stats.adapters().get(&adapter.to_string()) else {
+            return peers;
+        };
+
+        for (address, peer) in adapter_stats.peers.iter() {
+            let mut dict = Dictionary::new();
+            dict.set("address", format!("{:#x}", address));
+            dict.set("connected", peer.connected);
+            dict.set("first_seen_ms", peer.first_seen_ms as i64);
+            dict.set("last_seen_ms", peer.last_seen_ms as i64);
+            dict.set("packets_received", peer.packets_received as i64);
+            dict.set(
+                "announced_profile_version",
+                peer.announced_profile_version.map_or(-1, |v| v as i64),
+            );
+            dict.set(
+                "profile_version",
+                peer.profile_version.map_or(-1, |v| v as i64),
+            );
+            peers.push(dict);
+        }
+        peers
+    }
+
+    // Events with an id greater than `after_id`, the log keeps the last 500
+    #[func]
+    fn get_events(&self, after_id: i64) -> Array<Dictionary> {
+        let stats = COMMS_STATS.lock().unwrap();
+        let mut events = Array::new();
+        for event in stats.events_after(after_id.max(0) as u64) {
+            let mut dict = Dictionary::new();
+            dict.set("id", event.id as i64);
+            dict.set("time_ms", event.time_ms as i64);
+            dict.set("adapter", event.adapter.clone());
+            dict.set("direction", event.direction.as_str());
+            dict.set("message_type", event.message_type);
+            dict.set(
+                "address",
+                event
+                    .address
+                    .map(|address| format!("{:#x}", address))
+                    .unwrap_or_default(),
+            );
+            dict.set("bytes", event.bytes as i64);
+            events.push(dict);
+        }
+        events
+    }
+
+    #[func]
+    fn clear(&mut self) {
+        COMMS_STATS.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position() -> rfc4::packet::Message {
+        rfc4::packet::Message::Position(rfc4::Position::default())
+    }
+
+    #[test]
+    fn test_traffic_counters() {
+        let mut stats = CommsStats::default();
+        let address = H160::from_low_u64_be(1);
+        stats.record_packet(0, "ws-room", Direction::Sent, Some(&position()), None, 10);
+        stats.record_packet(1, "ws-room", Direction::Sent, Some(&position()), None, 20);
+        stats.record_packet(
+            2,
+            "ws-room",
+            Direction::Received,
+            Some(&position()),
+            Some(address),
+            30,
+        );
+
+        let adapter_stats = &stats.adapters()["ws-room"];
+        assert_eq!(
+            adapter_stats.sent["position"],
+            TrafficCounter {
+                packets: 2,
+                bytes: 30
+            }
+        );
+        assert_eq!(
+            adapter_stats.received["position"],
+            TrafficCounter {
+                packets: 1,
+                bytes: 30
+            }
+        );
+        assert_eq!(adapter_stats.peers[&address].last_seen_ms, 2);
+    }
+
+    #[test]
+    fn test_peer_profile_versions() {
+        let mut stats = CommsStats::default();
+        let address = H160::from_low_u64_be(1);
+        stats.record_peer_connection(0, "livekit", address, true);
+        stats.record_packet(
+            5,
+            "livekit",
+            Direction::Received,
+            Some(&rfc4::packet::Message::ProfileVersion(
+                rfc4::AnnounceProfileVersion { profile_version: 3 },
+            )),
+            Some(address),
+            4,
+        );
+        stats.record_profile_version(6, "livekit", address, 2);
+        stats.record_peer_connection(10, "livekit", address, false);
+
+        let peer = &stats.adapters()["livekit"].peers[&address];
+        assert!(!peer.connected);
+        assert_eq!(peer.first_seen_ms, 0);
+        assert_eq!(peer.last_seen_ms, 10);
+        assert_eq!(peer.packets_received, 1);
+        assert_eq!(peer.announced_profile_version, Some(3));
+        assert_eq!(peer.profile_version, Some(2));
+    }
+
+    #[test]
+    fn test_event_sampling() {
+        let mut stats = CommsStats::default();
+        stats.set_event_sample_rate(5);
+        for _ in 0..10 {
+            stats.record_packet(0, "ws-room", Direction::Sent, Some(&position()), None, 1);
+        }
+        stats.record_packet(0, "ws-room", Direction::Sent, None, None, 1);
+
+        let events: Vec<_> = stats.events_after(0).collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2].message_type, "empty");
+        assert_eq!(stats.events_after(events[1].id).count(), 1);
+    }
+
+    #[test]
+    fn test_max_events() {
+        let mut stats = CommsStats::default();
+        for _ in 0..(MAX_EVENTS + 10) {
+            stats.record_packet(0, "ws-room", Direction::Sent, None, None, 1);
+        }
+        assert_eq!(stats.events_after(0).count(), MAX_EVENTS);
+        assert_eq!(stats.events_after(0).next().unwrap().id, 11);
+    }
+}
@@ -1 +1,2 @@
+pub mod comms_inspector;
 pub mod network_inspector;
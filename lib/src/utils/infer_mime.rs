@@ -80,3 +80,13 @@ pub fn is_ktx(buffer: &[u8]) -> bool {
     // Check if the buffer starts with the KTX signature
     buffer.starts_with(&signature)
 }
+
+pub fn is_ktx2(buffer: &[u8]) -> bool {
+    // KTX2 file signature
+    let signature: [u8; 12] = [
+        0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+    ];
+
+    // Check if the buffer starts with the KTX2 signature
+    buffer.starts_with(&signature)
+}
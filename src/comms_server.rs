@@ -0,0 +1,657 @@
+// A local ws-room (rfc5) server, a stand-in of the comms servers for the
+//  integration tests. The auth chain signatures are not verified, only that
+//  the chain belongs to the identified address and signs the challenge.
+// The explorers connect to it with the adapter `ws-room:ws://127.0.0.1:7667`
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::{SinkExt, Stream, StreamExt};
+use prost::Message as ProstMessage;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    Message,
+};
+
+use rfc5::{
+    ws_packet, WsChallengeRequired, WsKicked, WsPacket, WsPeerJoin, WsPeerLeave, WsPeerUpdate,
+    WsWelcome,
+};
+
+pub const DEFAULT_COMMS_SERVER_PORT: u16 = 7667;
+
+// Mirrors decentraland/kernel/comms/rfc5/ws_comms.proto, the rfc4 packets
+//  are relayed as they come so they are not needed here
+pub mod rfc5 {
+    use std::collections::HashMap;
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct WsWelcome {
+        #[prost(uint32, tag = "1")]
+        pub alias: u32,
+        #[prost(map = "uint32, string", tag = "2")]
+        pub peer_identities: HashMap<u32, String>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct WsPeerJoin {
+        #[prost(uint32, tag = "1")]
+        pub alias: u32,
+        #[prost(string, tag = "2")]
+        pub address: String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct WsPeerLeave {
+        #[prost(uint32, tag = "1")]
+        pub alias: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct WsPeerUpdate {
+        #[prost(uint32, tag = "1")]
+        pub from_alias: u32,
+        #[prost(bytes = "vec", tag = "2")]
+        pub body: Vec<u8>,
+        #[prost(bool, tag = "3")]
+        pub unreliable: bool,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct WsChallengeRequired {
+        #[prost(string, tag = "1")]
+        pub challenge_to_sign: String,
+        #[prost(bool, tag = "2")]
+        pub already_connected: bool,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct WsSignedChallenge {
+        #[prost(string, tag = "1")]
+        pub auth_chain_json: String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct WsIdentification {
+        #[prost(string, tag = "1")]
+        pub address: String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct WsKicked {
+        #[prost(string, tag = "1")]
+        pub reason: String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct WsPacket {
+        #[prost(oneof = "ws_packet::Message", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
+        pub message: Option<ws_packet::Message>,
+    }
+
+    pub mod ws_packet {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum Message {
+            #[prost(message, tag = "1")]
+            WelcomeMessage(super::WsWelcome),
+            #[prost(message, tag = "2")]
+            PeerJoinMessage(super::WsPeerJoin),
+            #[prost(message, tag = "3")]
+            PeerUpdateMessage(super::WsPeerUpdate),
+            #[prost(message, tag = "4")]
+            ChallengeMessage(super::WsChallengeRequired),
+            #[prost(message, tag = "5")]
+            PeerIdentification(super::WsIdentification),
+            #[prost(message, tag = "6")]
+            SignedChallengeForServer(super::WsSignedChallenge),
+            #[prost(message, tag = "7")]
+            PeerLeaveMessage(super::WsPeerLeave),
+            #[prost(message, tag = "8")]
+            PeerKicked(super::WsKicked),
+        }
+    }
+}
+
+enum Outgoing {
+    Packet(WsPacket),
+    Close,
+}
+
+struct ConnectedPeer {
+    address: String,
+    sender: mpsc::UnboundedSender<Outgoing>,
+}
+
+impl ConnectedPeer {
+    fn send(&self, message: ws_packet::Message) {
+        let _ = self.sender.send(Outgoing::Packet(WsPacket {
+            message: Some(message),
+        }));
+    }
+
+    fn kick(&self, reason: &str) {
+        self.send(ws_packet::Message::PeerKicked(WsKicked {
+            reason: reason.to_string(),
+        }));
+        let _ = self.sender.send(Outgoing::Close);
+    }
+}
+
+#[derive(Default)]
+struct Room {
+    last_alias: u32,
+    peers: HashMap<u32, ConnectedPeer>,
+}
+
+impl Room {
+    fn is_connected(&self, address: &str) -> bool {
+        self.peers.values().any(|peer| peer.address == address)
+    }
+
+    // Returns the alias of the new peer and the identities of the others
+    fn join(
+        &mut self,
+        address: String,
+        sender: mpsc::UnboundedSender<Outgoing>,
+    ) -> (u32, HashMap<u32, String>) {
+        // the same address can only be connected once, the older connection is kicked
+        let previous_aliases: Vec<u32> = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| peer.address == address)
+            .map(|(alias, _)| *alias)
+            .collect();
+        for alias in previous_aliases {
+            if let Some(peer) = self.peers.remove(&alias) {
+                peer.kick("connected from another session");
+            }
+            self.broadcast(ws_packet::Message::PeerLeaveMessage(WsPeerLeave { alias }));
+        }
+
+        self.last_alias += 1;
+        let alias = self.last_alias;
+
+        let peer_identities = self
+            .peers
+            .iter()
+            .map(|(alias, peer)| (*alias, peer.address.clone()))
+            .collect();
+
+        self.broadcast(ws_packet::Message::PeerJoinMessage(WsPeerJoin {
+            alias,
+            address: address.clone(),
+        }));
+        self.peers.insert(alias, ConnectedPeer { address, sender });
+
+        (alias, peer_identities)
+    }
+
+    fn leave(&mut self, alias: u32) {
+        if self.peers.remove(&alias).is_some() {
+            self.broadcast(ws_packet::Message::PeerLeaveMessage(WsPeerLeave { alias }));
+        }
+    }
+
+    fn relay(&self, from_alias: u32, update: WsPeerUpdate) {
+        let message = ws_packet::Message::PeerUpdateMessage(WsPeerUpdate {
+            // the alias is not taken from the client
+            from_alias,
+            ..update
+        });
+        for (_, peer) in self.peers.iter().filter(|(alias, _)| **alias != from_alias) {
+            peer.send(message.clone());
+        }
+    }
+
+    fn broadcast(&self, message: ws_packet::Message) {
+        for peer in self.peers.values() {
+            peer.send(message.clone());
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CommsServer {
+    local_addr: SocketAddr,
+    room: Arc<Mutex<Room>>,
+}
+
+impl CommsServer {
+    // Binds the server and accepts the connections in the current tokio runtime,
+    //  use the port 0 to get a free one
+    pub async fn start(port: u16) -> Result<Self, anyhow::Error> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+        let server = Self {
+            local_addr: listener.local_addr()?,
+            room: Default::default(),
+        };
+
+        let room = server.room.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, peer_addr)) = listener.accept().await else {
+                    continue;
+                };
+                let room = room.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(room, stream).await {
+                        println!("comms-server > connection {peer_addr} closed: {e}");
+                    }
+                });
+            }
+        });
+
+        Ok(server)
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.local_addr)
+    }
+
+    pub fn peers(&self) -> HashMap<u32, String> {
+        self.room
+            .lock()
+            .unwrap()
+            .peers
+            .iter()
+            .map(|(alias, peer)| (*alias, peer.address.clone()))
+            .collect()
+    }
+
+    // Returns false if the address is not connected
+    pub fn kick(&self, address: &str, reason: &str) -> bool {
+        let address = address.to_lowercase();
+        let mut room = self.room.lock().unwrap();
+        let Some(alias) = room
+            .peers
+            .iter()
+            .find(|(_, peer)| peer.address == address)
+            .map(|(alias, _)| *alias)
+        else {
+            return false;
+        };
+
+        if let Some(peer) = room.peers.get(&alias) {
+            peer.kick(reason);
+        }
+        room.leave(alias);
+        true
+    }
+}
+
+async fn handle_connection(room: Arc<Mutex<Room>>, stream: TcpStream) -> anyhow::Result<()> {
+    #[allow(clippy::result_large_err)]
+    let accept_rfc5 =
+        |request: &Request, mut response: Response| -> Result<Response, ErrorResponse> {
+            let requested_rfc5 = request
+                .headers()
+                .get_all("Sec-WebSocket-Protocol")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .any(|protocol| protocol.trim() == "rfc5");
+            if requested_rfc5 {
+                response
+                    .headers_mut()
+                    .insert("Sec-WebSocket-Protocol", "rfc5".parse().unwrap());
+            }
+            Ok(response)
+        };
+
+    let ws_stream = tokio_tungstenite::accept_hdr_async(stream, accept_rfc5).await?;
+    let (mut write, mut read) = ws_stream.split();
+
+    let address = match read_packet(&mut read).await? {
+        ws_packet::Message::PeerIdentification(identification) => {
+            identification.address.to_lowercase()
+        }
+        message => anyhow::bail!("expected identification, received {message:?}"),
+    };
+
+    let challenge_to_sign = format!(
+        "dcl-{:x}",
+        SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos()
+    );
+    let already_connected = room.lock().unwrap().is_connected(&address);
+    write
+        .send(encode(ws_packet::Message::ChallengeMessage(
+            WsChallengeRequired {
+                challenge_to_sign: challenge_to_sign.clone(),
+                already_connected,
+            },
+        )))
+        .await?;
+
+    match read_packet(&mut read).await? {
+        ws_packet::Message::SignedChallengeForServer(signed_challenge) => {
+            verify_auth_chain(
+                &signed_challenge.auth_chain_json,
+                &address,
+                &challenge_to_sign,
+            )?;
+        }
+        message => anyhow::bail!("expected signed challenge, received {message:?}"),
+    }
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let (alias, peer_identities) = room.lock().unwrap().join(address.clone(), sender);
+    println!("comms-server > {address} joined with alias {alias}");
+
+    write
+        .send(encode(ws_packet::Message::WelcomeMessage(WsWelcome {
+            alias,
+            peer_identities,
+        })))
+        .await?;
+
+    let result = loop {
+        tokio::select! {
+            outgoing = receiver.recv() => match outgoing {
+                Some(Outgoing::Packet(packet)) => {
+                    if let Err(e) = write.send(Message::Binary(packet.encode_to_vec())).await {
+                        break Err(e.into());
+                    }
+                }
+                Some(Outgoing::Close) | None => break Ok(()),
+            },
+            incoming = read.next() => match incoming {
+                Some(Ok(Message::Binary(data))) => match WsPacket::decode(data.as_slice()) {
+                    Ok(WsPacket {
+                        message: Some(ws_packet::Message::PeerUpdateMessage(update)),
+                    }) => room.lock().unwrap().relay(alias, update),
+                    Ok(packet) => println!("comms-server > unexpected packet from {alias}: {packet:?}"),
+                    Err(e) => println!("comms-server > invalid packet from {alias}: {e}"),
+                },
+                Some(Ok(Message::Close(_))) | None => break Ok(()),
+                Some(Err(e)) => break Err(e.into()),
+                Some(Ok(_)) => {}
+            },
+        }
+    };
+
+    room.lock().unwrap().leave(alias);
+    println!("comms-server > {address} with alias {alias} left");
+    let _ = write.close().await;
+    result
+}
+
+async fn read_packet<S>(read: &mut S) -> anyhow::Result<ws_packet::Message>
+where
+    S: Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    loop {
+        match read.next().await {
+            Some(Ok(Message::Binary(data))) => {
+                if let Some(message) = WsPacket::decode(data.as_slice())?.message {
+                    return Ok(message);
+                }
+            }
+            Some(Ok(Message::Close(_))) | None => anyhow::bail!("closed during the handshake"),
+            Some(Err(e)) => return Err(e.into()),
+            Some(Ok(_)) => {}
+        }
+    }
+}
+
+fn encode(message: ws_packet::Message) -> Message {
+    Message::Binary(
+        WsPacket {
+            message: Some(message),
+        }
+        .encode_to_vec(),
+    )
+}
+
+// The first link is the signer and the last one signs the challenge
+fn verify_auth_chain(auth_chain_json: &str, address: &str, challenge: &str) -> anyhow::Result<()> {
+    let chain: Vec<serde_json::Value> = serde_json::from_str(auth_chain_json)?;
+    let payload = |link: Option<&serde_json::Value>| {
+        link.and_then(|link| link.get("payload"))
+            .and_then(|payload| payload.as_str())
+            .map(str::to_string)
+    };
+
+    let signer = payload(chain.first()).unwrap_or_default();
+    if !signer.eq_ignore_ascii_case(address) {
+        anyhow::bail!("the auth chain signer {signer} is not {address}");
+    }
+
+    if payload(chain.last()).as_deref() != Some(challenge) {
+        anyhow::bail!("the auth chain doesn't sign the challenge");
+    }
+
+    Ok(())
+}
+
+pub fn run_comms_server(port: u16) -> Result<(), anyhow::Error> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let server = CommsServer::start(port).await?;
+        println!("comms-server > listening at {}", server.url());
+        println!("comms-server > commands: `peers`, `kick <address> [reason]`, `quit`");
+
+        let mut lines = std::io::stdin().lines();
+        loop {
+            let Some(line) = tokio::task::block_in_place(|| lines.next()) else {
+                break;
+            };
+            let line = line?;
+            let mut args = line.split_whitespace();
+            match args.next() {
+                Some("peers") => {
+                    for (alias, address) in server.peers() {
+                        println!("{alias}: {address}");
+                    }
+                }
+                Some("kick") => {
+                    let Some(address) = args.next() else {
+                        println!("usage: kick <address> [reason]");
+                        continue;
+                    };
+                    let reason = args.collect::<Vec<_>>().join(" ");
+                    if !server.kick(address, &reason) {
+                        println!("{address} is not connected");
+                    }
+                }
+                Some("quit") => break,
+                Some(command) => println!("unknown command {command}"),
+                None => {}
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+    use super::{rfc5::*, *};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    async fn send(client: &mut Client, message: ws_packet::Message) {
+        client.send(encode(message)).await.unwrap();
+    }
+
+    async fn receive(client: &mut Client) -> ws_packet::Message {
+        tokio::time::timeout(Duration::from_secs(5), read_packet(client))
+            .await
+            .expect("timeout waiting for a packet")
+            .unwrap()
+    }
+
+    async fn connect(
+        server: &CommsServer,
+        address: &str,
+    ) -> (Client, WsChallengeRequired, WsWelcome) {
+        let (mut client, _) = connect_async(server.url()).await.unwrap();
+        send(
+            &mut client,
+            ws_packet::Message::PeerIdentification(WsIdentification {
+                address: address.to_string(),
+            }),
+        )
+        .await;
+
+        let ws_packet::Message::ChallengeMessage(challenge) = receive(&mut client).await else {
+            panic!("expected challenge");
+        };
+        assert!(challenge.challenge_to_sign.starts_with("dcl-"));
+
+        let auth_chain_json = serde_json::json!([
+            { "type": "SIGNER", "payload": address, "signature": "" },
+            { "type": "ECDSA_SIGNED_ENTITY", "payload": challenge.challenge_to_sign, "signature": "0x00" },
+        ])
+        .to_string();
+        send(
+            &mut client,
+            ws_packet::Message::SignedChallengeForServer(WsSignedChallenge { auth_chain_json }),
+        )
+        .await;
+
+        let ws_packet::Message::WelcomeMessage(welcome) = receive(&mut client).await else {
+            panic!("expected welcome");
+        };
+        (client, challenge, welcome)
+    }
+
+    const ADDRESS_A: &str = "0x00000000000000000000000000000000000000aa";
+    const ADDRESS_B: &str = "0x00000000000000000000000000000000000000bb";
+
+    #[tokio::test]
+    async fn test_handshake_and_relay() {
+        let server = CommsServer::start(0).await.unwrap();
+
+        let (mut client_a, _, welcome_a) = connect(&server, ADDRESS_A).await;
+        assert!(welcome_a.peer_identities.is_empty());
+
+        let (mut client_b, _, welcome_b) = connect(&server, ADDRESS_B).await;
+        assert_eq!(
+            welcome_b.peer_identities,
+            HashMap::from([(welcome_a.alias, ADDRESS_A.to_string())])
+        );
+
+        assert_eq!(
+            receive(&mut client_a).await,
+            ws_packet::Message::PeerJoinMessage(WsPeerJoin {
+                alias: welcome_b.alias,
+                address: ADDRESS_B.to_string(),
+            })
+        );
+
+        // the alias of the sender is set by the server
+        send(
+            &mut client_b,
+            ws_packet::Message::PeerUpdateMessage(WsPeerUpdate {
+                from_alias: 1234,
+                body: vec![1, 2, 3],
+                unreliable: true,
+            }),
+        )
+        .await;
+        assert_eq!(
+            receive(&mut client_a).await,
+            ws_packet::Message::PeerUpdateMessage(WsPeerUpdate {
+                from_alias: welcome_b.alias,
+                body: vec![1, 2, 3],
+                unreliable: true,
+            })
+        );
+
+        client_b.close(None).await.unwrap();
+        assert_eq!(
+            receive(&mut client_a).await,
+            ws_packet::Message::PeerLeaveMessage(WsPeerLeave {
+                alias: welcome_b.alias
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_signer() {
+        let server = CommsServer::start(0).await.unwrap();
+        let (mut client, _) = connect_async(server.url()).await.unwrap();
+        send(
+            &mut client,
+            ws_packet::Message::PeerIdentification(WsIdentification {
+                address: ADDRESS_A.to_string(),
+            }),
+        )
+        .await;
+        let ws_packet::Message::ChallengeMessage(challenge) = receive(&mut client).await else {
+            panic!("expected challenge");
+        };
+
+        let auth_chain_json = serde_json::json!([
+            { "type": "SIGNER", "payload": ADDRESS_B, "signature": "" },
+            { "type": "ECDSA_SIGNED_ENTITY", "payload": challenge.challenge_to_sign, "signature": "0x00" },
+        ])
+        .to_string();
+        send(
+            &mut client,
+            ws_packet::Message::SignedChallengeForServer(WsSignedChallenge { auth_chain_json }),
+        )
+        .await;
+
+        let closed = tokio::time::timeout(Duration::from_secs(5), read_packet(&mut client))
+            .await
+            .unwrap();
+        assert!(closed.is_err());
+        assert!(server.peers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_kick() {
+        let server = CommsServer::start(0).await.unwrap();
+        let (mut client_a, _, _) = connect(&server, ADDRESS_A).await;
+        let (mut client_b, _, welcome_b) = connect(&server, ADDRESS_B).await;
+        receive(&mut client_a).await;
+
+        assert!(server.kick(ADDRESS_B, "testing"));
+        assert!(!server.kick(ADDRESS_B, "testing"));
+        assert_eq!(
+            receive(&mut client_b).await,
+            ws_packet::Message::PeerKicked(WsKicked {
+                reason: "testing".to_string()
+            })
+        );
+        assert_eq!(
+            receive(&mut client_a).await,
+            ws_packet::Message::PeerLeaveMessage(WsPeerLeave {
+                alias: welcome_b.alias
+            })
+        );
+        assert_eq!(server.peers().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_already_connected() {
+        let server = CommsServer::start(0).await.unwrap();
+        let (mut first_session, first_challenge, _) = connect(&server, ADDRESS_A).await;
+        assert!(!first_challenge.already_connected);
+
+        let (_second_session, second_challenge, welcome) = connect(&server, ADDRESS_A).await;
+        assert!(second_challenge.already_connected);
+        assert!(welcome.peer_identities.is_empty());
+
+        assert!(matches!(
+            receive(&mut first_session).await,
+            ws_packet::Message::PeerKicked(_)
+        ));
+        assert_eq!(
+            server.peers(),
+            HashMap::from([(welcome.alias, ADDRESS_A.to_string())])
+        );
+    }
+}
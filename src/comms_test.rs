@@ -0,0 +1,87 @@
+// Runs two headless explorers against the local comms server, each one checks
+//  that the positions, chats, profiles and scene messages of the other peer
+//  round-trip (see godot/src/test/comms_test.gd)
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    thread,
+};
+
+use crate::{comms_server::CommsServer, consts::GODOT_PROJECT_FOLDER, path::get_godot_path, run};
+
+const COMMS_TEST_ROLES: [&str; 2] = ["a", "b"];
+
+pub fn run_comms_test(with_build_envs: Option<HashMap<String, String>>) -> anyhow::Result<()> {
+    // builds and copies the library
+    run::run(
+        false,
+        false,
+        false,
+        true,
+        false,
+        false,
+        vec![],
+        vec![],
+        with_build_envs,
+    )?;
+
+    // the server keeps running in the runtime threads while the explorers run
+    let runtime = tokio::runtime::Runtime::new()?;
+    let server = runtime.block_on(CommsServer::start(0))?;
+    let adapter = format!("ws-room:{}", server.url());
+    println!("comms-test > server listening at {}", server.url());
+
+    let program = get_godot_path();
+    let explorers = COMMS_TEST_ROLES
+        .iter()
+        .map(|role| {
+            let mut child = Command::new(program.as_str())
+                .args([
+                    "--path",
+                    GODOT_PROJECT_FOLDER,
+                    "--headless",
+                    "--comms-test",
+                    role,
+                    "--comms-adapter",
+                    adapter.as_str(),
+                ])
+                .stdout(Stdio::piped())
+                .spawn()?;
+
+            let stdout = child.stdout.take().expect("stdout is piped");
+            let role = role.to_string();
+            let reader = thread::spawn(move || {
+                let mut passed = false;
+                for line in BufReader::new(stdout).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    println!("[{role}] {line}");
+                    if line.contains("comms-test-exiting with code ") {
+                        passed = line.contains("comms-test-exiting with code 0");
+                    }
+                }
+                passed
+            });
+            Ok((child, reader))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut failed = Vec::new();
+    for ((mut child, reader), role) in explorers.into_iter().zip(COMMS_TEST_ROLES) {
+        let status = child.wait()?;
+        let passed = reader.join().unwrap_or(false);
+        if !status.success() || !passed {
+            failed.push(role);
+        }
+    }
+
+    if failed.is_empty() {
+        println!("comms-test > passed");
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("comms-test failed for peers {:?}", failed))
+    }
+}
@@ -9,6 +9,8 @@ use xtaskops::ops::{clean_files, cmd, confirm, remove_dir};
 
 use crate::consts::RUST_LIB_PROJECT_FOLDER;
 
+mod comms_server;
+mod comms_test;
 mod consts;
 mod copy_files;
 mod download_file;
@@ -92,6 +94,16 @@ fn main() -> Result<(), anyhow::Error> {
                         .required(true),
                 ),
         )
+        .subcommand(
+            Command::new("comms-server").arg(
+                Arg::new("port")
+                    .short('p')
+                    .long("port")
+                    .help("port to listen, 7667 by default")
+                    .takes_value(true),
+            ),
+        )
+        .subcommand(Command::new("comms-test"))
         .subcommand(Command::new("export"))
         .subcommand(Command::new("import-assets"))
         .subcommand(
@@ -201,6 +213,14 @@ fn main() -> Result<(), anyhow::Error> {
                 None,
             )
         }
+        ("comms-server", sm) => {
+            let port = match sm.value_of("port") {
+                Some(port) => port.parse().context("invalid port")?,
+                None => comms_server::DEFAULT_COMMS_SERVER_PORT,
+            };
+            comms_server::run_comms_server(port)
+        }
+        ("comms-test", _) => comms_test::run_comms_test(None),
         ("export", _m) => export::export(),
         ("import-assets", _m) => {
             let status = import_assets();
@@ -319,6 +339,9 @@ pub fn coverage_with_itest(devmode: bool) -> Result<(), anyhow::Error> {
         Some(build_envs.clone()),
     )?;
 
+    println!("=== running comms test ===");
+    comms_test::run_comms_test(Some(build_envs.clone()))?;
+
     let err = glob::glob("./godot/*.profraw")?
         .filter_map(|entry| entry.ok())
         .map(|entry| {